@@ -1,106 +1,130 @@
-use regex::Regex;
-use serde::Deserialize;
-use std::sync::Arc;
-use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use crate::filtering::FilterDecision;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
 
+pub mod filter;
 pub mod loader;
+pub use filter::{FilterSet, RequestContext, ResourceKind};
 pub use loader::BlacklistLoader;
-use crate::error::{AppError, Result};
 
-static REGEX_CACHE: Lazy<Mutex<HashMap<String, Arc<Regex>>>> = Lazy::new(|| {
-    Mutex::new(HashMap::new())
-});
-
-#[derive(Debug, Deserialize)]
+/// URL blacklist compiled from one or more list files. The rules can be
+/// swapped while a crawl runs when their files change on disk.
+#[derive(Debug, Default)]
 pub struct Blacklist {
-    pub domains: Vec<String>,
-    pub urls: Vec<String>,
-    pub patterns: Vec<String>,
-    #[serde(skip)]
-    compiled_patterns: Option<Vec<Arc<Regex>>>,
+    sources: Vec<PathBuf>,
+    filters: RwLock<FilterSet>,
+    /// Modification times of `sources` when they were last loaded
+    modified: Mutex<Vec<Option<SystemTime>>>,
 }
 
 impl Blacklist {
     /// Create a new empty blacklist
     pub fn new() -> Self {
-        Self {
-            domains: Vec::new(),
-            urls: Vec::new(),
-            patterns: Vec::new(),
-            compiled_patterns: None,
-        }
+        Self::default()
     }
-    
+
     /// Create a new blacklist with the given data
     #[allow(dead_code)]
     pub fn with_data(domains: Vec<String>, urls: Vec<String>, patterns: Vec<String>) -> Self {
-        Self {
-            domains,
-            urls,
-            patterns,
-            compiled_patterns: None,
+        let mut filters = FilterSet::default();
+        domains.iter().for_each(|d| filters.add_domain(d));
+        urls.iter().for_each(|u| filters.add_exact(u));
+        for pattern in &patterns {
+            if let Err(e) = filters.add_regex(pattern) {
+                log::warn!("Invalid regex pattern '{}': {}", pattern, e);
+            }
         }
+        Self { filters: RwLock::new(filters), ..Self::default() }
     }
 
-    /// Pre-compile all regex patterns for better performance
-    pub fn with_compiled_patterns(mut self) -> Result<Self> {
-        let mut compiled = Vec::new();
-        for pattern in &self.patterns {
-            let regex = Regex::new(pattern)
-                .map_err(AppError::Regex)?;
-            compiled.push(Arc::new(regex));
-        }
-        self.compiled_patterns = Some(compiled);
-        Ok(self)
+    /// Blacklist backed by files, so it can be reloaded later
+    pub(crate) fn from_sources(sources: Vec<PathBuf>, filters: FilterSet, modified: Vec<Option<SystemTime>>) -> Self {
+        Self { sources, filters: RwLock::new(filters), modified: Mutex::new(modified) }
     }
 
-    fn is_exact_match(&self, url: &str) -> bool {
-        self.urls.iter().any(|u| u == url)
+    /// Files the rules were loaded from
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
     }
 
-    fn is_domain_match(&self, url: &str) -> bool {
-        if let Ok(parsed_url) = url::Url::parse(url) {
-            if let Some(host) = parsed_url.host_str() {
-                return self.domains.iter().any(|d| host.ends_with(d));
-            }
-        }
-        false
+    /// Number of compiled rules
+    pub fn rule_count(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Whether a URL referenced as `ctx.kind` from `ctx.source` is blocked, naming the blocking rule
+    pub fn check(&self, url: &str, ctx: &RequestContext) -> FilterDecision {
+        self.read().check(url, ctx)
     }
 
-    fn is_pattern_match(&self, url: &str) -> bool {
-        if let Some(ref compiled) = self.compiled_patterns {
-            return compiled.iter().any(|regex| regex.is_match(url));
+    /// Recompile the rules if any source file changed since the last load.
+    /// A file that fails to parse keeps the previous rules in place.
+    pub fn reload_if_changed(&self) -> bool {
+        let current = BlacklistLoader::modified_times(&self.sources);
+        let mut modified = self.modified.lock().unwrap_or_else(|e| e.into_inner());
+        if *modified == current {
+            return false;
         }
+        // Remember the new times even on failure so a broken file is reported once
+        *modified = current;
+        match BlacklistLoader::compile(&self.sources) {
+            Ok(filters) => {
+                log::info!("Reloaded blacklist: {} rules from {} file(s)", filters.len(), self.sources.len());
+                *self.filters.write().unwrap_or_else(|e| e.into_inner()) = filters;
+                true
+            }
+            Err(e) => {
+                log::warn!("Keeping previous blacklist rules: {}", e);
+                false
+            }
+        }
+    }
 
-        self.patterns.iter().any(|pattern| {
-            let regex = {
-                let mut cache = REGEX_CACHE.lock().unwrap_or_else(|e| {
-                    log::warn!("Mutex poisoned in regex cache, recovering");
-                    e.into_inner()
-                });
-
-                cache.get(pattern).cloned().unwrap_or_else(|| {
-                    match Regex::new(pattern) {
-                        Ok(r) => {
-                            let arc_regex = Arc::new(r);
-                            cache.insert(pattern.to_string(), arc_regex.clone());
-                            arc_regex
-                        }
-                        Err(e) => {
-                            log::warn!("Invalid regex pattern '{}': {}", pattern, e);
-                            // Return a dummy regex that won't match anything
-                            Arc::new(Regex::new(r"(\b\B)").unwrap())
-                        }
-                    }
-                })
-            };
-            regex.is_match(url)
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, FilterSet> {
+        self.filters.read().unwrap_or_else(|e| {
+            log::warn!("Blacklist lock poisoned, recovering");
+            e.into_inner()
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(blacklist: &Blacklist, url: &str) -> bool {
+        !blacklist.check(url, &RequestContext::default()).is_accepted()
+    }
+
+    #[test]
+    fn reloads_when_a_source_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.txt");
+        std::fs::write(&path, "||ads.test^\n").unwrap();
+
+        let blacklist = BlacklistLoader::load(std::slice::from_ref(&path)).unwrap();
+        assert!(blocked(&blacklist, "https://ads.test/"));
+        assert!(!blacklist.reload_if_changed());
+
+        std::fs::write(&path, "||ads.test^\n@@||ads.test/ok^\n||more.test^\n").unwrap();
+        // Forget the recorded time so the test does not depend on mtime resolution
+        blacklist.modified.lock().unwrap().clear();
+        assert!(blacklist.reload_if_changed());
+        assert!(!blocked(&blacklist, "https://ads.test/ok"));
+        assert!(blocked(&blacklist, "https://more.test/"));
+    }
 
-    pub fn is_blacklisted(&self, url: &str) -> bool {
-        self.is_exact_match(url) || self.is_domain_match(url) || self.is_pattern_match(url)
+    #[test]
+    fn with_data_matches_domains_urls_and_patterns() {
+        let blacklist = Blacklist::with_data(
+            vec!["t.co".into()],
+            vec!["https://a.test/exact".into()],
+            vec![r"\.pdf$".into()],
+        );
+        assert!(blocked(&blacklist, "https://sub.t.co/x"));
+        assert!(!blocked(&blacklist, "https://reddit.co/"));
+        assert!(blocked(&blacklist, "https://a.test/exact"));
+        assert!(blocked(&blacklist, "https://a.test/doc.pdf"));
     }
 }
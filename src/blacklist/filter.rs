@@ -0,0 +1,404 @@
+//! Blocking rules: plain domains, exact URLs, regexes and Adblock Plus filters
+
+use crate::filtering::FilterDecision;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use url::Url;
+
+/// What a URL was referenced as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// A page followed through a link, sitemap or redirect
+    #[default]
+    Document,
+    /// A page embedded with `<iframe>` or `<frame>`
+    Subdocument,
+    Image,
+    Script,
+    Stylesheet,
+    Font,
+    Media,
+    Other,
+}
+
+impl ResourceKind {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Best guess at what a URL is from its file extension
+    pub fn from_extension(url: &str) -> Self {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let extension = path.rsplit('/').next().and_then(|name| name.rsplit_once('.')).map(|(_, ext)| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "avif" | "ico" | "bmp") => Self::Image,
+            Some("css") => Self::Stylesheet,
+            Some("js" | "mjs") => Self::Script,
+            Some("woff" | "woff2" | "ttf" | "otf" | "eot") => Self::Font,
+            Some("mp4" | "webm" | "ogg" | "mp3" | "wav" | "avi" | "mov") => Self::Media,
+            _ => Self::Document,
+        }
+    }
+
+    /// Kind named by an Adblock Plus `$` option
+    fn from_option(name: &str) -> Option<Self> {
+        Some(match name {
+            "document" => Self::Document,
+            "subdocument" => Self::Subdocument,
+            "image" => Self::Image,
+            "script" => Self::Script,
+            "stylesheet" => Self::Stylesheet,
+            "font" => Self::Font,
+            "media" => Self::Media,
+            "other" | "object" => Self::Other,
+            _ => return None,
+        })
+    }
+}
+
+/// The URL being checked, with where and how it was referenced
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestContext<'a> {
+    /// Page the URL was found on; `$third-party` rules only apply when known
+    pub source: Option<&'a str>,
+    pub kind: ResourceKind,
+}
+
+#[derive(Debug)]
+enum Matcher {
+    /// Host equal to the domain or one of its subdomains
+    Domain(String),
+    Exact(String),
+    Pattern(Regex),
+}
+
+/// One compiled rule
+#[derive(Debug)]
+struct FilterRule {
+    /// The rule as written, reported when it matches
+    text: String,
+    matcher: Matcher,
+    /// Bit set of kinds the rule applies to; 0 means all
+    kinds: u8,
+    /// Bit set of kinds excluded with `~kind`
+    excluded_kinds: u8,
+    /// `Some(true)` for `$third-party`, `Some(false)` for `$~third-party`
+    third_party: Option<bool>,
+}
+
+impl FilterRule {
+    fn new(text: &str, matcher: Matcher) -> Self {
+        Self { text: text.to_string(), matcher, kinds: 0, excluded_kinds: 0, third_party: None }
+    }
+
+    fn matches(&self, url: &str, host: &str, ctx: &RequestContext) -> bool {
+        let bit = ctx.kind.bit();
+        if (self.kinds != 0 && self.kinds & bit == 0) || self.excluded_kinds & bit != 0 {
+            return false;
+        }
+        if let Some(third_party) = self.third_party {
+            match ctx.source.and_then(host_of) {
+                Some(source_host) if is_third_party(host, &source_host) == third_party => {}
+                _ => return false,
+            }
+        }
+        match &self.matcher {
+            Matcher::Domain(domain) => host_matches(host, domain),
+            Matcher::Exact(exact) => url == exact,
+            Matcher::Pattern(regex) => regex.is_match(url),
+        }
+    }
+}
+
+/// Compiled block and exception rules
+#[derive(Debug, Default)]
+pub struct FilterSet {
+    /// `||domain^` rules without options, looked up by host suffix, with the rule text
+    blocked_domains: HashMap<String, String>,
+    block: Vec<FilterRule>,
+    allow: Vec<FilterRule>,
+}
+
+impl FilterSet {
+    /// Block a domain and its subdomains
+    pub fn add_domain(&mut self, domain: &str) {
+        let text = domain.trim();
+        self.blocked_domains.insert(text.trim_start_matches("*.").to_ascii_lowercase(), text.to_string());
+    }
+
+    /// Block one URL exactly
+    pub fn add_exact(&mut self, url: &str) {
+        self.block.push(FilterRule::new(url, Matcher::Exact(url.to_string())));
+    }
+
+    /// Block URLs the regex matches anywhere
+    pub fn add_regex(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.block.push(FilterRule::new(pattern, Matcher::Pattern(Regex::new(pattern)?)));
+        Ok(())
+    }
+
+    /// Add one line of an Adblock Plus filter list. Comments, element hiding
+    /// rules and filters with options this crawler cannot evaluate are
+    /// skipped; returns whether the line became a rule.
+    pub fn add_filter(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('!') || line.starts_with('[') || is_element_hiding(line) {
+            return false;
+        }
+        let (exception, filter) = match line.strip_prefix("@@") {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let Some(mut rule) = parse_filter(filter) else {
+            log::debug!("Skipping unsupported filter: {}", line);
+            return false;
+        };
+        rule.text = line.to_string();
+        match (exception, rule) {
+            (false, rule) if is_plain_domain_rule(&rule) => {
+                if let Matcher::Domain(domain) = rule.matcher {
+                    self.blocked_domains.insert(domain, rule.text);
+                }
+            }
+            (false, rule) => self.block.push(rule),
+            (true, rule) => self.allow.push(rule),
+        }
+        true
+    }
+
+    /// Merge another set's rules into this one
+    pub fn extend(&mut self, other: FilterSet) {
+        self.blocked_domains.extend(other.blocked_domains);
+        self.block.extend(other.block);
+        self.allow.extend(other.allow);
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocked_domains.len() + self.block.len() + self.allow.len()
+    }
+
+    /// Whether a URL is blocked, naming the blocking rule. A matching
+    /// exception rule always wins.
+    pub fn check(&self, url: &str, ctx: &RequestContext) -> FilterDecision {
+        let host = host_of(url).unwrap_or_default();
+        let blocked_by = self
+            .blocked_domain(&host)
+            .map(str::to_string)
+            .or_else(|| self.block.iter().find(|rule| rule.matches(url, &host, ctx)).map(|rule| rule.text.clone()));
+        match blocked_by {
+            Some(_) if self.allow.iter().any(|rule| rule.matches(url, &host, ctx)) => FilterDecision::Accepted,
+            Some(rule) => FilterDecision::reject_matching("blacklist", rule),
+            None => FilterDecision::Accepted,
+        }
+    }
+
+    /// Check the host and each parent domain, so `t.co` blocks `x.t.co` but not `reddit.co`
+    fn blocked_domain(&self, host: &str) -> Option<&str> {
+        if self.blocked_domains.is_empty() || host.is_empty() {
+            return None;
+        }
+        let mut rest = host;
+        loop {
+            if let Some(text) = self.blocked_domains.get(rest) {
+                return Some(text);
+            }
+            rest = rest.split_once('.')?.1;
+        }
+    }
+}
+
+/// Parse a filter without its `@@` prefix
+fn parse_filter(filter: &str) -> Option<FilterRule> {
+    // `/.../` is a regex and may itself contain `$`
+    if filter.len() > 2 && filter.starts_with('/') && filter.ends_with('/') {
+        let regex = RegexBuilder::new(&filter[1..filter.len() - 1]).case_insensitive(true).build().ok()?;
+        return Some(FilterRule::new(filter, Matcher::Pattern(regex)));
+    }
+
+    let (pattern, options) = match filter.rsplit_once('$') {
+        Some((pattern, options)) if is_option_list(options) => (pattern, Some(options)),
+        _ => (filter, None),
+    };
+    let mut case_sensitive = false;
+    let mut kinds = 0;
+    let mut excluded_kinds = 0;
+    let mut third_party = None;
+    for option in options.into_iter().flat_map(|o| o.split(',')) {
+        let (negated, name) = match option.strip_prefix('~') {
+            Some(name) => (true, name),
+            None => (false, option),
+        };
+        match name {
+            "third-party" | "3p" => third_party = Some(!negated),
+            "first-party" | "1p" => third_party = Some(negated),
+            "match-case" => case_sensitive = true,
+            _ => {
+                let kind = ResourceKind::from_option(name)?;
+                if negated {
+                    excluded_kinds |= kind.bit();
+                } else {
+                    kinds |= kind.bit();
+                }
+            }
+        }
+    }
+
+    let matcher = match domain_anchor(pattern) {
+        Some(domain) => Matcher::Domain(domain),
+        None => Matcher::Pattern(
+            RegexBuilder::new(&filter_regex(pattern)).case_insensitive(!case_sensitive).build().ok()?,
+        ),
+    };
+    Some(FilterRule { text: filter.to_string(), matcher, kinds, excluded_kinds, third_party })
+}
+
+/// `||example.com^` (or `||example.com`) names a whole domain
+fn domain_anchor(pattern: &str) -> Option<String> {
+    let domain = pattern.strip_prefix("||")?;
+    let domain = domain.strip_suffix('^').unwrap_or(domain);
+    let is_host = !domain.is_empty()
+        && domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    is_host.then(|| domain.to_ascii_lowercase())
+}
+
+/// Translate an Adblock Plus pattern into a regex
+fn filter_regex(pattern: &str) -> String {
+    let mut regex = String::new();
+    let mut rest = pattern;
+    if let Some(after) = rest.strip_prefix("||") {
+        // Scheme, then the start of the host or of one of its labels
+        regex.push_str(r"^[a-z][a-z0-9+.\-]*://(?:[^/?#]*\.)?");
+        rest = after;
+    } else if let Some(after) = rest.strip_prefix('|') {
+        regex.push('^');
+        rest = after;
+    }
+    let (rest, anchored_end) = match rest.strip_suffix('|') {
+        Some(rest) => (rest, true),
+        None => (rest, false),
+    };
+    for c in rest.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            // Separator: anything but a letter, digit or `_-.%`, or the end of the URL
+            '^' => regex.push_str(r"(?:[^\w\-.%]|$)"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if anchored_end {
+        regex.push('$');
+    }
+    regex
+}
+
+fn is_option_list(options: &str) -> bool {
+    !options.is_empty()
+        && options.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '~' | ',' | '=' | '|' | '.' | '_'))
+}
+
+fn is_element_hiding(line: &str) -> bool {
+    ["##", "#@#", "#?#", "#$#"].iter().any(|marker| line.contains(marker))
+}
+
+fn is_plain_domain_rule(rule: &FilterRule) -> bool {
+    matches!(rule.matcher, Matcher::Domain(_)) && rule.kinds == 0 && rule.excluded_kinds == 0 && rule.third_party.is_none()
+}
+
+fn host_of(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_ascii_lowercase)
+}
+
+fn host_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Hosts are third-party to each other unless they share their last two
+/// labels, an approximation of the registrable domain without a suffix list
+fn is_third_party(host: &str, source_host: &str) -> bool {
+    fn site(host: &str) -> &str {
+        let mut dots = host.rmatch_indices('.');
+        dots.next();
+        match dots.next() {
+            Some((i, _)) => &host[i + 1..],
+            None => host,
+        }
+    }
+    site(host) != site(source_host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(lines: &[&str]) -> FilterSet {
+        let mut set = FilterSet::default();
+        for line in lines {
+            set.add_filter(line);
+        }
+        set
+    }
+
+    fn blocked(set: &FilterSet, url: &str, ctx: &RequestContext) -> bool {
+        !set.check(url, ctx).is_accepted()
+    }
+
+    fn doc() -> RequestContext<'static> {
+        RequestContext::default()
+    }
+
+    #[test]
+    fn domains_match_on_label_boundaries() {
+        let mut set = FilterSet::default();
+        set.add_domain("t.co");
+        assert!(blocked(&set, "https://t.co/abc", &doc()));
+        assert!(blocked(&set, "https://x.t.co/abc", &doc()));
+        assert!(!blocked(&set, "https://reddit.co/", &doc()));
+    }
+
+    #[test]
+    fn domain_anchor_and_separator() {
+        let set = filters(&["||ads.example.com^", "||tracker.test/pixel^"]);
+        assert!(blocked(&set, "https://ads.example.com/x", &doc()));
+        assert!(blocked(&set, "http://cdn.ads.example.com/", &doc()));
+        assert!(!blocked(&set, "https://badads.example.com/", &doc()));
+        assert!(blocked(&set, "https://tracker.test/pixel?id=1", &doc()));
+        assert!(!blocked(&set, "https://tracker.test/pixels", &doc()));
+    }
+
+    #[test]
+    fn wildcards_and_exceptions() {
+        let set = filters(&["/banner/*/ad_", "@@||example.com/banner/ok/"]);
+        assert!(blocked(&set, "https://news.test/banner/top/ad_1.png", &doc()));
+        assert!(!blocked(&set, "https://example.com/banner/ok/ad_1.png", &doc()));
+    }
+
+    #[test]
+    fn type_and_party_options() {
+        let set = filters(&["||cdn.test^$image,third-party"]);
+        let image_from = |source| RequestContext { source: Some(source), kind: ResourceKind::Image };
+        assert!(blocked(&set, "https://cdn.test/a.png", &image_from("https://site.test/")));
+        assert!(!blocked(&set, "https://cdn.test/a.png", &image_from("https://www.cdn.test/")));
+        let script = RequestContext { source: Some("https://site.test/"), kind: ResourceKind::Script };
+        assert!(!blocked(&set, "https://cdn.test/a.js", &script));
+    }
+
+    #[test]
+    fn check_names_the_matching_rule() {
+        let set = filters(&["||ads.test^", "/track*.gif"]);
+        assert_eq!(set.check("https://x.ads.test/", &doc()), FilterDecision::reject_matching("blacklist", "||ads.test^"));
+        assert_eq!(
+            set.check("https://a.test/tracker.gif", &doc()),
+            FilterDecision::reject_matching("blacklist", "/track*.gif")
+        );
+        assert!(set.check("https://a.test/", &doc()).is_accepted());
+    }
+
+    #[test]
+    fn skips_comments_hiding_rules_and_unknown_options() {
+        let mut set = FilterSet::default();
+        assert!(!set.add_filter("! Title: list"));
+        assert!(!set.add_filter("example.com##.ad"));
+        assert!(!set.add_filter("||popup.test^$popup"));
+        assert_eq!(set.len(), 0);
+    }
+}
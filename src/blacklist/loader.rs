@@ -1,25 +1,93 @@
 //! Blacklist loading and management utilities
 
-use crate::blacklist::Blacklist;
+use crate::blacklist::{Blacklist, FilterSet};
 use crate::error::{AppError, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How often list files are checked for changes during a crawl
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Layout of a `.toml` list file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BlacklistFile {
+    /// Domains blocked along with their subdomains
+    domains: Vec<String>,
+    /// URLs blocked exactly
+    urls: Vec<String>,
+    /// Regexes matched anywhere in the URL
+    patterns: Vec<String>,
+    /// Adblock Plus filters, including `@@` exceptions
+    rules: Vec<String>,
+}
 
 /// BlacklistLoader handles loading and initializing the URL blacklist
 pub struct BlacklistLoader;
 
 impl BlacklistLoader {
-    /// Load a blacklist from a file path
-    pub fn load(path: &str) -> Result<Arc<Blacklist>> {
-        let content = std::fs::read_to_string(path)
-            .map_err(AppError::Io)?;
+    /// Load and merge list files. `.toml` files use the domains/urls/patterns
+    /// layout; anything else is read as an Adblock Plus filter list, the
+    /// format of downloaded lists such as EasyList.
+    pub fn load(paths: &[PathBuf]) -> Result<Arc<Blacklist>> {
+        let modified = Self::modified_times(paths);
+        let filters = Self::compile(paths)?;
+        Ok(Arc::new(Blacklist::from_sources(paths.to_vec(), filters, modified)))
+    }
 
-        let blacklist: Blacklist = toml::from_str(&content)
-            .map_err(|e| AppError::Blacklist(format!("Failed to parse blacklist: {e}")))?;
+    /// Compile every file into one rule set
+    pub fn compile(paths: &[PathBuf]) -> Result<FilterSet> {
+        let mut filters = FilterSet::default();
+        for path in paths {
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                AppError::Blacklist(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+            let file_filters = if path.extension().is_some_and(|ext| ext == "toml") {
+                Self::parse_toml(path, &content)?
+            } else {
+                Self::parse_filter_list(&content)
+            };
+            log::debug!("Loaded {} blacklist rules from {}", file_filters.len(), path.display());
+            filters.extend(file_filters);
+        }
+        Ok(filters)
+    }
 
-        // Pre-compile regex patterns for better performance
-        let blacklist = blacklist.with_compiled_patterns()
-            .map_err(|e| AppError::Blacklist(e.to_string()))?;
+    /// Modification times used to detect changed files
+    pub fn modified_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+        paths
+            .iter()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    fn parse_toml(path: &Path, content: &str) -> Result<FilterSet> {
+        let file: BlacklistFile = toml::from_str(content).map_err(|e| {
+            AppError::Blacklist(format!("Failed to parse blacklist {}: {e}", path.display()))
+        })?;
+
+        let mut filters = FilterSet::default();
+        file.domains.iter().for_each(|d| filters.add_domain(d));
+        file.urls.iter().for_each(|u| filters.add_exact(u));
+        for pattern in &file.patterns {
+            filters.add_regex(pattern).map_err(AppError::Regex)?;
+        }
+        for rule in &file.rules {
+            if !filters.add_filter(rule) {
+                log::warn!("Ignoring unsupported blacklist rule in {}: {}", path.display(), rule);
+            }
+        }
+        Ok(filters)
+    }
 
-        Ok(Arc::new(blacklist))
+    /// Adblock Plus list; unsupported lines are skipped as browsers do
+    fn parse_filter_list(content: &str) -> FilterSet {
+        let mut filters = FilterSet::default();
+        for line in content.lines() {
+            filters.add_filter(line);
+        }
+        filters
     }
 }
@@ -3,7 +3,9 @@
 use std::path::PathBuf;
 use log::info;
 use url::Url;
-use crate::config::{AppConfig, CrawlerConfig, DEFAULT_WORKERS};
+use crate::config::{
+    AppConfig, CrawlStrategy, CrawlerConfig, OutputFormat, PruneMode, ReportFormat, RuleAction, ScopeRule, DEFAULT_WORKERS,
+};
 use crate::cli::help::print_help;
 use crate::error::{AppError, Result};
 
@@ -16,8 +18,9 @@ impl ArgParser {
         // Load configuration from TOML file first
         let app_config = AppConfig::load_or_default("config.toml");
         // Clone is necessary since app_config is used later
-        let mut config: CrawlerConfig = app_config.crawler.clone().into();
+        let mut config: CrawlerConfig = app_config.clone().into();
         let mut save_dir: Option<PathBuf> = None;
+        let mut blacklists: Vec<PathBuf> = Vec::new();
         let mut i = 1;
 
         // Allow command line arguments to override config file values
@@ -35,12 +38,89 @@ impl ArgParser {
                 "--max-depth" | "-d" => {
                     i = Self::handle_max_depth_arg(args, i, &mut config)?;
                 },
+                "--max-in-flight" => {
+                    i = Self::handle_max_in_flight_arg(args, i, &mut config)?;
+                },
+                "--max-frontier" => {
+                    i = Self::handle_max_frontier_arg(args, i, &mut config)?;
+                },
+                "--max-pages" => {
+                    i = Self::handle_max_pages_arg(args, i, &mut config)?;
+                },
+                "--max-bytes" => {
+                    i = Self::handle_max_bytes_arg(args, i, &mut config)?;
+                },
+                "--max-time" => {
+                    i = Self::handle_max_time_arg(args, i, &mut config)?;
+                },
+                "--max-pages-per-host" => {
+                    i = Self::handle_max_pages_per_host_arg(args, i, &mut config)?;
+                },
+                "--max-pages-per-dir" => {
+                    i = Self::handle_max_pages_per_dir_arg(args, i, &mut config)?;
+                },
+                "--max-response-size" => {
+                    i = Self::handle_max_response_size_arg(args, i, &mut config)?;
+                },
+                "--strategy" => {
+                    i = Self::handle_strategy_arg(args, i, &mut config)?;
+                },
                 "--config" | "-c" => {
                     i = Self::handle_config_arg(args, i, &mut config)?;
                 },
                 "--scope" => {
                     i = Self::handle_scope_arg(args, i, &mut config)?;
                 },
+                "--include" => {
+                    i = Self::handle_rule_arg(args, i, RuleAction::Include, &mut config)?;
+                },
+                "--exclude" => {
+                    i = Self::handle_rule_arg(args, i, RuleAction::Exclude, &mut config)?;
+                },
+                "--external-assets" => {
+                    config.scope.external_assets = true;
+                    i += 1;
+                },
+                "--blacklist" => {
+                    i = Self::handle_blacklist_arg(args, i, &mut blacklists)?;
+                },
+                "--sitemap" => {
+                    i = Self::handle_sitemap_arg(args, i, &mut config)?;
+                },
+                "--state" => {
+                    i = Self::handle_state_arg(args, i, &mut config)?;
+                },
+                "--resume" => {
+                    config.resume = true;
+                    i += 1;
+                },
+                "--cache" => {
+                    i = Self::handle_cache_arg(args, i, &mut config)?;
+                },
+                "--offline" => {
+                    config.offline = true;
+                    i += 1;
+                },
+                "--format" => {
+                    i = Self::handle_format_arg(args, i, &mut config)?;
+                },
+                "--report" => {
+                    i = Self::handle_report_arg(args, i, &mut config)?;
+                },
+                "--report-format" => {
+                    i = Self::handle_report_format_arg(args, i, &mut config)?;
+                },
+                "--incremental" => {
+                    config.incremental = true;
+                    i += 1;
+                },
+                "--prune" => {
+                    i = Self::handle_prune_arg(args, i, &mut config)?;
+                },
+                "--ignore-robots" => {
+                    config.ignore_robots_txt = true;
+                    i += 1;
+                },
                 "--generate-config" | "-g" => {
                     Self::handle_generate_config_arg(args, i)?;
                     // This will exit the program if successful
@@ -55,12 +135,28 @@ impl ArgParser {
             }
         }
 
+        // Blacklists named on the command line replace those in the config file
+        if !blacklists.is_empty() {
+            config.blacklists = blacklists;
+        }
+
         Self::adjust_worker_count(&mut config);
         Self::log_configuration(&config, &save_dir);
 
         Ok((config, save_dir))
     }
 
+    /// URL given to the `explain` subcommand, if that is what was run
+    pub fn explain_target(args: &[String]) -> Result<Option<&str>> {
+        if args.get(1).map(String::as_str) != Some("explain") {
+            return Ok(None);
+        }
+        match args.get(2) {
+            Some(url) if !url.starts_with('-') => Ok(Some(url)),
+            _ => Err(AppError::MissingArgument("URL to explain")),
+        }
+    }
+
     fn handle_url_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
         if i + 1 < args.len() {
             config.base_url = args[i + 1].to_string();
@@ -108,11 +204,98 @@ impl ArgParser {
         }
     }
 
+    fn handle_max_in_flight_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+            Some(limit) if limit > 0 => config.max_pages_in_flight = limit,
+            _ => return Err(AppError::MissingArgument("number of pages in flight (at least 1)")),
+        }
+        Ok(i + 2)
+    }
+
+    fn handle_max_frontier_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+            Some(limit) if limit > 0 => config.max_frontier_in_memory = limit,
+            _ => return Err(AppError::MissingArgument("number of frontier URLs in memory (at least 1)")),
+        }
+        Ok(i + 2)
+    }
+
+    fn handle_max_pages_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+            Some(limit) if limit > 0 => config.budget.max_pages = Some(limit),
+            _ => return Err(AppError::MissingArgument("maximum number of pages (at least 1)")),
+        }
+        Ok(i + 2)
+    }
+
+    fn handle_max_bytes_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| Self::parse_size(n)) {
+            Some(limit) if limit > 0 => config.budget.max_bytes = Some(limit),
+            _ => return Err(AppError::MissingArgument("maximum bytes to download (e.g. 500M)")),
+        }
+        Ok(i + 2)
+    }
+
+    fn handle_max_time_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| n.parse::<u64>().ok()) {
+            Some(secs) if secs > 0 => config.budget.max_time_secs = Some(secs),
+            _ => return Err(AppError::MissingArgument("maximum crawl time in seconds (at least 1)")),
+        }
+        Ok(i + 2)
+    }
+
+    fn handle_max_pages_per_host_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+            Some(limit) if limit > 0 => config.budget.max_pages_per_host = Some(limit),
+            _ => return Err(AppError::MissingArgument("maximum pages per host (at least 1)")),
+        }
+        Ok(i + 2)
+    }
+
+    fn handle_max_pages_per_dir_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+            Some(limit) if limit > 0 => config.budget.max_pages_per_dir = Some(limit),
+            _ => return Err(AppError::MissingArgument("maximum pages per directory (at least 1)")),
+        }
+        Ok(i + 2)
+    }
+
+    fn handle_max_response_size_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).and_then(|n| Self::parse_size(n)) {
+            Some(limit) if limit > 0 => config.budget.max_response_bytes = Some(limit),
+            _ => return Err(AppError::MissingArgument("maximum response size (e.g. 10M)")),
+        }
+        Ok(i + 2)
+    }
+
+    /// Parse a byte count with an optional K, M or G suffix (powers of 1024)
+    fn parse_size(value: &str) -> Option<u64> {
+        let value = value.trim();
+        let (digits, multiplier) = match value.chars().last()?.to_ascii_uppercase() {
+            'K' => (&value[..value.len() - 1], 1u64 << 10),
+            'M' => (&value[..value.len() - 1], 1 << 20),
+            'G' => (&value[..value.len() - 1], 1 << 30),
+            _ => (value, 1),
+        };
+        digits.parse::<u64>().ok()?.checked_mul(multiplier)
+    }
+
+    fn handle_strategy_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        config.strategy = match args.get(i + 1).map(String::as_str) {
+            Some("bfs") => CrawlStrategy::Bfs,
+            Some("dfs") => CrawlStrategy::Dfs,
+            Some("best-first") => CrawlStrategy::BestFirst,
+            Some("round-robin") => CrawlStrategy::RoundRobin,
+            _ => return Err(AppError::MissingArgument("crawl strategy (bfs, dfs, best-first or round-robin)")),
+        };
+        Ok(i + 2)
+    }
+
     fn handle_config_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
         if i + 1 < args.len() {
             // Load different config file
             let custom_config = AppConfig::load_or_default(&args[i + 1]);
-            *config = custom_config.crawler.into();
+            *config = custom_config.into();
             Ok(i + 2)
         } else {
             Err(AppError::MissingArgument("config file path"))
@@ -127,15 +310,100 @@ impl ArgParser {
             Ok(i + 2)
         } else {
             // No domains specified - restrict to base domain
-            if let Ok(parsed) = Url::parse(&config.base_url) {
-                if let Some(host) = parsed.host_str() {
-                    config.allowed_domains = vec![host.to_string()];
-                }
+            if let Ok(parsed) = Url::parse(&config.base_url)
+                && let Some(host) = parsed.host_str()
+            {
+                config.allowed_domains = vec![host.to_string()];
             }
             Ok(i + 1)
         }
     }
 
+    /// Append a glob rule; rules from the command line follow those in the config file
+    fn handle_rule_arg(args: &[String], i: usize, action: RuleAction, config: &mut CrawlerConfig) -> Result<usize> {
+        let glob = args.get(i + 1).ok_or(AppError::MissingArgument("URL glob for --include or --exclude"))?;
+        config.scope.rules.push(ScopeRule { action, prefix: None, glob: Some(glob.clone()), regex: None });
+        Ok(i + 2)
+    }
+
+    fn handle_blacklist_arg(args: &[String], i: usize, blacklists: &mut Vec<PathBuf>) -> Result<usize> {
+        let path = args.get(i + 1).ok_or(AppError::MissingArgument("blacklist file path"))?;
+        blacklists.push(PathBuf::from(path));
+        Ok(i + 2)
+    }
+
+    fn handle_sitemap_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        if i + 1 < args.len() {
+            config.sitemaps.push(args[i + 1].to_string());
+            Ok(i + 2)
+        } else {
+            Err(AppError::MissingArgument("sitemap url"))
+        }
+    }
+
+    fn handle_state_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        if i + 1 < args.len() {
+            config.state_path = Some(PathBuf::from(&args[i + 1]));
+            Ok(i + 2)
+        } else {
+            Err(AppError::MissingArgument("state file path"))
+        }
+    }
+
+    fn handle_cache_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        if i + 1 < args.len() {
+            config.cache_dir = Some(PathBuf::from(&args[i + 1]));
+            Ok(i + 2)
+        } else {
+            Err(AppError::MissingArgument("cache directory"))
+        }
+    }
+
+    fn handle_format_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        let list = args.get(i + 1).ok_or(AppError::MissingArgument("output format (mirror, warc)"))?;
+        let mut formats = Vec::new();
+        for name in list.split(',').map(str::trim) {
+            let format = match name {
+                "mirror" => OutputFormat::Mirror,
+                "warc" => OutputFormat::Warc,
+                _ => return Err(AppError::MissingArgument("output format (mirror, warc)")),
+            };
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        config.output_formats = formats;
+        Ok(i + 2)
+    }
+
+    fn handle_report_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        if i + 1 < args.len() {
+            config.report_path = Some(PathBuf::from(&args[i + 1]));
+            Ok(i + 2)
+        } else {
+            Err(AppError::MissingArgument("report file path"))
+        }
+    }
+
+    fn handle_report_format_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        config.report_format = Some(match args.get(i + 1).map(String::as_str) {
+            Some("json") => ReportFormat::Json,
+            Some("jsonl") => ReportFormat::JsonLines,
+            Some("csv") => ReportFormat::Csv,
+            _ => return Err(AppError::MissingArgument("report format (json, jsonl or csv)")),
+        });
+        Ok(i + 2)
+    }
+
+    fn handle_prune_arg(args: &[String], i: usize, config: &mut CrawlerConfig) -> Result<usize> {
+        match args.get(i + 1).map(String::as_str) {
+            Some("delete") => config.prune_mode = PruneMode::Delete,
+            Some("quarantine") => config.prune_mode = PruneMode::Quarantine,
+            _ => return Err(AppError::MissingArgument("prune mode (delete or quarantine)")),
+        }
+        Ok(i + 2)
+    }
+
     fn handle_generate_config_arg(args: &[String], i: usize) -> Result<()> {
         let output_path = if i + 1 < args.len() && !args[i + 1].starts_with("--") {
             &args[i + 1]
@@ -144,7 +412,7 @@ impl ArgParser {
         };
 
         let default_config = AppConfig::default();
-        match default_config.save_to_file(&output_path) {
+        match default_config.save_to_file(output_path) {
             Ok(()) => {
                 info!("Generated default configuration file: {}", output_path);
                 std::process::exit(0);
@@ -158,7 +426,7 @@ impl ArgParser {
     fn adjust_worker_count(config: &mut CrawlerConfig) {
         let cpu_count = num_cpus::get();
         if config.worker_count == DEFAULT_WORKERS {
-            config.worker_count = std::cmp::max(2, std::cmp::min(cpu_count * 2, 16));
+            config.worker_count = (cpu_count * 2).clamp(2, 16);
         }
     }
 
@@ -167,11 +435,78 @@ impl ArgParser {
         info!("  Base URL: {}", config.base_url);
         info!("  Max Depth: {}", config.max_depth);
         info!("  Worker Count: {}", config.worker_count);
+        info!("  Max Pages In Flight: {}", config.max_pages_in_flight);
+        info!("  Max Frontier In Memory: {}", config.max_frontier_in_memory);
+        info!("  Strategy: {:?}", config.strategy);
+        let budget = &config.budget;
+        if let Some(limit) = budget.max_pages {
+            info!("  Max Pages: {}", limit);
+        }
+        if let Some(limit) = budget.max_bytes {
+            info!("  Max Bytes: {}", limit);
+        }
+        if let Some(secs) = budget.max_time_secs {
+            info!("  Max Time: {}s", secs);
+        }
+        if let Some(limit) = budget.max_pages_per_host {
+            info!("  Max Pages Per Host: {}", limit);
+        }
+        if let Some(limit) = budget.max_pages_per_dir {
+            info!("  Max Pages Per Directory: {}", limit);
+        }
+        if let Some(limit) = budget.max_response_bytes {
+            info!("  Max Response Size: {} bytes", limit);
+        }
+        if !config.priority_patterns.is_empty() {
+            info!("  Priority Patterns: {:?}", config.priority_patterns);
+        }
+        let normalization = &config.normalization;
+        if normalization.strip_trailing_slash || normalization.fold_index {
+            info!("  Folding URLs: trailing slashes {}, index pages {}",
+                normalization.strip_trailing_slash, normalization.fold_index);
+        }
         if !config.allowed_domains.is_empty() {
             info!("  Allowed Domains: {:?}", config.allowed_domains);
         }
+        if !config.scope.rules.is_empty() {
+            info!("  Scope Rules: {} ({:?})", config.scope.rules.len(), config.scope.precedence);
+        }
+        if config.scope.external_assets {
+            info!("  Fetching external assets");
+        }
+        if !config.blacklists.is_empty() {
+            info!("  Blacklists: {:?}", config.blacklists);
+        }
+        if !config.traps.pattern_caps.is_empty() {
+            info!("  Trap Pattern Caps: {}", config.traps.pattern_caps.len());
+        }
+        if !config.sitemaps.is_empty() {
+            info!("  Sitemaps: {:?}", config.sitemaps);
+        }
+        if let Some(path) = &config.state_path {
+            info!("  State File: {}", path.display());
+        }
+        if config.resume {
+            info!("  Resuming previous crawl");
+        }
+        if let Some(dir) = &config.cache_dir {
+            info!("  Cache Directory: {}", dir.display());
+        }
+        if config.offline {
+            info!("  Offline: serving only from the cache");
+        }
+        if let Some(path) = &config.report_path {
+            info!("  Report: {}", path.display());
+        }
+        if config.incremental {
+            info!("  Incremental mirror update (prune: {:?})", config.prune_mode);
+        }
+        if config.ignore_robots_txt {
+            info!("  Ignoring robots.txt");
+        }
         if let Some(dir) = save_dir {
             info!("  Save Directory: {}", dir.display());
+            info!("  Output Formats: {:?}", config.output_formats);
         }
     }
 }
@@ -8,6 +8,7 @@ pub fn print_help() {
     println!();
     println!("USAGE:");
     println!("    {} [OPTIONS]", get_program_name());
+    println!("    {} explain <URL> [OPTIONS]   Show which filters accept or reject a URL", get_program_name());
     println!();
     println!("OPTIONS:");
     print_options();
@@ -26,8 +27,32 @@ fn print_options() {
         ("-s, --save [DIR]", "Save crawled content (optional directory)"),
         ("-w, --workers <NUM>", "Number of worker threads"),
         ("-d, --max-depth <NUM>", "Maximum crawl depth"),
+        ("--max-in-flight <NUM>", "Crawled pages held in memory before workers wait for saving (default: 64)"),
+        ("--strategy <NAME>", "Crawl order: bfs (default), dfs, best-first or round-robin"),
+        ("--max-frontier <NUM>", "Queued URLs held in memory; the rest spill to a temp file (default: 100000)"),
+        ("--max-pages <NUM>", "Stop after fetching this many URLs"),
+        ("--max-bytes <SIZE>", "Stop after downloading this much (e.g. 500M; K, M, G suffixes)"),
+        ("--max-time <SECS>", "Stop after this many seconds"),
+        ("--max-pages-per-host <NUM>", "Fetch at most this many URLs from each host"),
+        ("--max-pages-per-dir <NUM>", "Fetch at most this many URLs from each directory"),
+        ("--max-response-size <SIZE>", "Abort responses larger than this (e.g. 10M)"),
         ("-c, --config <FILE>", "Use custom config file (default: config.toml)"),
         ("--scope [DOMAINS]", "Restrict crawling to specified domains (e.g., '*.google.com,example.com')"),
+        ("--include <GLOB>", "Only crawl URLs matching a glob, e.g. 'https://*/docs/**' (repeatable)"),
+        ("--exclude <GLOB>", "Skip URLs matching a glob (repeatable)"),
+        ("--external-assets", "Fetch images, CSS, JS and fonts from other domains without crawling them"),
+        ("--blacklist <FILE>", "Blacklist file, .toml or Adblock Plus list (repeatable; default: blacklist.toml)"),
+        ("--sitemap <URL>", "Seed the crawl from a sitemap (repeatable)"),
+        ("--state <FILE>", "Crawl state journal (default: <save dir>/crawl_state.jsonl)"),
+        ("--resume", "Resume an interrupted crawl from its state journal"),
+        ("--cache <DIR>", "Cache responses on disk and revalidate them on later runs"),
+        ("--offline", "Serve responses only from the --cache directory"),
+        ("--format <LIST>", "What --save writes: mirror, warc or mirror,warc (default: mirror)"),
+        ("--report <FILE>", "Write a machine-readable crawl report"),
+        ("--report-format <FMT>", "Report format: json, jsonl or csv (default: from file extension)"),
+        ("--incremental", "Update an existing mirror, writing only changed files"),
+        ("--prune <MODE>", "Removed pages in --incremental mode: quarantine (default) or delete"),
+        ("--ignore-robots", "Do not honor robots.txt (only for sites you own)"),
         ("-g, --generate-config [FILE]", "Generate default config file"),
         ("-h, --help", "Show this help message"),
     ];
@@ -44,7 +69,18 @@ fn print_examples() {
         format!("{} --workers 4 --max-depth 5 --save ./output", program_name),
         format!("{} --scope '*.example.com' --save", program_name),
         format!("{} --scope 'google.com,*.github.com' --save", program_name),
+        format!("{} --exclude '**/print' --external-assets --save", program_name),
+        format!("{} --blacklist blacklist.toml --blacklist easylist.txt --save", program_name),
         format!("{} --config my-config.toml --save", program_name),
+        format!("{} --save ./output --max-in-flight 16 --max-frontier 10000", program_name),
+        format!("{} --strategy best-first --save", program_name),
+        format!("{} --max-pages 500 --max-time 600 --max-response-size 10M --save", program_name),
+        format!("{} --save ./output --resume", program_name),
+        format!("{} --cache ./cache --save ./output", program_name),
+        format!("{} --save ./output --incremental --prune delete", program_name),
+        format!("{} --save ./archive --format warc", program_name),
+        format!("{} --report crawl.jsonl --report-format jsonl", program_name),
+        format!("{} explain https://example.com/files/setup.exe --scope example.com", program_name),
         format!("{} --generate-config my-config.toml", program_name),
     ];
 
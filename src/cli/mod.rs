@@ -1,6 +1,8 @@
 //! Command line interface module
 
 pub mod args;
+pub mod explain;
 pub mod help;
 
 pub use args::ArgParser;
+pub use explain::UrlExplainer;
@@ -1,13 +1,22 @@
 //! Configuration for the web crawler
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// HTTP request timeout (ms)
 pub const REQUEST_TIMEOUT_MS: u64 = 10_000;
 /// Default number of concurrent workers
 pub const DEFAULT_WORKERS: usize = 8;
+/// Default number of crawled pages waiting to be saved before workers pause
+pub const DEFAULT_MAX_PAGES_IN_FLIGHT: usize = 64;
+/// Default number of frontier URLs kept in memory before the rest spill to disk
+pub const DEFAULT_MAX_FRONTIER_IN_MEMORY: usize = 100_000;
+/// Default minimum delay between requests to the same host (ms)
+pub const DEFAULT_HOST_DELAY_MS: u64 = 100;
+/// Default number of concurrent requests to the same host
+pub const DEFAULT_HOST_CONCURRENCY: usize = 2;
 
 /// Network configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -23,20 +32,291 @@ impl Default for NetworkConfig {
     }
 }
 
+/// Output formats written to the save directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Browsable directory tree with rewritten links
+    Mirror,
+    /// WARC/1.1 files for web-archive tooling
+    Warc,
+}
+
+/// Machine-readable report formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Json,
+    /// One JSON object per line, written as pages arrive
+    #[serde(rename = "jsonl")]
+    JsonLines,
+    Csv,
+}
+
+impl ReportFormat {
+    /// Guess the format from a report file extension, defaulting to JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("jsonl" | "ndjson") => Self::JsonLines,
+            Some("csv") => Self::Csv,
+            _ => Self::Json,
+        }
+    }
+}
+
 /// Output configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OutputConfig {
     pub default_save_dir: String,
+    #[serde(default = "default_formats")]
+    pub formats: Vec<OutputFormat>,
+}
+
+fn default_formats() -> Vec<OutputFormat> {
+    vec![OutputFormat::Mirror]
 }
 
 impl Default for OutputConfig {
     fn default() -> Self {
         Self {
             default_save_dir: "output".to_string(),
+            formats: default_formats(),
+        }
+    }
+}
+
+/// WARC output configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WarcConfig {
+    /// File name prefix; files are named `<prefix>-<timestamp>-<NNNNN>.warc[.gz]`
+    pub prefix: String,
+    /// Compress each record as its own gzip member
+    pub gzip: bool,
+    /// Start a new file once the current one reaches this size
+    pub max_file_size_mb: u64,
+}
+
+impl Default for WarcConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "crawl".to_string(),
+            gzip: true,
+            max_file_size_mb: 1024,
+        }
+    }
+}
+
+/// What an incremental mirror update does with files of removed URLs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PruneMode {
+    /// Move them into the mirror's quarantine directory
+    #[default]
+    Quarantine,
+    /// Delete them
+    Delete,
+}
+
+/// Order in which queued URLs are crawled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrawlStrategy {
+    /// Breadth-first: oldest queued URL first
+    #[default]
+    Bfs,
+    /// Depth-first: newest queued URL first
+    Dfs,
+    /// Priority patterns, then pages before assets, then shorter paths
+    BestFirst,
+    /// One URL per host in turn
+    RoundRobin,
+}
+
+/// Politeness settings that override the defaults for one domain
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HostPolitenessOverride {
+    pub max_concurrent: Option<usize>,
+    pub min_delay_ms: Option<u64>,
+}
+
+/// Crawl budgets; a missing limit means unlimited
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// Stop after fetching this many URLs
+    pub max_pages: Option<usize>,
+    /// Stop once this many body bytes have been downloaded
+    pub max_bytes: Option<u64>,
+    /// Stop after this many seconds of crawling
+    pub max_time_secs: Option<u64>,
+    /// Skip further URLs of a host after this many
+    pub max_pages_per_host: Option<usize>,
+    /// Skip further URLs of a directory after this many
+    pub max_pages_per_dir: Option<usize>,
+    /// Abort fetches whose body is larger than this
+    pub max_response_bytes: Option<u64>,
+}
+
+/// What a matching scope rule does with a URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Include,
+    Exclude,
+}
+
+/// How scope rules combine when several match a URL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RulePrecedence {
+    /// The first matching rule decides
+    #[default]
+    FirstMatch,
+    /// Any matching exclude rule wins over include rules
+    DenyOverrides,
+}
+
+/// One include or exclude rule; set exactly one of `prefix`, `glob` or `regex`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScopeRule {
+    pub action: RuleAction,
+    /// Path prefix such as `/blog/`, or a prefix of the full URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Glob on the full URL; `*` stays within a path segment, `**` does not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    /// Regex searched for in the full URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+}
+
+/// Which URLs the crawl may fetch
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScopeConfig {
+    /// Domain patterns such as `example.com` or `*.example.com`; merged with
+    /// `[crawler] allowed_domains`, empty means any domain
+    pub allowed_domains: Vec<String>,
+    /// Include and exclude rules on full URLs, in order. When include rules
+    /// exist, URLs no rule matches are out of scope.
+    pub rules: Vec<ScopeRule>,
+    pub precedence: RulePrecedence,
+    /// Fetch images, stylesheets, scripts and fonts from domains outside
+    /// `allowed_domains`, without following links found in them
+    pub external_assets: bool,
+}
+
+/// Spider-trap detection; setting a limit to 0 turns its rule off
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrapConfig {
+    /// Reject URLs containing `///` or encoded `//`, typical of broken relative links
+    pub malformed_paths: bool,
+    /// Reject URLs in which one path segment occurs more than this many times
+    pub max_segment_repeats: usize,
+    /// Reject URLs with more path segments than this
+    pub max_path_depth: usize,
+    /// Reject URLs with more query parameters than this
+    pub max_query_params: usize,
+    /// Stop following links that only bump a number of the page URL by one
+    /// (next day, next page) after this many in a row
+    pub max_numeric_chain: usize,
+    /// Fetch at most `max_pages` URLs matching each regex, e.g. `/calendar/`
+    pub pattern_caps: Vec<TrapPatternCap>,
+}
+
+impl Default for TrapConfig {
+    fn default() -> Self {
+        Self {
+            malformed_paths: true,
+            max_segment_repeats: 3,
+            max_path_depth: 30,
+            max_query_params: 12,
+            max_numeric_chain: 100,
+            pattern_caps: Vec::new(),
+        }
+    }
+}
+
+/// Page cap for URLs matching a regex
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrapPatternCap {
+    pub pattern: String,
+    pub max_pages: usize,
+}
+
+/// How URLs are canonicalized before they are compared, queued or saved
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NormalizationConfig {
+    /// Query and `;` path parameters to drop, matched case-insensitively; a
+    /// trailing `*` matches any suffix
+    pub strip_params: Vec<String>,
+    /// Sort query parameters so their order does not matter
+    pub sort_query: bool,
+    /// Treat `/docs/` and `/docs` as the same URL
+    pub strip_trailing_slash: bool,
+    /// Treat `/docs/index.html` and `/docs/` as the same URL
+    pub fold_index: bool,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            strip_params: [
+                "utm_*", "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "_ga",
+                "jsessionid", "phpsessid", "aspsessionid*", "sessionid",
+            ]
+            .map(String::from)
+            .to_vec(),
+            sort_query: true,
+            strip_trailing_slash: false,
+            fold_index: false,
+        }
+    }
+}
+
+/// Per-host request scheduling configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PolitenessConfig {
+    /// Maximum number of in-flight requests to a single host
+    pub max_concurrent_per_host: usize,
+    /// Minimum delay between two requests to the same host
+    pub min_delay_ms: u64,
+    /// Use robots.txt `Crawl-delay` when it is longer than `min_delay_ms`
+    pub respect_crawl_delay: bool,
+    /// Upper bound for Crawl-delay and adaptive slowdown after 429/503 responses
+    pub max_delay_ms: u64,
+    /// Per-domain overrides; a key matches the host itself and its subdomains
+    pub overrides: HashMap<String, HostPolitenessOverride>,
+}
+
+impl Default for PolitenessConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_per_host: DEFAULT_HOST_CONCURRENCY,
+            min_delay_ms: DEFAULT_HOST_DELAY_MS,
+            respect_crawl_delay: true,
+            max_delay_ms: 60_000,
+            overrides: HashMap::new(),
         }
     }
 }
 
+impl PolitenessConfig {
+    /// Find the override that applies to a host, preferring the most specific domain
+    pub fn override_for(&self, host: &str) -> Option<&HostPolitenessOverride> {
+        self.overrides
+            .iter()
+            .filter(|(domain, _)| host == domain.as_str() || host.ends_with(&format!(".{}", domain)))
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(_, o)| o)
+    }
+}
+
 /// Crawler-specific configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CrawlerConfigSection {
@@ -46,6 +326,46 @@ pub struct CrawlerConfigSection {
     pub user_agent: String,
     #[serde(default)]
     pub allowed_domains: Vec<String>,
+    /// Skip robots.txt checks (for sites we own)
+    #[serde(default)]
+    pub ignore_robots_txt: bool,
+    /// Sitemap URLs to seed the crawl from
+    #[serde(default)]
+    pub sitemaps: Vec<String>,
+    /// Look for /sitemap.xml and robots.txt `Sitemap:` lines on the base host
+    #[serde(default = "default_true")]
+    pub discover_sitemaps: bool,
+    /// Crawled pages held in memory while waiting to be saved
+    #[serde(default = "default_max_pages_in_flight")]
+    pub max_pages_in_flight: usize,
+    /// Frontier URLs kept in memory; more are spilled to a temporary file
+    #[serde(default = "default_max_frontier_in_memory")]
+    pub max_frontier_in_memory: usize,
+    /// Order in which queued URLs are crawled
+    #[serde(default)]
+    pub strategy: CrawlStrategy,
+    /// Regexes of URLs the best-first strategy crawls first
+    #[serde(default)]
+    pub priority_patterns: Vec<String>,
+    /// Blacklist files: `.toml` lists or Adblock Plus filter lists
+    #[serde(default = "default_blacklists")]
+    pub blacklists: Vec<PathBuf>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_blacklists() -> Vec<PathBuf> {
+    vec![PathBuf::from("blacklist.toml")]
+}
+
+fn default_max_pages_in_flight() -> usize {
+    DEFAULT_MAX_PAGES_IN_FLIGHT
+}
+
+fn default_max_frontier_in_memory() -> usize {
+    DEFAULT_MAX_FRONTIER_IN_MEMORY
 }
 
 impl Default for CrawlerConfigSection {
@@ -56,26 +376,36 @@ impl Default for CrawlerConfigSection {
             max_depth: 1000,
             user_agent: "Mozilla/5.0 (compatible; RustCrawler/1.0)".to_string(),
             allowed_domains: Vec::new(),
+            ignore_robots_txt: false,
+            sitemaps: Vec::new(),
+            discover_sitemaps: true,
+            max_pages_in_flight: DEFAULT_MAX_PAGES_IN_FLIGHT,
+            max_frontier_in_memory: DEFAULT_MAX_FRONTIER_IN_MEMORY,
+            strategy: CrawlStrategy::default(),
+            priority_patterns: Vec::new(),
+            blacklists: default_blacklists(),
         }
     }
 }
 
 /// Full application configuration loaded from TOML
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct AppConfig {
     pub crawler: CrawlerConfigSection,
     pub network: NetworkConfig,
     pub output: OutputConfig,
-}
-
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            crawler: CrawlerConfigSection::default(),
-            network: NetworkConfig::default(),
-            output: OutputConfig::default(),
-        }
-    }
+    #[serde(default)]
+    pub politeness: PolitenessConfig,
+    #[serde(default)]
+    pub warc: WarcConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub normalization: NormalizationConfig,
+    #[serde(default)]
+    pub scope: ScopeConfig,
+    #[serde(default)]
+    pub traps: TrapConfig,
 }
 
 impl AppConfig {
@@ -114,16 +444,69 @@ pub struct CrawlerConfig {
     pub worker_count: usize,
     pub allowed_domains: Vec<String>, // Domain patterns (e.g., "*.google.com", "example.com")
     pub user_agent: String,  // User agent string for HTTP requests
+    pub ignore_robots_txt: bool, // Crawl paths disallowed by robots.txt
+    pub sitemaps: Vec<String>, // Explicit sitemap URLs to seed from
+    pub discover_sitemaps: bool, // Seed from /sitemap.xml and robots.txt Sitemap: lines
+    pub state_path: Option<PathBuf>, // Crawl state journal (defaults to the save directory)
+    pub resume: bool, // Continue from the crawl state journal
+    pub cache_dir: Option<PathBuf>, // HTTP response cache for revalidating recrawls
+    pub offline: bool, // Serve responses only from the cache
+    pub incremental: bool, // Update an existing mirror, writing only changed files
+    pub prune_mode: PruneMode, // Handling of removed URLs' files in incremental mode
+    pub output_formats: Vec<OutputFormat>, // What --save writes
+    pub warc: WarcConfig, // WARC file settings
+    pub report_path: Option<PathBuf>, // Machine-readable report file
+    pub report_format: Option<ReportFormat>, // Report format (defaults from the file extension)
+    pub politeness: PolitenessConfig, // Per-host request scheduling
+    pub max_pages_in_flight: usize, // Crawled pages waiting to be saved before workers pause
+    pub max_frontier_in_memory: usize, // Frontier URLs kept in memory before spilling to disk
+    pub strategy: CrawlStrategy, // Order in which queued URLs are crawled
+    pub priority_patterns: Vec<String>, // URL regexes crawled first by the best-first strategy
+    pub blacklists: Vec<PathBuf>, // Blacklist files, reloaded when they change
+    pub budget: BudgetConfig, // Page, byte, time and per-host/per-directory limits
+    pub normalization: NormalizationConfig, // URL canonicalization rules
+    pub scope: ScopeConfig, // Include/exclude rules and external assets; domains live in allowed_domains
+    pub traps: TrapConfig, // Spider-trap detection rules
 }
 
 impl From<AppConfig> for CrawlerConfig {
-    fn from(app_config: AppConfig) -> Self {
+    fn from(mut app_config: AppConfig) -> Self {
+        // Domains may be listed under [crawler] or [scope]; both count
+        let mut allowed_domains = app_config.crawler.allowed_domains;
+        for domain in std::mem::take(&mut app_config.scope.allowed_domains) {
+            if !allowed_domains.contains(&domain) {
+                allowed_domains.push(domain);
+            }
+        }
         Self {
             base_url: app_config.crawler.base_url,
             worker_count: app_config.crawler.worker_count,
             max_depth: app_config.crawler.max_depth,
-            allowed_domains: app_config.crawler.allowed_domains,
+            allowed_domains,
             user_agent: app_config.crawler.user_agent, // Initialize user_agent
+            ignore_robots_txt: app_config.crawler.ignore_robots_txt,
+            sitemaps: app_config.crawler.sitemaps,
+            discover_sitemaps: app_config.crawler.discover_sitemaps,
+            state_path: None,
+            resume: false,
+            cache_dir: None,
+            offline: false,
+            incremental: false,
+            prune_mode: PruneMode::default(),
+            output_formats: app_config.output.formats,
+            report_path: None,
+            report_format: None,
+            politeness: app_config.politeness,
+            warc: app_config.warc,
+            max_pages_in_flight: app_config.crawler.max_pages_in_flight,
+            max_frontier_in_memory: app_config.crawler.max_frontier_in_memory,
+            strategy: app_config.crawler.strategy,
+            priority_patterns: app_config.crawler.priority_patterns,
+            blacklists: app_config.crawler.blacklists,
+            budget: app_config.budget,
+            normalization: app_config.normalization,
+            scope: app_config.scope,
+            traps: app_config.traps,
         }
     }
 }
@@ -136,6 +519,29 @@ impl From<CrawlerConfigSection> for CrawlerConfig {
             max_depth: crawler_config.max_depth,
             allowed_domains: crawler_config.allowed_domains,
             user_agent: crawler_config.user_agent, // Initialize user_agent
+            ignore_robots_txt: crawler_config.ignore_robots_txt,
+            sitemaps: crawler_config.sitemaps,
+            discover_sitemaps: crawler_config.discover_sitemaps,
+            state_path: None,
+            resume: false,
+            cache_dir: None,
+            offline: false,
+            incremental: false,
+            prune_mode: PruneMode::default(),
+            output_formats: default_formats(),
+            report_path: None,
+            report_format: None,
+            politeness: PolitenessConfig::default(),
+            warc: WarcConfig::default(),
+            max_pages_in_flight: crawler_config.max_pages_in_flight,
+            max_frontier_in_memory: crawler_config.max_frontier_in_memory,
+            strategy: crawler_config.strategy,
+            priority_patterns: crawler_config.priority_patterns,
+            blacklists: crawler_config.blacklists,
+            budget: BudgetConfig::default(),
+            normalization: NormalizationConfig::default(),
+            scope: ScopeConfig::default(),
+            traps: TrapConfig::default(),
         }
     }
 }
@@ -148,6 +554,29 @@ impl Default for CrawlerConfig {
             worker_count: DEFAULT_WORKERS,
             allowed_domains: Vec::new(), // Default to no domain restrictions
             user_agent: "Mozilla/5.0 (compatible; RustCrawler/1.0)".to_string(), // Default user agent
+            ignore_robots_txt: false, // Honor robots.txt by default
+            sitemaps: Vec::new(),
+            discover_sitemaps: true,
+            state_path: None,
+            resume: false,
+            cache_dir: None,
+            offline: false,
+            incremental: false,
+            prune_mode: PruneMode::default(),
+            output_formats: default_formats(),
+            report_path: None,
+            report_format: None,
+            politeness: PolitenessConfig::default(),
+            warc: WarcConfig::default(),
+            max_pages_in_flight: DEFAULT_MAX_PAGES_IN_FLIGHT,
+            max_frontier_in_memory: DEFAULT_MAX_FRONTIER_IN_MEMORY,
+            strategy: CrawlStrategy::default(),
+            priority_patterns: Vec::new(),
+            blacklists: default_blacklists(),
+            budget: BudgetConfig::default(),
+            normalization: NormalizationConfig::default(),
+            scope: ScopeConfig::default(),
+            traps: TrapConfig::default(),
         }
     }
 }
@@ -0,0 +1,197 @@
+//! URL canonicalization rules
+
+use crate::config::NormalizationConfig;
+use std::sync::OnceLock;
+use url::Url;
+
+/// Rules installed for this run, shared by everything that keys on URLs
+static RULES: OnceLock<UrlCanonicalizer> = OnceLock::new();
+
+/// Rewrites URLs into one canonical form so that trivially different
+/// spellings of the same resource are crawled and saved once.
+///
+/// The `url` crate already lowercases hosts, drops default ports and
+/// resolves dot segments; on top of that this removes fragments and
+/// tracking/session parameters, sorts the query, normalizes
+/// percent-encoding and optionally folds trailing slashes and index pages.
+#[derive(Debug, Clone)]
+pub struct UrlCanonicalizer {
+    strip_params: Vec<String>,
+    sort_query: bool,
+    strip_trailing_slash: bool,
+    fold_index: bool,
+}
+
+impl UrlCanonicalizer {
+    pub fn new(config: &NormalizationConfig) -> Self {
+        Self {
+            strip_params: config.strip_params.iter().map(|p| p.to_ascii_lowercase()).collect(),
+            sort_query: config.sort_query,
+            strip_trailing_slash: config.strip_trailing_slash,
+            fold_index: config.fold_index,
+        }
+    }
+
+    /// Use `config` for every URL normalized from now on. Must run before
+    /// the crawl starts; later calls are ignored.
+    pub fn install(config: &NormalizationConfig) {
+        if RULES.set(Self::new(config)).is_err() {
+            log::warn!("URL normalization rules were already in use; keeping them");
+        }
+    }
+
+    /// Installed rules, or the defaults if none were installed
+    pub fn current() -> &'static Self {
+        RULES.get_or_init(|| Self::new(&NormalizationConfig::default()))
+    }
+
+    /// Canonical form of `url`
+    pub fn canonicalize(&self, url: &str) -> Result<String, url::ParseError> {
+        let parsed = Url::parse(url)?;
+        let mut canonical = format!("{}://{}", parsed.scheme(), parsed.host_str().unwrap_or(""));
+        if let Some(port) = parsed.port() {
+            canonical.push_str(&format!(":{}", port));
+        }
+        canonical.push_str(&self.canonical_path(parsed.path()));
+        if let Some(query) = parsed.query().and_then(|q| self.canonical_query(q)) {
+            canonical.push('?');
+            canonical.push_str(&query);
+        }
+        Ok(canonical)
+    }
+
+    fn canonical_path(&self, path: &str) -> String {
+        let mut path = path
+            .split('/')
+            .map(|segment| self.strip_path_params(segment))
+            .collect::<Vec<_>>()
+            .join("/");
+        path = normalize_percent_encoding(&path);
+
+        if self.fold_index {
+            for index in ["index.html", "index.htm"] {
+                if let Some(dir) = path.strip_suffix(index)
+                    && dir.ends_with('/')
+                {
+                    path.truncate(dir.len());
+                    break;
+                }
+            }
+        }
+        if self.strip_trailing_slash {
+            let trimmed = path.trim_end_matches('/').len().max(1);
+            path.truncate(trimmed);
+        }
+        path
+    }
+
+    /// Drop stripped `;name=value` parameters from one path segment
+    fn strip_path_params(&self, segment: &str) -> String {
+        let mut parts = segment.split(';');
+        let mut kept = parts.next().unwrap_or_default().to_string();
+        for param in parts {
+            if !self.is_stripped(param) {
+                kept.push(';');
+                kept.push_str(param);
+            }
+        }
+        kept
+    }
+
+    fn canonical_query(&self, query: &str) -> Option<String> {
+        let mut params: Vec<String> = query
+            .split('&')
+            .filter(|param| !param.is_empty() && !self.is_stripped(param))
+            .map(normalize_percent_encoding)
+            .collect();
+        if self.sort_query {
+            params.sort();
+        }
+        (!params.is_empty()).then(|| params.join("&"))
+    }
+
+    /// Whether a `name=value` parameter matches one of the strip rules
+    fn is_stripped(&self, param: &str) -> bool {
+        let name = param.split('=').next().unwrap_or_default().to_ascii_lowercase();
+        self.strip_params.iter().any(|rule| match rule.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *rule,
+        })
+    }
+}
+
+/// Decode escaped unreserved characters and uppercase the hex digits of the
+/// remaining escapes, so `%7e`, `%7E` and `~` compare equal
+fn normalize_percent_encoding(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity(input.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = input.get(i + 1..i + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                out.push(byte as char);
+            } else {
+                out.push_str(&format!("%{:02X}", byte));
+            }
+            i += 3;
+            continue;
+        }
+        // Multi-byte characters are already percent-encoded by the parser
+        out.push(bytes[i] as char);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(url: &str) -> String {
+        UrlCanonicalizer::new(&NormalizationConfig::default()).canonicalize(url).unwrap()
+    }
+
+    #[test]
+    fn normalizes_host_port_fragment_and_dot_segments() {
+        assert_eq!(canonical("HTTP://Example.COM:80/a/./b/../c#top"), "http://example.com/a/c");
+        assert_eq!(canonical("https://example.com:8443/"), "https://example.com:8443/");
+    }
+
+    #[test]
+    fn strips_tracking_params_and_sorts_query() {
+        assert_eq!(
+            canonical("https://example.com/p?b=2&utm_source=x&a=1&fbclid=abc&UTM_Medium=y"),
+            "https://example.com/p?a=1&b=2"
+        );
+        assert_eq!(canonical("https://example.com/p?gclid=1"), "https://example.com/p");
+        assert_eq!(
+            canonical("https://example.com/cart;jsessionid=ABC123?PHPSESSID=9&id=4"),
+            "https://example.com/cart?id=4"
+        );
+    }
+
+    #[test]
+    fn normalizes_percent_encoding() {
+        assert_eq!(canonical("https://example.com/%7euser/a%2fb?q=%3d"), "https://example.com/~user/a%2Fb?q=%3D");
+    }
+
+    #[test]
+    fn folds_index_pages_and_trailing_slashes_when_enabled() {
+        let config = NormalizationConfig { strip_trailing_slash: true, fold_index: true, ..NormalizationConfig::default() };
+        let rules = UrlCanonicalizer::new(&config);
+        assert_eq!(rules.canonicalize("https://example.com/docs/index.html").unwrap(), "https://example.com/docs");
+        assert_eq!(rules.canonicalize("https://example.com/docs/").unwrap(), "https://example.com/docs");
+        assert_eq!(rules.canonicalize("https://example.com/index.html").unwrap(), "https://example.com/");
+        assert_eq!(canonical("https://example.com/docs/index.html"), "https://example.com/docs/index.html");
+    }
+
+    #[test]
+    fn keeps_query_order_when_sorting_is_disabled() {
+        let config = NormalizationConfig { sort_query: false, ..NormalizationConfig::default() };
+        let rules = UrlCanonicalizer::new(&config);
+        assert_eq!(rules.canonicalize("https://example.com/?b=1&a=2").unwrap(), "https://example.com/?b=1&a=2");
+    }
+}
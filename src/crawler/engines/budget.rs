@@ -0,0 +1,144 @@
+//! Page, byte and time limits that end a crawl early
+
+use crate::config::BudgetConfig;
+use crate::models::StopReason;
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use url::Url;
+
+/// Outcome of asking the budget for permission to fetch a URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// Fetch the URL
+    Granted,
+    /// Fetch the URL, then stop: it used up the rest of the budget
+    Last(StopReason),
+    /// Leave the URL out; its host or directory cap is reached
+    Skip(&'static str),
+    /// Do not fetch; the crawl is out of budget
+    Exhausted(StopReason),
+}
+
+/// Counters checked against a `BudgetConfig`, shared by all workers
+#[derive(Debug, Default)]
+pub struct CrawlBudget {
+    config: BudgetConfig,
+    pages: AtomicUsize,
+    bytes: AtomicU64,
+    per_host: DashMap<String, usize>,
+    per_dir: DashMap<String, usize>,
+}
+
+impl CrawlBudget {
+    pub fn new(config: BudgetConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Count a fetch of `url` against the page caps
+    pub fn claim(&self, url: &str) -> Claim {
+        let host_limit = self.config.max_pages_per_host;
+        let dir_limit = self.config.max_pages_per_dir;
+        if (host_limit.is_some() || dir_limit.is_some())
+            && let Ok(parsed) = Url::parse(url)
+        {
+            let host = parsed.host_str().unwrap_or_default();
+            // Both entries stay locked (host first) so a skip counts against neither
+            let mut host_count = host_limit.map(|_| self.per_host.entry(host.to_string()).or_insert(0));
+            if let (Some(limit), Some(count)) = (host_limit, &host_count)
+                && **count >= limit
+            {
+                return Claim::Skip("per-host page budget reached");
+            }
+            let mut dir_count = dir_limit.map(|_| self.per_dir.entry(Self::dir_key(host, parsed.path())).or_insert(0));
+            if let (Some(limit), Some(count)) = (dir_limit, &dir_count)
+                && **count >= limit
+            {
+                return Claim::Skip("per-directory page budget reached");
+            }
+            for count in [host_count.as_mut(), dir_count.as_mut()].into_iter().flatten() {
+                **count += 1;
+            }
+        }
+        self.claim_page()
+    }
+
+    /// Add downloaded body bytes; returns `MaxBytes` once the byte cap is reached
+    pub fn record_bytes(&self, len: usize) -> Option<StopReason> {
+        let limit = self.config.max_bytes?;
+        let total = self.bytes.fetch_add(len as u64, Ordering::SeqCst) + len as u64;
+        (total >= limit).then_some(StopReason::MaxBytes)
+    }
+
+    /// Wall-clock limit for the whole crawl
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.config.max_time_secs.map(Duration::from_secs)
+    }
+
+    fn claim_page(&self) -> Claim {
+        let Some(limit) = self.config.max_pages else {
+            return Claim::Granted;
+        };
+        let claimed = self.pages.fetch_add(1, Ordering::SeqCst) + 1;
+        match claimed.cmp(&limit) {
+            std::cmp::Ordering::Less => Claim::Granted,
+            std::cmp::Ordering::Equal => Claim::Last(StopReason::MaxPages),
+            std::cmp::Ordering::Greater => Claim::Exhausted(StopReason::MaxPages),
+        }
+    }
+
+    /// Host plus the path up to its last `/`, so `/docs/a.html` and `/docs/` share a directory
+    fn dir_key(host: &str, path: &str) -> String {
+        let dir = path.rfind('/').map_or("/", |i| &path[..=i]);
+        format!("{}{}", host, dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(config: BudgetConfig) -> CrawlBudget {
+        CrawlBudget::new(config)
+    }
+
+    #[test]
+    fn page_cap_marks_last_page_then_exhausts() {
+        let budget = budget(BudgetConfig { max_pages: Some(2), ..BudgetConfig::default() });
+        assert_eq!(budget.claim("https://a.test/1"), Claim::Granted);
+        assert_eq!(budget.claim("https://a.test/2"), Claim::Last(StopReason::MaxPages));
+        assert_eq!(budget.claim("https://a.test/3"), Claim::Exhausted(StopReason::MaxPages));
+    }
+
+    #[test]
+    fn host_and_dir_caps_skip_without_using_page_budget() {
+        let budget = budget(BudgetConfig {
+            max_pages: Some(3),
+            max_pages_per_host: Some(2),
+            max_pages_per_dir: Some(1),
+            ..BudgetConfig::default()
+        });
+        assert_eq!(budget.claim("https://a.test/docs/a.html"), Claim::Granted);
+        assert_eq!(budget.claim("https://a.test/docs/"), Claim::Skip("per-directory page budget reached"));
+        assert_eq!(budget.claim("https://a.test/blog/a"), Claim::Granted);
+        assert_eq!(budget.claim("https://a.test/news/a"), Claim::Skip("per-host page budget reached"));
+        assert_eq!(budget.claim("https://b.test/"), Claim::Last(StopReason::MaxPages));
+    }
+
+    #[test]
+    fn byte_cap_reports_once_reached() {
+        let budget = budget(BudgetConfig { max_bytes: Some(100), ..BudgetConfig::default() });
+        assert_eq!(budget.record_bytes(60), None);
+        assert_eq!(budget.record_bytes(40), Some(StopReason::MaxBytes));
+    }
+
+    #[test]
+    fn unlimited_budget_always_grants() {
+        let budget = budget(BudgetConfig::default());
+        for _ in 0..10 {
+            assert_eq!(budget.claim("https://a.test/"), Claim::Granted);
+        }
+        assert_eq!(budget.record_bytes(usize::MAX >> 8), None);
+        assert_eq!(budget.time_limit(), None);
+    }
+}
@@ -1,18 +1,30 @@
 //! Core crawling engine with low-complexity methods
 
+use crate::blacklist::Blacklist;
 use crate::config::CrawlerConfig;
-use crate::models::PageInfo;
-use crate::http::HttpClient;
+use crate::models::{PageInfo, QueuedUrl, StopReason};
+use crate::http::{BodyDecoder, FetchResponse, HttpClient};
 use crate::extraction::HtmlProcessor;
-use crate::crawler::StandardUrlParser;
+use crate::crawler::{RobotsCache, ScopeVerdict, SitemapLoader, StandardUrlParser, TrapDetector, UrlFilter, UrlScope};
+use crate::filtering::FilterDecision;
 use crate::error::Result;
+use crate::io::{CrawlState, CrawlStateStore};
+use super::budget::{Claim, CrawlBudget};
+use super::frontier::Frontier;
+use super::shutdown::Shutdown;
+use super::stats::{millis, CrawlStats};
 use dashmap::DashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Semaphore};
-use tokio::task::JoinHandle;
-use std::collections::VecDeque;
+use std::time::Instant;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinSet;
+use std::collections::HashSet;
 use tokio::sync::Mutex;
 
+/// Discovered URLs waiting to enter the frontier before workers wait
+const URL_CHANNEL_CAPACITY: usize = 1024;
+
 /// Core crawling logic broken into focused methods
 pub struct CrawlEngine {
     config: Arc<CrawlerConfig>,
@@ -21,6 +33,19 @@ pub struct CrawlEngine {
     base_domain: Arc<String>,
     base_path: Arc<String>,
     url_parser: StandardUrlParser,
+    robots: Arc<RobotsCache>,
+    stats: Arc<CrawlStats>,
+    /// Stable ID of the worker slot running this engine clone
+    worker_id: usize,
+    blacklist: Arc<Blacklist>,
+    state_store: Option<Arc<CrawlStateStore>>,
+    resume_state: Option<Arc<CrawlState>>,
+    shutdown: Shutdown,
+    budget: Arc<CrawlBudget>,
+    scope: Arc<UrlScope>,
+    traps: Arc<TrapDetector>,
+    /// Pages that declared another URL canonical and were not saved
+    aliases: Arc<DashSet<String>>,
 }
 
 impl CrawlEngine {
@@ -30,7 +55,10 @@ impl CrawlEngine {
         html_processor: Arc<HtmlProcessor>,
         base_domain: Arc<String>,
         base_path: Arc<String>,
+        robots: Arc<RobotsCache>,
+        stats: Arc<CrawlStats>,
     ) -> Self {
+        let budget = Arc::new(CrawlBudget::new(config.budget.clone()));
         Self {
             config,
             http_client,
@@ -38,100 +66,256 @@ impl CrawlEngine {
             base_domain,
             base_path,
             url_parser: StandardUrlParser,
+            robots,
+            stats,
+            worker_id: 0,
+            blacklist: Arc::new(Blacklist::new()),
+            state_store: None,
+            resume_state: None,
+            shutdown: Shutdown::new(),
+            budget,
+            scope: Arc::new(UrlScope::default()),
+            traps: Arc::new(TrapDetector::default()),
+            aliases: Arc::new(DashSet::new()),
         }
     }
 
-    /// Main crawling loop with multi-threading 
-    pub async fn crawl_all(&self, tx: mpsc::UnboundedSender<PageInfo>) -> Result<()> {
+    /// Use a blacklist to filter URLs seeded from sitemaps
+    pub fn with_blacklist(mut self, blacklist: Arc<Blacklist>) -> Self {
+        self.blacklist = blacklist;
+        self
+    }
+
+    /// Journal progress to a state store, continuing from `resume_state` if given
+    pub fn with_state_store(mut self, store: Arc<CrawlStateStore>, resume_state: Option<Arc<CrawlState>>) -> Self {
+        self.state_store = Some(store);
+        self.resume_state = resume_state;
+        self
+    }
+
+    /// Restrict the crawl to `scope`; without one every URL is in scope
+    pub fn with_scope(mut self, scope: Arc<UrlScope>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Reject spider-trap URLs according to `traps`
+    pub fn with_traps(mut self, traps: Arc<TrapDetector>) -> Self {
+        self.traps = traps;
+        self
+    }
+
+    /// Stop dispatching URLs once `shutdown` is requested
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Main crawling loop with multi-threading. Ends when the frontier is
+    /// empty and nothing is in flight, or after in-flight fetches finish
+    /// once a shutdown was requested.
+    pub async fn crawl_all(&self, tx: mpsc::Sender<PageInfo>) -> Result<()> {
         let visited = Arc::new(DashSet::new());
-        let queue = Arc::new(Mutex::new(VecDeque::from([(self.config.base_url.clone(), 0)])));
-        let semaphore = Arc::new(Semaphore::new(self.config.worker_count));
-        let (url_tx, mut url_rx) = mpsc::unbounded_channel::<(String, usize)>();
-        
-        let start_time = std::time::Instant::now();
-        let mut idle_cycles = 0;
-        
+        let frontier = match &self.resume_state {
+            Some(state) => self.resume_frontier(state, &visited),
+            None => self.initial_frontier().await,
+        };
+        if let Some(store) = &self.state_store {
+            for item in &frontier {
+                store.record_queued(item);
+            }
+        }
+        let queue = {
+            let mut queue = Frontier::from_config(&self.config)?;
+            for item in frontier {
+                queue.push(item);
+            }
+            self.stats.observe_frontier(queue.len(), queue.total_spilled());
+            Arc::new(Mutex::new(queue))
+        };
+        let (url_tx, mut url_rx) = mpsc::channel::<QueuedUrl>(URL_CHANNEL_CAPACITY);
+        // URLs sent by workers that the queue handler has not pushed yet
+        let in_transit = Arc::new(AtomicUsize::new(0));
+        // Signalled whenever the queue handler pushes a URL
+        let queued = Arc::new(Notify::new());
+
         // Spawn URL queue handler
         let queue_clone = Arc::clone(&queue);
+        let queue_store = self.state_store.clone();
+        let queue_stats = Arc::clone(&self.stats);
+        let queue_in_transit = Arc::clone(&in_transit);
+        let queue_notify = Arc::clone(&queued);
         let queue_handle = tokio::spawn(async move {
-            while let Some((url, depth)) = url_rx.recv().await {
+            while let Some(item) = url_rx.recv().await {
+                if let Some(store) = &queue_store {
+                    store.record_queued(&item);
+                }
                 let mut q = queue_clone.lock().await;
-                q.push_back((url, depth));
+                q.push(item);
+                queue_stats.observe_frontier(q.len(), q.total_spilled());
+                drop(q);
+                queue_in_transit.fetch_sub(1, Ordering::SeqCst);
+                queue_notify.notify_one();
             }
         });
-        
-        let mut active_workers = 0;
-        let mut handles: Vec<JoinHandle<()>> = Vec::new();
-        
+
+        // Flush the state journal periodically so idle periods are persisted too
+        let flush_handle = self.state_store.clone().map(|store| {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(crate::io::crawl_state::FLUSH_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    store.flush();
+                }
+            })
+        });
+
+        // Stop dispatching once the time budget runs out
+        let timer_handle = self.budget.time_limit().map(|limit| {
+            let shutdown = self.shutdown.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(limit).await;
+                if shutdown.request(StopReason::MaxTime) {
+                    log::warn!("Time budget of {:?} reached, finishing in-flight fetches", limit);
+                }
+            })
+        });
+
+        let mut workers: JoinSet<usize> = JoinSet::new();
+        // Worker slot IDs; a task takes one when spawned and hands it back when it finishes
+        let mut free_worker_ids: Vec<usize> = (0..self.config.worker_count).rev().collect();
+
         loop {
-            // Check if we can spawn more workers
-            if active_workers < self.config.worker_count {
-                let (url, depth) = {
-                    let mut q = queue.lock().await;
-                    if let Some(item) = q.pop_front() {
-                        item
-                    } else if active_workers == 0 {
-                        // No URLs to process and no active workers, we're done
-                        break;
-                    } else {
-                        // Wait for workers to finish and add more URLs
-                        drop(q);
-                        idle_cycles += 1;
-                        
-                        // Deadlock detection: if idle for too long, break out
-                        if idle_cycles > 200 { // 10 seconds at 50ms intervals
-                            log::warn!("Potential deadlock detected, terminating crawl after {} idle cycles", idle_cycles);
-                            break;
-                        }
-                        
-                        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-                        continue;
-                    }
+            let stopping = self.shutdown.is_requested();
+
+            // Start workers for queued URLs while slots are free
+            while !stopping && let Some(&worker_id) = free_worker_ids.last() {
+                let Some(item) = queue.lock().await.pop_front() else {
+                    break;
                 };
-                
-                // Reset idle counter since we found work
-                idle_cycles = 0;
-                
-                // Spawn worker
-                let permit = semaphore.clone().acquire_owned().await?;
+                free_worker_ids.pop();
                 let visited_clone = Arc::clone(&visited);
                 let tx_clone = tx.clone();
                 let url_tx_clone = url_tx.clone();
-                let engine_clone = self.clone_for_worker();
-                
-                active_workers += 1;
-                let handle = tokio::spawn(async move {
-                    let _permit = permit; // Keep permit alive
-                    if let Some(new_urls) = engine_clone.process_single_url(&url, depth, &visited_clone, tx_clone).await {
-                        for new_url in new_urls {
-                            let _ = url_tx_clone.send(new_url);
+                let in_transit_clone = Arc::clone(&in_transit);
+                let engine_clone = self.clone_for_worker(worker_id);
+
+                workers.spawn(async move {
+                    if let Some(new_urls) = engine_clone.process_single_url(&item, &visited_clone, tx_clone).await {
+                        for (new_url, new_depth) in new_urls {
+                            // Counted before sending so the crawl cannot look finished while it is in the channel
+                            in_transit_clone.fetch_add(1, Ordering::SeqCst);
+                            if url_tx_clone.send(QueuedUrl::new(new_url, new_depth)).await.is_err() {
+                                in_transit_clone.fetch_sub(1, Ordering::SeqCst);
+                            }
+                            engine_clone.stats.observe_pending_urls(url_tx_clone.max_capacity() - url_tx_clone.capacity());
                         }
                     }
+                    worker_id
                 });
-                handles.push(handle);
-            } else {
-                // Wait for at least one worker to complete
-                if let Some(handle) = handles.pop() {
-                    handle.await?;
-                    active_workers -= 1;
+            }
+
+            if workers.is_empty() {
+                // Done once nothing is being fetched, sent or queued
+                if stopping || (in_transit.load(Ordering::SeqCst) == 0 && queue.lock().await.len() == 0) {
+                    break;
+                }
+                tokio::select! {
+                    _ = queued.notified() => {}
+                    _ = self.shutdown.requested() => {}
                 }
+                continue;
+            }
+
+            if stopping {
+                // Let in-flight fetches finish without starting new ones
+                if let Some(joined) = workers.join_next().await {
+                    free_worker_ids.push(joined?);
+                }
+                continue;
+            }
+
+            // Wait for a worker to finish, more URLs to be queued or a shutdown request
+            tokio::select! {
+                Some(joined) = workers.join_next() => free_worker_ids.push(joined?),
+                _ = queued.notified() => {}
+                _ = self.shutdown.requested() => {}
             }
         }
-        
-        // Wait for all workers to complete
-        for handle in handles {
-            handle.await?;
-        }
-        
+
         // Close URL queue and wait for handler
         drop(url_tx);
         queue_handle.await?;
-        
+
+        for handle in [flush_handle, timer_handle].into_iter().flatten() {
+            handle.abort();
+        }
+        if let Some(store) = &self.state_store {
+            store.flush();
+        }
+
+        if self.shutdown.is_requested() {
+            let remaining = queue.lock().await.len();
+            match &self.state_store {
+                Some(store) => log::warn!(
+                    "Crawl stopped with {} URLs left; resume with --resume (state in {})",
+                    remaining,
+                    store.path().display()
+                ),
+                None => log::warn!(
+                    "Crawl stopped with {} URLs left; use --save or --state to make it resumable",
+                    remaining
+                ),
+            }
+        }
+
         Ok(())
     }
-    
+
+    /// Restore the visited set from a previous run and return its remaining frontier
+    fn resume_frontier(&self, state: &CrawlState, visited: &DashSet<String>) -> Vec<QueuedUrl> {
+        for url in state.visited.keys() {
+            visited.insert(url.clone());
+        }
+        log::info!(
+            "Resuming crawl: {} URLs already visited, {} queued, {} filtered",
+            state.visited.len(), state.frontier.len(), state.filtered.len()
+        );
+        state.frontier.clone()
+    }
+
+    /// Base URL plus any sitemap URLs that pass scope and blacklist checks
+    async fn initial_frontier(&self) -> Vec<QueuedUrl> {
+        let base_url = self.url_parser.normalize_url(&self.config.base_url);
+        let mut frontier = vec![QueuedUrl::new(base_url.clone(), 0)];
+        if self.config.sitemaps.is_empty() && !self.config.discover_sitemaps {
+            return frontier;
+        }
+
+        let loader = SitemapLoader::new(Arc::clone(&self.http_client), Arc::clone(&self.robots));
+        let entries = loader
+            .load(&self.config.base_url, &self.config.sitemaps, self.config.discover_sitemaps)
+            .await;
+
+        let mut seen = HashSet::from([base_url]);
+        for entry in entries {
+            let url = self.url_parser.normalize_url(&entry.url);
+            if self.is_blacklisted(&url) || !self.should_add_url_to_queue(&url, 0) {
+                continue;
+            }
+            if seen.insert(url.clone()) {
+                frontier.push(QueuedUrl { url, depth: 0, lastmod: entry.lastmod });
+            }
+        }
+
+        if frontier.len() > 1 {
+            log::info!("Seeded {} URLs from sitemaps", frontier.len() - 1);
+        }
+        frontier
+    }
+
     /// Create a clone suitable for worker tasks
-    fn clone_for_worker(&self) -> Self {
+    fn clone_for_worker(&self, worker_id: usize) -> Self {
         Self {
             config: Arc::clone(&self.config),
             http_client: Arc::clone(&self.http_client),
@@ -139,198 +323,426 @@ impl CrawlEngine {
             base_domain: Arc::clone(&self.base_domain),
             base_path: Arc::clone(&self.base_path),
             url_parser: StandardUrlParser,
+            robots: Arc::clone(&self.robots),
+            stats: Arc::clone(&self.stats),
+            worker_id,
+            blacklist: Arc::clone(&self.blacklist),
+            state_store: self.state_store.clone(),
+            resume_state: None,
+            shutdown: self.shutdown.clone(),
+            budget: Arc::clone(&self.budget),
+            scope: Arc::clone(&self.scope),
+            traps: Arc::clone(&self.traps),
+            aliases: Arc::clone(&self.aliases),
         }
     }
 
     /// Process a single URL and return new URLs to add to queue
     async fn process_single_url(
         &self,
-        url: &str,
-        depth: usize,
+        item: &QueuedUrl,
         visited: &DashSet<String>,
-        tx: mpsc::UnboundedSender<PageInfo>,
+        tx: mpsc::Sender<PageInfo>,
     ) -> Option<Vec<(String, usize)>> {
+        let (url, depth) = (item.url.as_str(), item.depth);
+
         // Fast validation checks first (before any locking)
         if !self.should_process_url_fast(url, depth) {
             return None;
         }
 
+        // Fetches robots.txt for the host on first use
+        if !self.robots.is_allowed(url).await {
+            self.record_robots_skip(url);
+            return None;
+        }
+
         // Atomic insert-if-not-present to prevent race conditions
         if !visited.insert(url.to_string()) {
             // URL already processed by another worker
             return None;
         }
 
-        log::debug!("Fetching URL: {} at depth {}", url, depth);
+        if !self.claim_trap_caps(url) || !self.claim_budget(url) {
+            return None;
+        }
+
+        log::debug!("Fetching URL: {} at depth {} (worker {})", url, depth, self.worker_id);
 
-        // Fetch the URL
+        let started = Instant::now();
+        let new_urls = self.fetch_and_process(item, visited, &tx).await;
+        self.stats.update_worker(self.worker_id, |w| w.processing_time_ms += millis(started.elapsed()));
+        new_urls
+    }
+
+    /// Fetch a claimed URL, emit its page and return the links to queue
+    async fn fetch_and_process(
+        &self,
+        item: &QueuedUrl,
+        visited: &DashSet<String>,
+        tx: &mpsc::Sender<PageInfo>,
+    ) -> Option<Vec<(String, usize)>> {
+        let (url, depth) = (item.url.as_str(), item.depth);
+        let fetch_started = Instant::now();
         match self.http_client.fetch(url).await {
-            Ok((status, content_type, content_length, body)) => {
-                if content_type.contains("text/html") {
-                    self.process_html_response(url, status, content_type, content_length, body, depth, visited, &tx)
+            Ok(response) => {
+                self.stats.update_worker(self.worker_id, |w| {
+                    w.pages_processed += 1;
+                    w.fetch_time_ms += millis(fetch_started.elapsed());
+                });
+                if response.status >= 400 {
+                    self.stats.record_error(self.worker_id, url, format!("http status: {}", response.status));
+                }
+                log::debug!(
+                    "Fetched {} ({}) in {:?} from {}",
+                    response.final_url,
+                    response.status,
+                    response.elapsed,
+                    response.remote_addr.map_or_else(|| "unknown address".to_string(), |a| a.to_string())
+                );
+                self.record_visited(url, depth, Some(response.status));
+                if let Some(reason) = self.budget.record_bytes(response.body.len()) {
+                    self.stop_for_budget(reason);
+                }
+                let page_url = self.follow_redirects(item, &response, visited, tx).await?;
+                // Links are only followed from pages inside the scope, not from external
+                // assets; stylesheets still lead to the assets they use
+                if response.content_type().contains("text/html") && self.scope.check(&page_url) == ScopeVerdict::Crawl {
+                    self.process_html_response(item, page_url, response, visited, tx).await
                 } else {
-                    self.process_non_html_response(url, status, content_type, content_length, body, &tx);
-                    None
+                    self.process_non_html_response(item, page_url, response, visited, tx).await
                 }
             }
             Err(e) => {
                 log::warn!("Fetch error for {}: {}", url, e);
+                self.stats.update_worker(self.worker_id, |w| w.fetch_time_ms += millis(fetch_started.elapsed()));
+                self.stats.record_error(self.worker_id, url, format!("{}: {}", e.category(), e));
+                self.record_visited(url, depth, None);
                 None
             }
         }
     }
 
-    /// Check if URL should be processed (without marking as visited)
-    fn should_process_url_fast(&self, url: &str, depth: usize) -> bool {
-        if depth > self.config.max_depth {
-            return false;
+    /// Emit an entry for each redirect hop and claim the final URL.
+    /// Returns the URL the page content belongs to, or `None` if another
+    /// worker already fetched it or it lies outside the crawl scope.
+    async fn follow_redirects(
+        &self,
+        item: &QueuedUrl,
+        response: &FetchResponse,
+        visited: &DashSet<String>,
+        tx: &mpsc::Sender<PageInfo>,
+    ) -> Option<String> {
+        if response.redirects.is_empty() {
+            return Some(item.url.clone());
         }
 
-        if !self.is_url_in_scope(url) {
-            return false;
+        let final_url = self.url_parser.normalize_url(&response.final_url);
+        if !self.is_url_in_scope(&final_url) {
+            log::debug!("Skipping redirect out of scope: {} -> {}", item.url, final_url);
+            self.stats.record_skipped(&item.url, format!("redirects out of scope to {}", final_url));
+            return None;
+        }
+
+        for hop in &response.redirects {
+            let hop_url = self.url_parser.normalize_url(&hop.url);
+            if hop_url == final_url {
+                // e.g. `/docs` -> `/docs/` when trailing slashes are folded
+                continue;
+            }
+            self.emit(tx, PageInfo::redirect(hop_url, hop.status, final_url.clone(), item.depth)).await;
+        }
+
+        if final_url != item.url && !visited.insert(final_url.clone()) {
+            log::debug!("Redirect target already crawled: {} -> {}", item.url, final_url);
+            return None;
+        }
+        self.record_visited(&final_url, item.depth, Some(response.status));
+        Some(final_url)
+    }
+
+    /// Count a fetch against the spider-trap pattern caps. Returns false if
+    /// a cap is reached; such URLs do not use up the crawl budget.
+    fn claim_trap_caps(&self, url: &str) -> bool {
+        let decision = self.traps.claim(url);
+        if decision.is_accepted() {
+            return true;
+        }
+        log::debug!("Skipping {}: {}", url, decision);
+        self.stats.record_filter(&decision);
+        self.stats.record_skipped(url, decision.to_string());
+        false
+    }
+
+    /// Count a fetch against the crawl budget. Returns false if the URL
+    /// must not be fetched, stopping the crawl when the budget is used up.
+    fn claim_budget(&self, url: &str) -> bool {
+        match self.budget.claim(url) {
+            Claim::Granted => true,
+            Claim::Last(reason) => {
+                self.stop_for_budget(reason);
+                true
+            }
+            Claim::Skip(reason) => {
+                log::debug!("Skipping {}: {}", url, reason);
+                self.stats.record_skipped(url, reason);
+                false
+            }
+            Claim::Exhausted(reason) => {
+                self.stop_for_budget(reason);
+                false
+            }
         }
+    }
+
+    /// Stop dispatching new URLs because a budget ran out
+    fn stop_for_budget(&self, reason: StopReason) {
+        if self.shutdown.request(reason) {
+            log::warn!("Budget {} reached, finishing in-flight fetches", reason.as_str());
+        }
+    }
 
-        if self.url_parser.is_recursive_url(url) {
+    /// Hand a page to the save stage, waiting while its queue is full
+    async fn emit(&self, tx: &mpsc::Sender<PageInfo>, page_info: PageInfo) {
+        if tx.send(page_info).await.is_ok() {
+            self.stats.observe_pending_pages(tx.max_capacity() - tx.capacity());
+        }
+    }
+
+    /// Check if URL should be processed (without marking as visited)
+    fn should_process_url_fast(&self, url: &str, depth: usize) -> bool {
+        if !self.url_filter().check(url, depth).is_accepted() {
             return false;
         }
 
-        if url.len() > 500 {
+        if self.robots.cached_verdict(url) == Some(false) {
+            self.record_robots_skip(url);
             return false;
         }
 
         true
     }
 
-    /// Check if a URL is within the defined scope
-    fn is_url_in_scope(&self, url: &str) -> bool {
-        if self.config.allowed_domains.is_empty() {
-            // No domain restrictions - allow all domains
-            true
-        } else {
-            // Check if URL matches any allowed domain pattern
-            self.config.allowed_domains.iter().any(|pattern| {
-                self.matches_domain_pattern(url, pattern)
-            })
+    /// Remember a URL that robots.txt disallowed so it shows up in the report
+    fn record_robots_skip(&self, url: &str) {
+        log::debug!("Skipping disallowed by robots.txt: {}", url);
+        self.stats.record_filter(&FilterDecision::reject("robots"));
+        self.stats.record_skipped(url, "disallowed by robots.txt");
+        self.record_filtered(url, "disallowed by robots.txt");
+    }
+
+    /// Journal a fetch outcome if crawl state is being persisted
+    fn record_visited(&self, url: &str, depth: usize, status: Option<u16>) {
+        if let Some(store) = &self.state_store {
+            store.record_visited(url, depth, status);
         }
     }
 
-    /// Check if URL matches a domain pattern (supports wildcards like *.google.com)
-    fn matches_domain_pattern(&self, url: &str, pattern: &str) -> bool {
-        if let Ok(parsed_url) = url::Url::parse(url) {
-            if let Some(host) = parsed_url.host_str() {
-                if pattern.starts_with("*.") {
-                    // Wildcard pattern: *.google.com matches google.com, sub.google.com, etc.
-                    let domain_suffix = &pattern[2..]; // Remove "*."
-                    host.ends_with(domain_suffix) && 
-                    (host == domain_suffix || host.ends_with(&format!(".{}", domain_suffix)))
-                } else {
-                    // Exact domain match
-                    host == pattern
-                }
-            } else {
-                false
-            }
-        } else {
-            false
+    /// Journal a rejected URL if crawl state is being persisted
+    fn record_filtered(&self, url: &str, reason: &str) {
+        if let Some(store) = &self.state_store {
+            store.record_filtered(url, reason);
         }
     }
 
+    /// Check if a URL may be fetched, either as a page or as an external asset
+    fn is_url_in_scope(&self, url: &str) -> bool {
+        self.scope.check(url) != ScopeVerdict::Out
+    }
+
+    /// Depth, scope, length and recursion checks for discovered URLs
+    fn url_filter(&self) -> UrlFilter<'_> {
+        UrlFilter::new(self.config.max_depth, &self.scope, &self.traps)
+    }
+
+    /// Check a page URL against the blacklist, counting a rejection
+    fn is_blacklisted(&self, url: &str) -> bool {
+        let decision = self.blacklist.check(url, &Default::default());
+        self.stats.record_filter(&decision);
+        !decision.is_accepted()
+    }
+
     /// Process HTML response and extract links
-    fn process_html_response(
+    async fn process_html_response(
         &self,
-        url: &str,
-        status: u16,
-        content_type: String,
-        content_length: Option<usize>,
-        body: String,
-        depth: usize,
+        item: &QueuedUrl,
+        page_url: String,
+        response: FetchResponse,
         visited: &DashSet<String>,
-        tx: &mpsc::UnboundedSender<PageInfo>,
+        tx: &mpsc::Sender<PageInfo>,
     ) -> Option<Vec<(String, usize)>> {
-        match self.html_processor.process(
-            url,
-            &body,
-            depth + 1,
+        // Links are resolved against the final URL as served, since the
+        // canonical page URL may have lost a trailing slash or index page
+        let url = page_url.as_str();
+        let content_type = response.content_type();
+        let text = BodyDecoder::decode(&content_type, &response.body).unwrap_or_default();
+        let parse_started = Instant::now();
+        let parsed = self.html_processor.process(
+            &response.final_url,
+            &text,
+            item.depth + 1,
             &self.base_domain,
             &self.base_path,
-        ) {
-            Ok((links, title, discovered)) => {
-                let filtered_urls = self.filter_discovered_urls(discovered.clone(), visited);
-                if filtered_urls.len() > 0 {
+        );
+        self.stats.update_worker(self.worker_id, |w| w.parse_time_ms += millis(parse_started.elapsed()));
+        match parsed {
+            Ok((links, title, discovered, canonical)) => {
+                self.stats.update_worker(self.worker_id, |w| w.total_links_found += discovered.len());
+                let mut filtered_urls = self.filter_discovered_urls(url, discovered.clone(), visited);
+                if !filtered_urls.is_empty() {
                     println!("Found {} new URLs to crawl from: {}", filtered_urls.len(), url);
                 } else {
                     log::debug!("Found {} URLs (0 new) from: {}", discovered.len(), url);
                 }
 
+                let declared = canonical.or_else(|| response.canonical_link());
+                if let Some(canonical) = self.alias_target(url, declared, item.depth) {
+                    log::debug!("{} declares {} canonical, keeping it as an alias", url, canonical);
+                    if !visited.contains(&canonical) {
+                        filtered_urls.push((canonical.clone(), item.depth));
+                    }
+                    let fetch_ms = response.elapsed.as_millis() as u64;
+                    self.emit(tx, PageInfo::alias(page_url, response.status, canonical, item.depth, fetch_ms)).await;
+                    return Some(filtered_urls);
+                }
+
                 // Create page info and send via channel
                 let page_info = PageInfo {
-                    url: url.to_string(),
-                    status_code: status,
+                    url: page_url.clone(),
+                    status_code: response.status,
                     content_type,
-                    content_length,
+                    content_length: response.content_length(),
                     title,
                     links,
-                    content: body,
+                    content: response.body,
+                    text: Some(text),
+                    lastmod: item.lastmod.clone(),
+                    redirect_to: None,
+                    canonical: None,
+                    depth: item.depth,
+                    fetch_ms: response.elapsed.as_millis() as u64,
                 };
-                let _ = tx.send(page_info);
+                self.emit(tx, page_info).await;
 
                 // Return filtered URLs
                 Some(filtered_urls)
             }
             Err(e) => {
                 log::warn!("HTML processing error for {}: {}", url, e);
+                self.stats.record_error(self.worker_id, url, format!("parse: {}", e));
                 None
             }
         }
     }
 
-    /// Process non-HTML response
-    fn process_non_html_response(
+    /// Process non-HTML response. Returns the canonical URL to queue if a
+    /// `Link` header made the resource an alias of it, or the new URLs a
+    /// stylesheet refers to.
+    async fn process_non_html_response(
         &self,
-        url: &str,
-        status: u16,
-        content_type: String,
-        content_length: Option<usize>,
-        body: String,
-        tx: &mpsc::UnboundedSender<PageInfo>,
-    ) {
+        item: &QueuedUrl,
+        page_url: String,
+        response: FetchResponse,
+        visited: &DashSet<String>,
+        tx: &mpsc::Sender<PageInfo>,
+    ) -> Option<Vec<(String, usize)>> {
+        if let Some(canonical) = self.alias_target(&page_url, response.canonical_link(), item.depth) {
+            let queue = (!visited.contains(&canonical)).then(|| vec![(canonical.clone(), item.depth)]);
+            let fetch_ms = response.elapsed.as_millis() as u64;
+            self.emit(tx, PageInfo::alias(page_url, response.status, canonical, item.depth, fetch_ms)).await;
+            return queue;
+        }
+        let content_type = response.content_type();
+        let text = BodyDecoder::decode(&content_type, &response.body);
+        // Stylesheets lead to their images, fonts and imports
+        let discovered = match &text {
+            Some(css) if content_type.contains("text/css") => {
+                self.html_processor.process_css(&response.final_url, css, item.depth + 1)
+            }
+            _ => Vec::new(),
+        };
+        self.stats.update_worker(self.worker_id, |w| w.total_links_found += discovered.len());
+        let filtered_urls = self.filter_discovered_urls(&page_url, discovered.clone(), visited);
+        if !filtered_urls.is_empty() {
+            log::debug!("Found {} new URLs to crawl from: {}", filtered_urls.len(), page_url);
+        }
         let page_info = PageInfo {
-            url: url.to_string(),
-            status_code: status,
+            url: page_url,
+            status_code: response.status,
+            text,
             content_type,
-            content_length,
+            content_length: response.content_length(),
             title: None,
-            links: Vec::new(),
-            content: body,
+            links: discovered.into_iter().map(|(url, _)| url).collect(),
+            content: response.body,
+            lastmod: item.lastmod.clone(),
+            redirect_to: None,
+            canonical: None,
+            depth: item.depth,
+            fetch_ms: response.elapsed.as_millis() as u64,
         };
-        let _ = tx.send(page_info);
+        self.emit(tx, page_info).await;
+        (!filtered_urls.is_empty()).then_some(filtered_urls)
+    }
+
+    /// Normalized canonical URL when a page declares another crawlable URL
+    /// canonical, making the page an alias of it. A page whose canonical is
+    /// itself an alias is kept, so canonical cycles still save one copy.
+    fn alias_target(&self, page_url: &str, declared: Option<String>, depth: usize) -> Option<String> {
+        let canonical = self.url_parser.normalize_url(&declared?);
+        if canonical == page_url
+            || self.is_blacklisted(&canonical)
+            || !self.should_add_url_to_queue(&canonical, depth)
+        {
+            return None;
+        }
+        // Claim the alias before checking the target, so two pages naming each
+        // other concurrently both end up kept rather than both dropped
+        self.aliases.insert(page_url.to_string());
+        if self.aliases.contains(&canonical) {
+            self.aliases.remove(page_url);
+            return None;
+        }
+        Some(canonical)
     }
 
-    /// Filter discovered URLs based on validation rules
-    fn filter_discovered_urls(&self, discovered: Vec<(String, usize)>, visited: &DashSet<String>) -> Vec<(String, usize)> {
+    /// Filter URLs discovered on `page_url` based on validation rules
+    fn filter_discovered_urls(
+        &self,
+        page_url: &str,
+        discovered: Vec<(String, usize)>,
+        visited: &DashSet<String>,
+    ) -> Vec<(String, usize)> {
         discovered.into_iter()
-            .filter(|(u, d)| self.should_add_url_to_queue(u, *d) && !visited.contains(u))
+            .filter(|(u, d)| {
+                self.should_add_url_to_queue(u, *d) && !visited.contains(u) && self.follows_no_trap(page_url, u)
+            })
             .collect()
     }
 
-    /// Check if URL should be added to crawling queue
-    fn should_add_url_to_queue(&self, url: &str, depth: usize) -> bool {
-        if depth > self.config.max_depth {
-            return false;
-        }
-
-        if !self.is_url_in_scope(url) {
-            log::debug!("Skipping out of scope: {}", url);
-            return false;
+    /// Check a link against incrementing-number chains that started on earlier pages
+    fn follows_no_trap(&self, page_url: &str, link: &str) -> bool {
+        let decision = self.traps.follow_link(page_url, link);
+        if !decision.is_accepted() {
+            log::debug!("Skipping {}: {}", link, decision);
+            self.stats.record_filter(&decision);
+            self.record_filtered(link, &decision.to_string());
         }
+        decision.is_accepted()
+    }
 
-        if url.len() > 500 {
-            log::debug!("Skipping long URL ({}): {}", url.len(), url);
+    /// Check if URL should be added to crawling queue
+    fn should_add_url_to_queue(&self, url: &str, depth: usize) -> bool {
+        let decision = self.url_filter().check(url, depth);
+        if !decision.is_accepted() {
+            log::debug!("Skipping {}: {}", url, decision);
+            self.stats.record_filter(&decision);
+            self.record_filtered(url, &decision.to_string());
             return false;
         }
 
-        if self.url_parser.is_recursive_url(url) {
-            log::debug!("Skipping recursive: {}", url);
+        if self.robots.cached_verdict(url) == Some(false) {
+            self.record_robots_skip(url);
             return false;
         }
 
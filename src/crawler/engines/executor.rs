@@ -1,17 +1,21 @@
 //! Crawler execution coordination
 
-use std::collections::BTreeSet;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::{Instant, Duration};
 use log::info;
 use tokio::runtime::Runtime;
 
 use crate::blacklist::Blacklist;
-use crate::config::CrawlerConfig;
-use crate::crawler::CrawlerFactory;
-use crate::io::SiteSaver;
-use crate::models::CrawlResult;
+use crate::blacklist::loader::RELOAD_INTERVAL;
+use crate::config::{CrawlerConfig, OutputFormat, ReportFormat};
+use crate::crawler::{Crawler, CrawlerFactory, UrlCanonicalizer, UrlScope};
+use crate::crawler::engines::pipeline::PagePipeline;
+use crate::crawler::engines::shutdown::{interrupt_signal, Shutdown};
+use crate::io::{CrawlStateStore, SiteSaver, WarcWriter};
+use crate::io::crawl_state::STATE_FILE_NAME;
+use crate::models::{CrawlResult, StopReason};
+use crate::processing::{JsonLinesReport, ReportExporter};
 use crate::error::{AppError, Result};
 
 /// Executor handles the coordination of crawl execution
@@ -31,33 +35,164 @@ impl CrawlExecutor {
         // Log crawler startup
         Self::log_crawler_startup(config);
 
+        // Every URL key below, from the visited set to the mirror paths, uses these rules
+        UrlCanonicalizer::install(&config.normalization);
+
+        // Archive every fetch if WARC output was requested
+        let warc = Self::open_warc_writer(config, save_dir.as_deref())?;
+
+        // Pick up edits to the blacklist files while the crawl runs
+        Self::spawn_blacklist_reloader(&runtime, Arc::clone(&blacklist));
+
         // Create the crawler instance
         // We need to clone the config here since the factory expects an owned value
-        let crawler = CrawlerFactory::create_multi_threaded_with_blacklist(
+        let mut crawler = CrawlerFactory::create_multi_threaded_with_blacklist(
             config.clone(),
-            blacklist
+            blacklist,
+            warc.clone(),
         )?;
 
-        // Run the crawl
-        let (pages, elapsed) = Self::execute_crawl(&runtime, crawler)?;
+        // Journal crawl progress so an interrupted crawl can be resumed
+        let mut resumed = Vec::new();
+        if let Some(state_path) = Self::state_path(config, save_dir.as_deref()) {
+            let (store, resume_state) = CrawlStateStore::open(&state_path, config.resume)?;
+            if let Some(state) = &resume_state {
+                resumed = state.visited.keys().cloned().collect();
+            }
+            info!("Recording crawl state to {}", store.path().display());
+            crawler = crawler.with_state_store(Arc::new(store), resume_state);
+        } else if config.resume {
+            return Err(AppError::MissingArgument("--state or --save directory for --resume"));
+        }
 
-        // Save pages if requested
-        let errors = if let Some(save_dir) = save_dir {
-            Self::save_crawled_pages(&pages, save_dir, config)
-        } else {
-            Vec::new()
-        };
+        // The first Ctrl-C or SIGTERM stops dispatching, the second exits at once
+        let shutdown = Shutdown::new();
+        crawler = crawler.with_shutdown(shutdown.clone());
+        Self::spawn_signal_handler(&runtime, shutdown.clone());
+
+        // Pages are saved, archived and reported as they arrive
+        let report_format = config.report_path.as_deref().map(|path| {
+            config.report_format.unwrap_or_else(|| ReportFormat::from_path(path))
+        });
+        let pipeline = Self::build_pipeline(config, save_dir, warc, report_format, resumed)?;
+
+        // Run the crawl
+        let (pipeline, elapsed) = Self::execute_crawl(&runtime, &crawler, pipeline)?;
+        let stop_reason = shutdown.reason();
+        let duplicates = pipeline.duplicate_clusters();
+        let (fetch_errors, skipped) = (crawler.fetch_errors(), crawler.skipped_urls());
+        // Failed and skipped URLs keep their files in an incremental mirror
+        let unfinished = fetch_errors.iter().chain(&skipped).map(|(url, _)| url.clone()).collect();
+        let (pages, errors, mut live_report) = pipeline.finish(stop_reason.is_some(), unfinished);
 
         // Create the result
         let result = CrawlResult {
             pages,
-            errors: errors.into_iter().collect(),
-            worker_stats: Default::default(),
+            errors: fetch_errors.into_iter().chain(errors).collect(),
+            skipped,
+            filtered: crawler.filter_counts(),
+            worker_stats: crawler.worker_stats(),
+            cache_stats: crawler.cache_stats(),
+            queue_stats: crawler.queue_stats(),
+            stop_reason,
+            duplicates,
         };
 
+        if let (Some(path), Some(format)) = (&config.report_path, report_format) {
+            let written = match &mut live_report {
+                Some(report) => report.finish(&result, elapsed),
+                None => ReportExporter::write(path, format, &result, elapsed),
+            };
+            match written {
+                Ok(()) => info!("Wrote {:?} report to {}", format, path.display()),
+                Err(e) => log::error!("{}", e),
+            }
+        }
+
         Ok((result, elapsed))
     }
 
+    /// Request a shutdown on the first interrupt and exit on the second
+    fn spawn_signal_handler(runtime: &Runtime, shutdown: Shutdown) {
+        runtime.spawn(async move {
+            interrupt_signal().await;
+            log::warn!("Interrupted: finishing in-flight fetches and saving partial results (interrupt again to exit now)");
+            shutdown.request(StopReason::Interrupted);
+            interrupt_signal().await;
+            log::error!("Interrupted again, exiting without saving");
+            std::process::exit(130);
+        });
+    }
+
+    /// Periodically recompile the blacklist if one of its files changed
+    fn spawn_blacklist_reloader(runtime: &Runtime, blacklist: Arc<Blacklist>) {
+        if blacklist.sources().is_empty() {
+            return;
+        }
+        info!("Blacklist: {} rules from {} file(s)", blacklist.rule_count(), blacklist.sources().len());
+        runtime.spawn(async move {
+            let mut ticker = tokio::time::interval(RELOAD_INTERVAL);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let blacklist = Arc::clone(&blacklist);
+                // Parsing a large filter list should not stall a worker thread
+                let _ = tokio::task::spawn_blocking(move || blacklist.reload_if_changed()).await;
+            }
+        });
+    }
+
+    /// Open the WARC writer when `warc` is among the output formats
+    fn open_warc_writer(config: &CrawlerConfig, save_dir: Option<&Path>) -> Result<Option<Arc<WarcWriter>>> {
+        if !config.output_formats.contains(&OutputFormat::Warc) {
+            return Ok(None);
+        }
+        let dir = save_dir.ok_or(AppError::MissingArgument("--save directory for WARC output"))?;
+        let writer = WarcWriter::open(dir, config)?;
+        info!("Writing WARC files to {}", writer.dir().display());
+        Ok(Some(Arc::new(writer)))
+    }
+
+    /// Set up where crawled pages go: the mirror, WARC outlinks and a live JSON Lines report
+    fn build_pipeline(
+        config: &CrawlerConfig,
+        save_dir: Option<PathBuf>,
+        warc: Option<Arc<WarcWriter>>,
+        report_format: Option<ReportFormat>,
+        resumed: Vec<String>,
+    ) -> Result<PagePipeline> {
+        let mut pipeline = PagePipeline::new(&config.base_url);
+        if let Some(save_dir) = save_dir
+            && config.output_formats.contains(&OutputFormat::Mirror)
+        {
+            let scope = Arc::new(UrlScope::new(&config.allowed_domains, &config.scope)?);
+            let mut saver = SiteSaver::new(save_dir).with_scope(scope);
+            if config.incremental {
+                saver = saver.with_incremental(config.prune_mode);
+            }
+            if !resumed.is_empty() {
+                saver = saver.with_resumed(resumed, &config.base_url);
+            }
+            pipeline = pipeline.with_saver(saver);
+        }
+        if let Some(writer) = warc {
+            pipeline = pipeline.with_warc(writer);
+        }
+        // JSON Lines reports are written while crawling so they survive a crash
+        if let (Some(path), Some(ReportFormat::JsonLines)) = (&config.report_path, report_format) {
+            pipeline = pipeline.with_report(JsonLinesReport::create(path).map_err(AppError::Unknown)?);
+        }
+        Ok(pipeline)
+    }
+
+    /// Journal location: explicit `--state`, else inside the save directory
+    fn state_path(config: &CrawlerConfig, save_dir: Option<&Path>) -> Option<PathBuf> {
+        config
+            .state_path
+            .clone()
+            .or_else(|| save_dir.map(|dir| dir.join(STATE_FILE_NAME)))
+    }
+
     /// Log information about crawler startup
     fn log_crawler_startup(config: &CrawlerConfig) {
         info!(
@@ -68,64 +203,43 @@ impl CrawlExecutor {
         info!("Crawling URL: {}", config.base_url);
     }
 
-    /// Execute the crawl operation
+    /// Execute the crawl operation, feeding pages to the pipeline as they arrive.
+    /// The pipeline runs on a blocking thread, and the crawler's bounded channel
+    /// makes workers wait while it is busy.
     fn execute_crawl<C>(
         runtime: &Runtime,
-        crawler: C
-    ) -> Result<(BTreeSet<crate::models::PageInfo>, Duration)>
+        crawler: &C,
+        pipeline: PagePipeline,
+    ) -> Result<(PagePipeline, Duration)>
     where
-        C: crate::crawler::Crawler
+        C: Crawler
     {
         let start_time = Instant::now();
 
-        let pages = runtime.block_on(async {
-            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
-            let pages = Arc::new(tokio::sync::Mutex::new(BTreeSet::new()));
-            let pages_clone = pages.clone();
-            
-            // Spawn task to collect pages
-            let collector = tokio::spawn(async move {
-                while let Some(page_info) = rx.recv().await {
-                    let mut p = pages_clone.lock().await;
-                    p.insert(page_info);
-                }
-            });
-            
-            // Start crawling
-            let tx_clone = tx.clone();
-            let crawl_result = crawler.crawl_with_callback(move |page_info| {
-                let _ = tx_clone.send(page_info);
-            }).await;
-            
-            // Wait for collector to finish
-            drop(tx);
-            collector.await.map_err(|e| AppError::Join(e.to_string()))?;
-            
-            // Return pages
-            let pages = Arc::try_unwrap(pages).unwrap().into_inner();
-            crawl_result.map(|_| pages)
-        })?;
+        let pipeline = Arc::new(Mutex::new(pipeline));
+        let pipeline_clone = pipeline.clone();
+        let crawl_result = runtime.block_on(crawler.crawl_with_callback(move |page_info| {
+            pipeline_clone
+                .lock()
+                .unwrap_or_else(|e| {
+                    log::warn!("Page pipeline mutex poisoned, recovering");
+                    e.into_inner()
+                })
+                .accept(page_info);
+        }));
+
+        // The callback is dropped once the crawl returns, leaving the only reference here
+        let pipeline = Arc::try_unwrap(pipeline)
+            .map_err(|_| AppError::Crawler("page pipeline still in use after the crawl".to_string()))?
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = crawl_result {
+            pipeline.abandon();
+            return Err(e);
+        }
 
         let elapsed = Instant::now().duration_since(start_time);
 
-        Ok((pages, elapsed))
-    }
-
-    /// Save crawled pages to the filesystem
-    fn save_crawled_pages(
-        pages: &BTreeSet<crate::models::PageInfo>,
-        save_dir: PathBuf,
-        config: &CrawlerConfig
-    ) -> Vec<(String, String)> {
-        let mut errors = Vec::new();
-        let mut saver = SiteSaver::new(save_dir);
-
-        for page_info in pages {
-            if let Err(e) = saver.save_page_from_content(page_info, &config.base_url) {
-                errors.push((page_info.url.clone(), e));
-            }
-        }
-
-        errors
+        Ok((pipeline, elapsed))
     }
 }
@@ -0,0 +1,490 @@
+//! Crawl frontier: pluggable URL ordering over a bounded in-memory queue
+//! that overflows to disk
+
+use crate::config::{CrawlStrategy, CrawlerConfig};
+use crate::error::Result;
+use crate::models::QueuedUrl;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use tempfile::TempPath;
+
+/// One line of the overflow file
+#[derive(Serialize, Deserialize)]
+struct SpilledUrl {
+    url: String,
+    depth: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lastmod: Option<String>,
+}
+
+/// JSON Lines file read back from the front, or from the back when `lifo` is set
+struct SpillFile {
+    /// Removes the file when dropped
+    path: TempPath,
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    lifo: bool,
+    /// Start of each line still in the file (LIFO only)
+    offsets: Vec<u64>,
+    /// Where the next line is written
+    end: u64,
+}
+
+impl SpillFile {
+    fn create(lifo: bool) -> io::Result<Self> {
+        let file = tempfile::Builder::new().prefix("crawl-frontier-").suffix(".jsonl").tempfile()?;
+        let writer = BufWriter::new(file.reopen()?);
+        let reader = BufReader::new(file.reopen()?);
+        Ok(Self { path: file.into_temp_path(), writer, reader, lifo, offsets: Vec::new(), end: 0 })
+    }
+
+    fn push(&mut self, item: &QueuedUrl) -> io::Result<()> {
+        let line = SpilledUrl { url: item.url.clone(), depth: item.depth, lastmod: item.lastmod.clone() };
+        let mut line = serde_json::to_string(&line)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        if self.lifo {
+            self.offsets.push(self.end);
+        }
+        self.end += line.len() as u64;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> io::Result<Option<QueuedUrl>> {
+        self.writer.flush()?;
+        let mut line = String::new();
+        if self.lifo {
+            let Some(offset) = self.offsets.pop() else {
+                return Ok(None);
+            };
+            self.reader.seek(SeekFrom::Start(offset))?;
+            self.reader.read_line(&mut line)?;
+            // Drop the line so the next push overwrites it
+            self.writer.get_ref().set_len(offset)?;
+            self.writer.seek(SeekFrom::Start(offset))?;
+            self.end = offset;
+        } else if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let spilled: SpilledUrl = serde_json::from_str(&line)?;
+        Ok(Some(QueuedUrl { url: spilled.url, depth: spilled.depth, lastmod: spilled.lastmod }))
+    }
+}
+
+/// Order in which in-memory URLs leave the frontier
+pub trait FrontierOrder: Send {
+    fn push(&mut self, item: QueuedUrl);
+    fn pop(&mut self) -> Option<QueuedUrl>;
+    /// Remove the URL that would be popped last, to make room in memory
+    fn evict(&mut self) -> Option<QueuedUrl>;
+    /// Take back a URL evicted earlier
+    fn restore(&mut self, item: QueuedUrl) {
+        self.push(item);
+    }
+    /// Whether evicted URLs come back newest first rather than oldest first
+    fn restores_newest_first(&self) -> bool {
+        false
+    }
+    fn len(&self) -> usize;
+}
+
+/// Breadth-first: oldest URL first
+#[derive(Default)]
+pub struct BreadthFirst(VecDeque<QueuedUrl>);
+
+impl FrontierOrder for BreadthFirst {
+    fn push(&mut self, item: QueuedUrl) {
+        self.0.push_back(item);
+    }
+
+    fn pop(&mut self) -> Option<QueuedUrl> {
+        self.0.pop_front()
+    }
+
+    fn evict(&mut self) -> Option<QueuedUrl> {
+        self.0.pop_back()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Depth-first: newest URL first
+#[derive(Default)]
+pub struct DepthFirst(VecDeque<QueuedUrl>);
+
+impl FrontierOrder for DepthFirst {
+    fn push(&mut self, item: QueuedUrl) {
+        self.0.push_back(item);
+    }
+
+    fn pop(&mut self) -> Option<QueuedUrl> {
+        self.0.pop_back()
+    }
+
+    fn evict(&mut self) -> Option<QueuedUrl> {
+        self.0.pop_front()
+    }
+
+    /// Evicted URLs are older than everything in memory
+    fn restore(&mut self, item: QueuedUrl) {
+        self.0.push_front(item);
+    }
+
+    fn restores_newest_first(&self) -> bool {
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Scores URLs for best-first crawling; higher scores are crawled first
+pub struct UrlScorer {
+    priority_patterns: Vec<Regex>,
+}
+
+impl UrlScorer {
+    /// Extensions of pages rather than assets
+    const PAGE_EXTENSIONS: [&'static str; 7] = ["html", "htm", "shtml", "php", "asp", "aspx", "jsp"];
+
+    pub fn new(priority_patterns: &[String]) -> Result<Self> {
+        let priority_patterns = priority_patterns.iter().map(|p| Regex::new(p)).collect::<std::result::Result<_, _>>()?;
+        Ok(Self { priority_patterns })
+    }
+
+    /// URLs matching a priority pattern first, then pages before assets, then shorter paths
+    pub fn score(&self, item: &QueuedUrl) -> i64 {
+        let path = url::Url::parse(&item.url).map(|u| u.path().to_string()).unwrap_or_default();
+        let segments = path.split('/').filter(|s| !s.is_empty()).count() as i64;
+        let is_page = match path.rsplit('/').next().and_then(|name| name.rsplit_once('.')) {
+            Some((_, extension)) => Self::PAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()),
+            None => true,
+        };
+
+        let mut score = -10 * segments - item.depth as i64;
+        if is_page {
+            score += 1_000;
+        }
+        if self.priority_patterns.iter().any(|p| p.is_match(&item.url)) {
+            score += 100_000;
+        }
+        score
+    }
+}
+
+/// A URL with its score; ties go to the URL queued first
+struct Scored {
+    score: i64,
+    seq: Reverse<u64>,
+    item: QueuedUrl,
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.score, self.seq).cmp(&(other.score, other.seq))
+    }
+}
+
+/// Best-first: highest `UrlScorer` score first
+pub struct BestFirst {
+    scorer: UrlScorer,
+    /// Ordered lowest first, so both ends can be taken
+    queue: BTreeSet<Scored>,
+    next_seq: u64,
+}
+
+impl BestFirst {
+    pub fn new(scorer: UrlScorer) -> Self {
+        Self { scorer, queue: BTreeSet::new(), next_seq: 0 }
+    }
+}
+
+impl FrontierOrder for BestFirst {
+    fn push(&mut self, item: QueuedUrl) {
+        let score = self.scorer.score(&item);
+        self.queue.insert(Scored { score, seq: Reverse(self.next_seq), item });
+        self.next_seq += 1;
+    }
+
+    fn pop(&mut self) -> Option<QueuedUrl> {
+        self.queue.pop_last().map(|scored| scored.item)
+    }
+
+    fn evict(&mut self) -> Option<QueuedUrl> {
+        self.queue.pop_first().map(|scored| scored.item)
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/// Per-host round-robin: one URL from each host in turn, oldest first within a host
+#[derive(Default)]
+pub struct HostRoundRobin {
+    hosts: VecDeque<String>,
+    queues: HashMap<String, VecDeque<QueuedUrl>>,
+    len: usize,
+}
+
+impl FrontierOrder for HostRoundRobin {
+    fn push(&mut self, item: QueuedUrl) {
+        let host = url::Url::parse(&item.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let queue = self.queues.entry(host.clone()).or_default();
+        if queue.is_empty() {
+            self.hosts.push_back(host);
+        }
+        queue.push_back(item);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<QueuedUrl> {
+        let host = self.hosts.pop_front()?;
+        let queue = self.queues.get_mut(&host)?;
+        let item = queue.pop_front();
+        if queue.is_empty() {
+            self.queues.remove(&host);
+        } else {
+            self.hosts.push_back(host);
+        }
+        self.len -= 1;
+        item
+    }
+
+    /// The last URL of the longest host queue, which would be reached last
+    fn evict(&mut self) -> Option<QueuedUrl> {
+        let host = self
+            .hosts
+            .iter()
+            .enumerate()
+            .max_by_key(|(position, host)| (self.queues.get(*host).map_or(0, VecDeque::len), *position))
+            .map(|(_, host)| host.clone())?;
+        let queue = self.queues.get_mut(&host)?;
+        let item = queue.pop_back();
+        if queue.is_empty() {
+            self.queues.remove(&host);
+            self.hosts.retain(|h| *h != host);
+        }
+        self.len -= 1;
+        item
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// URLs to crawl, ordered by a `FrontierOrder`. At most `capacity` URLs are
+/// kept in memory; when more arrive, the ones the ordering would reach last
+/// go to a temporary file and rejoin the ordering as soon as memory frees up.
+pub struct Frontier {
+    memory: Box<dyn FrontierOrder>,
+    capacity: usize,
+    spill: Option<SpillFile>,
+    spilled: usize,
+    total_spilled: usize,
+}
+
+impl Frontier {
+    pub fn new(order: Box<dyn FrontierOrder>, capacity: usize) -> Self {
+        Self {
+            memory: order,
+            capacity: capacity.max(1),
+            spill: None,
+            spilled: 0,
+            total_spilled: 0,
+        }
+    }
+
+    /// Frontier using the configured strategy and in-memory limit
+    pub fn from_config(config: &CrawlerConfig) -> Result<Self> {
+        let order: Box<dyn FrontierOrder> = match config.strategy {
+            CrawlStrategy::Bfs => Box::new(BreadthFirst::default()),
+            CrawlStrategy::Dfs => Box::new(DepthFirst::default()),
+            CrawlStrategy::BestFirst => Box::new(BestFirst::new(UrlScorer::new(&config.priority_patterns)?)),
+            CrawlStrategy::RoundRobin => Box::new(HostRoundRobin::default()),
+        };
+        Ok(Self::new(order, config.max_frontier_in_memory))
+    }
+
+    /// URLs waiting, in memory and on disk
+    pub fn len(&self) -> usize {
+        self.memory.len() + self.spilled
+    }
+
+    /// URLs written to disk since the frontier was created
+    pub fn total_spilled(&self) -> usize {
+        self.total_spilled
+    }
+
+    /// Queue a URL. If the overflow file cannot be written, the URL is kept
+    /// in memory rather than lost.
+    pub fn push(&mut self, item: QueuedUrl) {
+        self.memory.push(item);
+        if self.memory.len() <= self.capacity {
+            return;
+        }
+        let Some(evicted) = self.memory.evict() else {
+            return;
+        };
+        match self.spill_file().and_then(|spill| spill.push(&evicted)) {
+            Ok(()) => {
+                self.spilled += 1;
+                self.total_spilled += 1;
+            }
+            Err(e) => {
+                log::warn!("Failed to spill frontier to disk, keeping {} in memory: {}", evicted.url, e);
+                self.memory.restore(evicted);
+            }
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<QueuedUrl> {
+        let item = self.memory.pop();
+        self.refill();
+        item
+    }
+
+    /// Move spilled URLs back into memory until it is full again
+    fn refill(&mut self) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        while self.spilled > 0 && self.memory.len() < self.capacity {
+            match spill.pop() {
+                Ok(Some(item)) => {
+                    self.spilled -= 1;
+                    self.memory.restore(item);
+                }
+                Ok(None) => {
+                    log::warn!("Frontier overflow file ended early, {} URLs lost", self.spilled);
+                    self.spilled = 0;
+                }
+                Err(e) => {
+                    log::warn!("Failed to read frontier overflow file, {} URLs lost: {}", self.spilled, e);
+                    self.spilled = 0;
+                }
+            }
+        }
+        if self.spilled == 0 {
+            // Start over with an empty file next time memory fills up
+            self.spill = None;
+        }
+    }
+
+    fn spill_file(&mut self) -> io::Result<&mut SpillFile> {
+        let spill = match self.spill.take() {
+            Some(spill) => spill,
+            None => {
+                let spill = SpillFile::create(self.memory.restores_newest_first())?;
+                log::info!("Frontier exceeded {} URLs, spilling to {}", self.capacity, spill.path.display());
+                spill
+            }
+        };
+        Ok(self.spill.insert(spill))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(order: &mut dyn FrontierOrder) -> Vec<String> {
+        std::iter::from_fn(|| order.pop()).map(|item| item.url).collect()
+    }
+
+    #[test]
+    fn test_spills_and_keeps_fifo_order() {
+        let mut frontier = Frontier::new(Box::new(BreadthFirst::default()), 2);
+        for i in 0..5 {
+            frontier.push(QueuedUrl::new(format!("https://a.test/{}", i), i));
+        }
+        assert_eq!(frontier.len(), 5);
+        assert_eq!(frontier.total_spilled(), 3);
+
+        let first = frontier.pop_front().unwrap();
+        assert_eq!(first.url, "https://a.test/0");
+        frontier.push(QueuedUrl::new("https://a.test/5".to_string(), 5));
+
+        let rest: Vec<usize> = std::iter::from_fn(|| frontier.pop_front()).map(|item| item.depth).collect();
+        assert_eq!(rest, vec![1, 2, 3, 4, 5]);
+        assert_eq!(frontier.len(), 0);
+    }
+
+    #[test]
+    fn test_strategies_keep_their_order_across_a_spill() {
+        let mut frontier = Frontier::new(Box::new(DepthFirst::default()), 2);
+        for i in 0..5 {
+            frontier.push(QueuedUrl::new(format!("https://a.test/{}", i), i));
+        }
+        assert_eq!(frontier.total_spilled(), 3);
+        let depths: Vec<usize> = std::iter::from_fn(|| frontier.pop_front()).map(|item| item.depth).collect();
+        assert_eq!(depths, vec![4, 3, 2, 1, 0]);
+
+        let scorer = UrlScorer::new(&["/docs/".to_string()]).unwrap();
+        let mut frontier = Frontier::new(Box::new(BestFirst::new(scorer)), 2);
+        for url in ["https://a.test/1.png", "https://a.test/2.png", "https://a.test/a", "https://a.test/3.png"] {
+            frontier.push(QueuedUrl::new(url.to_string(), 1));
+        }
+        frontier.push(QueuedUrl::new("https://a.test/docs/x".to_string(), 1));
+        assert_eq!(frontier.total_spilled(), 3);
+        let first: Vec<String> = (0..2).filter_map(|_| frontier.pop_front()).map(|item| item.url).collect();
+        assert_eq!(first, ["https://a.test/docs/x", "https://a.test/a"]);
+        assert_eq!(frontier.len(), 3);
+    }
+
+    #[test]
+    fn test_depth_first_pops_newest() {
+        let mut order = DepthFirst::default();
+        for url in ["https://a.test/1", "https://a.test/2"] {
+            order.push(QueuedUrl::new(url.to_string(), 1));
+        }
+        assert_eq!(urls(&mut order), ["https://a.test/2", "https://a.test/1"]);
+    }
+
+    #[test]
+    fn test_best_first_prefers_patterns_pages_and_short_paths() {
+        let mut order = BestFirst::new(UrlScorer::new(&["/docs/".to_string()]).unwrap());
+        for url in ["https://a.test/a/b/c", "https://a.test/logo.png", "https://a.test/a", "https://a.test/docs/x/y"] {
+            order.push(QueuedUrl::new(url.to_string(), 1));
+        }
+        assert_eq!(
+            urls(&mut order),
+            ["https://a.test/docs/x/y", "https://a.test/a", "https://a.test/a/b/c", "https://a.test/logo.png"]
+        );
+    }
+
+    #[test]
+    fn test_round_robin_alternates_hosts() {
+        let mut order = HostRoundRobin::default();
+        for url in ["https://a.test/1", "https://a.test/2", "https://a.test/3", "https://b.test/1"] {
+            order.push(QueuedUrl::new(url.to_string(), 1));
+        }
+        assert_eq!(order.len(), 4);
+        assert_eq!(urls(&mut order), ["https://a.test/1", "https://b.test/1", "https://a.test/2", "https://a.test/3"]);
+    }
+}
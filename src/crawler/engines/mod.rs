@@ -3,5 +3,10 @@
 pub mod core;
 pub mod tokio_crawler;
 pub mod executor;
+pub mod stats;
+pub mod pipeline;
+pub mod frontier;
+pub mod shutdown;
+pub mod budget;
 
 pub use executor::CrawlExecutor;
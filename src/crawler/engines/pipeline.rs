@@ -0,0 +1,147 @@
+//! Saves, archives and reports crawled pages as they arrive
+
+use crate::io::digest::sha1;
+use crate::io::mirror_manifest::MANIFEST_FILE_NAME;
+use crate::io::{SiteSaver, WarcWriter};
+use crate::models::PageInfo;
+use crate::processing::JsonLinesReport;
+use log::info;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Everything a crawled page is written to. Only page metadata is kept once a
+/// page has been handled, so memory does not grow with the size of the site.
+pub struct PagePipeline {
+    base_url: String,
+    saver: Option<SiteSaver>,
+    warc: Option<Arc<WarcWriter>>,
+    report: Option<JsonLinesReport>,
+    pages: BTreeSet<PageInfo>,
+    errors: Vec<(String, String)>,
+    /// URLs of successful responses by body digest
+    bodies: HashMap<[u8; 20], Vec<String>>,
+}
+
+impl PagePipeline {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            saver: None,
+            warc: None,
+            report: None,
+            pages: BTreeSet::new(),
+            errors: Vec::new(),
+            bodies: HashMap::new(),
+        }
+    }
+
+    /// Save pages into a mirror
+    pub fn with_saver(mut self, saver: SiteSaver) -> Self {
+        self.saver = Some(saver);
+        self
+    }
+
+    /// Add outlink metadata records to the WARC files
+    pub fn with_warc(mut self, writer: Arc<WarcWriter>) -> Self {
+        self.warc = Some(writer);
+        self
+    }
+
+    /// Append each page to a JSON Lines report
+    pub fn with_report(mut self, report: JsonLinesReport) -> Self {
+        self.report = Some(report);
+        self
+    }
+
+    /// Handle one crawled page, then keep it without its body
+    pub fn accept(&mut self, mut page: PageInfo) {
+        if page.content_length.is_none() && !page.content.is_empty() {
+            page.content_length = Some(page.content.len());
+        }
+        if let Some(report) = &mut self.report {
+            report.write_page(&page);
+        }
+        if let Some(saver) = &mut self.saver
+            && let Err(e) = saver.save_page_from_content(&page, &self.base_url)
+        {
+            self.errors.push((page.url.clone(), e));
+        }
+        if let Some(writer) = &self.warc
+            && page.redirect_to.is_none()
+            && let Err(e) = writer.write_outlinks(&page.url, &page.links)
+        {
+            self.errors.push((page.url.clone(), e.to_string()));
+        }
+        if (200..300).contains(&page.status_code) && !page.content.is_empty() {
+            self.bodies.entry(sha1(&page.content)).or_default().push(page.url.clone());
+        }
+
+        page.content = Vec::new();
+        page.text = None;
+        self.pages.insert(page);
+    }
+
+    /// URLs that returned byte-identical bodies, grouped, largest group first
+    pub fn duplicate_clusters(&self) -> Vec<Vec<String>> {
+        let mut clusters: Vec<Vec<String>> = self
+            .bodies
+            .values()
+            .filter(|urls| urls.len() > 1)
+            .map(|urls| {
+                let mut urls = urls.clone();
+                urls.sort();
+                urls
+            })
+            .collect();
+        clusters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        clusters
+    }
+
+    /// Close the WARC files after a failed crawl. Saved pages stay on disk, but
+    /// the mirror index is not updated and nothing is pruned, since an
+    /// incomplete crawl would make every page it missed look removed.
+    pub fn abandon(self) {
+        if let Some(writer) = &self.warc
+            && let Err(e) = writer.finish()
+        {
+            log::error!("Failed to finish WARC files in {}: {}", writer.dir().display(), e);
+        }
+        if self.saver.is_some() {
+            log::warn!("Crawl failed; {} saved page(s) were left as they are", self.pages.len());
+        }
+    }
+
+    /// Finish the mirror and WARC files. Returns the handled pages, save
+    /// errors and the JSON Lines report, which still needs its summary.
+    /// After a crawl that stopped early nothing is pruned from the mirror,
+    /// and `unfinished` URLs, which failed or were skipped, never are.
+    pub fn finish(
+        mut self,
+        stopped_early: bool,
+        unfinished: Vec<String>,
+    ) -> (BTreeSet<PageInfo>, Vec<(String, String)>, Option<JsonLinesReport>) {
+        if let Some(saver) = &mut self.saver {
+            saver.keep_unfinished(unfinished);
+            let finished = if stopped_early { saver.finish_partial() } else { saver.finish() };
+            match finished {
+                Ok(Some(changes)) => info!(
+                    "Mirror changes: {} added, {} modified, {} removed, {} unchanged (see {})",
+                    changes.added.len(),
+                    changes.modified.len(),
+                    changes.removed.len(),
+                    changes.unchanged.len(),
+                    MANIFEST_FILE_NAME
+                ),
+                Ok(None) => {}
+                Err(e) => self.errors.push((self.base_url.clone(), e)),
+            }
+        }
+        if let Some(writer) = &self.warc {
+            match writer.finish() {
+                Ok(files) => info!("Wrote {} WARC file(s) to {}", files, writer.dir().display()),
+                Err(e) => self.errors.push((writer.dir().display().to_string(), e.to_string())),
+            }
+        }
+        (self.pages, self.errors, self.report)
+    }
+}
@@ -0,0 +1,75 @@
+//! Cooperative stop request for interrupted crawls and exhausted budgets
+
+use crate::models::StopReason;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct ShutdownState {
+    reason: OnceLock<StopReason>,
+    notify: Notify,
+}
+
+/// Shared flag telling the engine to stop dispatching URLs. In-flight fetches
+/// are allowed to finish; clones observe the same request. The first
+/// request's reason is kept.
+#[derive(Debug, Default, Clone)]
+pub struct Shutdown {
+    state: Arc<ShutdownState>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the crawl to stop and wake everything waiting in `requested`.
+    /// Returns false if a stop had already been requested.
+    pub fn request(&self, reason: StopReason) -> bool {
+        let first = self.state.reason.set(reason).is_ok();
+        self.state.notify.notify_waiters();
+        first
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.state.reason.get().is_some()
+    }
+
+    /// Reason of the first stop request, if any
+    pub fn reason(&self) -> Option<StopReason> {
+        self.state.reason.get().copied()
+    }
+
+    /// Resolve once a stop has been requested
+    pub async fn requested(&self) {
+        loop {
+            let notified = self.state.notify.notified();
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Wait for SIGINT (Ctrl-C) or, on Unix, SIGTERM
+pub async fn interrupt_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => log::warn!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        log::warn!("Failed to listen for Ctrl-C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,107 @@
+//! Side results collected by the engine while crawling
+
+use crate::filtering::{FilterCounts, FilterDecision};
+use crate::models::{QueueStats, WorkerStats};
+use dashmap::DashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Skipped URLs, fetch errors, filter rejections, per-worker statistics and
+/// queue depths shared by all workers
+#[derive(Debug, Default)]
+pub struct CrawlStats {
+    skipped: DashMap<String, String>,
+    /// Shared with link extraction, which applies its own filters
+    filters: Arc<FilterCounts>,
+    errors: DashMap<String, String>,
+    workers: DashMap<usize, WorkerStats>,
+    peak_frontier: AtomicUsize,
+    spilled_urls: AtomicUsize,
+    peak_pending_urls: AtomicUsize,
+    peak_pending_pages: AtomicUsize,
+}
+
+impl CrawlStats {
+    /// Count rejections into `filters`, which link extraction also counts into
+    pub fn with_filter_counts(filters: Arc<FilterCounts>) -> Self {
+        Self { filters, ..Self::default() }
+    }
+
+    /// Count a filter rejection against its rule
+    pub fn record_filter(&self, decision: &FilterDecision) {
+        self.filters.record(decision);
+    }
+
+    /// Remember a URL that was deliberately not crawled
+    pub fn record_skipped(&self, url: &str, reason: impl Into<String>) {
+        self.skipped.insert(url.to_string(), reason.into());
+    }
+
+    /// Record a failed fetch or parse, counting it against the worker
+    pub fn record_error(&self, worker_id: usize, url: &str, reason: impl Into<String>) {
+        self.errors.insert(url.to_string(), reason.into());
+        self.workers.entry(worker_id).or_default().errors += 1;
+    }
+
+    /// Update a worker's counters
+    pub fn update_worker(&self, worker_id: usize, update: impl FnOnce(&mut WorkerStats)) {
+        update(&mut self.workers.entry(worker_id).or_default());
+    }
+
+    /// Record the frontier size and how many URLs it has spilled to disk so far
+    pub fn observe_frontier(&self, len: usize, total_spilled: usize) {
+        self.peak_frontier.fetch_max(len, Ordering::Relaxed);
+        self.spilled_urls.fetch_max(total_spilled, Ordering::Relaxed);
+    }
+
+    /// Record how many discovered URLs are waiting to enter the frontier
+    pub fn observe_pending_urls(&self, depth: usize) {
+        self.peak_pending_urls.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    /// Record how many crawled pages are waiting to be saved
+    pub fn observe_pending_pages(&self, depth: usize) {
+        self.peak_pending_pages.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    pub fn skipped(&self) -> BTreeSet<(String, String)> {
+        Self::collect(&self.skipped)
+    }
+
+    pub fn filter_counts(&self) -> BTreeMap<String, usize> {
+        self.filters.snapshot()
+    }
+
+    pub fn errors(&self) -> BTreeSet<(String, String)> {
+        Self::collect(&self.errors)
+    }
+
+    pub fn workers(&self) -> HashMap<usize, WorkerStats> {
+        self.workers
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect()
+    }
+
+    pub fn queues(&self) -> QueueStats {
+        QueueStats {
+            peak_frontier: self.peak_frontier.load(Ordering::Relaxed),
+            spilled_urls: self.spilled_urls.load(Ordering::Relaxed),
+            peak_pending_urls: self.peak_pending_urls.load(Ordering::Relaxed),
+            peak_pending_pages: self.peak_pending_pages.load(Ordering::Relaxed),
+        }
+    }
+
+    fn collect(map: &DashMap<String, String>) -> BTreeSet<(String, String)> {
+        map.iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+}
+
+/// Whole milliseconds of a duration, saturating
+pub fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
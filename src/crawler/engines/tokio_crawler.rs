@@ -2,16 +2,20 @@
 
 use crate::blacklist::Blacklist;
 use crate::config::CrawlerConfig;
-use crate::models::PageInfo;
+use crate::models::{CacheStats, PageInfo, QueueStats, WorkerStats};
 use crate::http::HttpClient;
 use crate::crawler::UrlParser;
 use crate::crawler::Crawler;
-use crate::http::ReqwestClient;
+use crate::http::{CachingClient, PolitenessScheduler, ReqwestClient, WarcRecorder};
 use crate::extraction::HtmlProcessor;
-use crate::crawler::StandardUrlParser;
+use crate::crawler::{RobotsCache, StandardUrlParser, TrapDetector, UrlScope};
 use super::core::CrawlEngine;
+use super::shutdown::Shutdown;
+use super::stats::CrawlStats;
 use crate::error::{AppError, Result};
+use crate::io::{CrawlState, CrawlStateStore, WarcWriter};
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 use url::Url;
 use std::pin::Pin;
@@ -23,6 +27,15 @@ pub struct TokioCrawler {
     html_processor: Arc<HtmlProcessor>,
     base_domain: Arc<String>,
     base_path: Arc<String>,
+    robots: Arc<RobotsCache>,
+    stats: Arc<CrawlStats>,
+    blacklist: Arc<Blacklist>,
+    state_store: Option<Arc<CrawlStateStore>>,
+    resume_state: Option<Arc<CrawlState>>,
+    cache: Option<Arc<CachingClient>>,
+    shutdown: Shutdown,
+    scope: Arc<UrlScope>,
+    traps: Arc<TrapDetector>,
 }
 
 impl TokioCrawler {
@@ -31,6 +44,8 @@ impl TokioCrawler {
         http_client: Arc<dyn HttpClient>,
         html_processor: HtmlProcessor,
         _url_parser: Arc<dyn UrlParser>,
+        scheduler: Arc<PolitenessScheduler>,
+        blacklist: Arc<Blacklist>,
     ) -> Self {
         let config = Arc::new(config);
         let base_domain = Arc::new(match Url::parse(&config.base_url) {
@@ -41,14 +56,61 @@ impl TokioCrawler {
             Ok(url) => url.path().to_string(),
             Err(_) => "/".to_string(),
         });
+        let robots = Arc::new(RobotsCache::new(
+            http_client.clone(),
+            scheduler,
+            &config.user_agent,
+            !config.ignore_robots_txt,
+        ));
+        let stats = Arc::new(CrawlStats::with_filter_counts(html_processor.filter_counts()));
         Self {
             config,
             http_client,
             html_processor: Arc::new(html_processor),
             base_domain,
             base_path,
+            robots,
+            stats,
+            blacklist,
+            state_store: None,
+            resume_state: None,
+            cache: None,
+            shutdown: Shutdown::new(),
+            scope: Arc::new(UrlScope::default()),
+            traps: Arc::new(TrapDetector::default()),
         }
     }
+
+    /// Only fetch URLs that `scope` admits
+    pub fn with_scope(mut self, scope: Arc<UrlScope>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Reject URLs that look like spider traps according to `traps`
+    pub fn with_traps(mut self, traps: Arc<TrapDetector>) -> Self {
+        self.traps = traps;
+        self
+    }
+
+    /// Stop dispatching URLs when `shutdown` is requested, keeping what was crawled
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Report counters of the response cache that `http_client` goes through
+    pub fn with_cache(mut self, cache: Arc<CachingClient>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Persist crawl progress to a state journal, optionally resuming from a snapshot
+    pub fn with_state_store(mut self, store: Arc<CrawlStateStore>, resume_state: Option<CrawlState>) -> Self {
+        self.state_store = Some(store);
+        self.resume_state = resume_state.map(Arc::new);
+        self
+    }
 }
 
 impl Crawler for TokioCrawler {
@@ -57,19 +119,28 @@ impl Crawler for TokioCrawler {
         F: FnMut(PageInfo) + Send + 'static,
     {
         Box::pin(async move {
-            let engine = CrawlEngine::new(
+            let mut engine = CrawlEngine::new(
                 self.config.clone(),
                 self.http_client.clone(),
                 self.html_processor.clone(),
                 self.base_domain.clone(),
                 self.base_path.clone(),
-            );
+                self.robots.clone(),
+                self.stats.clone(),
+            ).with_blacklist(self.blacklist.clone())
+            .with_scope(self.scope.clone())
+            .with_traps(self.traps.clone())
+            .with_shutdown(self.shutdown.clone());
+            if let Some(store) = &self.state_store {
+                engine = engine.with_state_store(store.clone(), self.resume_state.clone());
+            }
             
-            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            // Bounded so workers wait while the callback catches up, capping pages held in memory
+            let (tx, mut rx) = tokio::sync::mpsc::channel(self.config.max_pages_in_flight.max(1));
             
-            // Spawn the callback handler
-            let callback_handle = tokio::spawn(async move {
-                while let Some(page_info) = rx.recv().await {
+            // The callback saves files, so it runs on a blocking thread instead of a runtime worker
+            let callback_handle = tokio::task::spawn_blocking(move || {
+                while let Some(page_info) = rx.blocking_recv() {
                     callback(page_info);
                 }
             });
@@ -83,19 +154,72 @@ impl Crawler for TokioCrawler {
             crawl_result
         })
     }
+
+    fn skipped_urls(&self) -> BTreeSet<(String, String)> {
+        self.stats.skipped()
+    }
+
+    fn filter_counts(&self) -> BTreeMap<String, usize> {
+        self.stats.filter_counts()
+    }
+
+    fn fetch_errors(&self) -> BTreeSet<(String, String)> {
+        self.stats.errors()
+    }
+
+    fn worker_stats(&self) -> HashMap<usize, WorkerStats> {
+        self.stats.workers()
+    }
+
+    fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    fn queue_stats(&self) -> QueueStats {
+        self.stats.queues()
+    }
 }
 
 pub struct CrawlerFactory;
 
 impl CrawlerFactory {
+    /// Build a crawler; with a `warc` writer every fetch is archived
     pub fn create_multi_threaded_with_blacklist(
         config: CrawlerConfig,
         blacklist: Arc<Blacklist>,
+        warc: Option<Arc<WarcWriter>>,
     ) -> Result<TokioCrawler> {
-        let http_client = Arc::new(ReqwestClient::new(&config.user_agent)?);
-        let html_processor = HtmlProcessor::with_blacklist(blacklist)
+        let scheduler = Arc::new(PolitenessScheduler::new(config.politeness.clone()));
+        let reqwest_client = Arc::new(
+            ReqwestClient::new(&config.user_agent, scheduler.clone())?
+                .with_max_response_bytes(config.budget.max_response_bytes),
+        );
+        let html_processor = HtmlProcessor::with_blacklist(blacklist.clone())
             .map_err(|e| AppError::Crawler(format!("HtmlProcessor error: {}", e)))?;
         let url_parser = Arc::new(StandardUrlParser);
-        Ok(TokioCrawler::new(config, http_client, html_processor, url_parser))
+        let scope = Arc::new(UrlScope::new(&config.allowed_domains, &config.scope)?);
+        let traps = Arc::new(TrapDetector::new(&config.traps)?);
+
+        let cache = match &config.cache_dir {
+            Some(dir) => Some(Arc::new(CachingClient::open(dir, reqwest_client.clone(), config.offline)?)),
+            None if config.offline => return Err(AppError::MissingArgument("--cache directory for --offline")),
+            None => None,
+        };
+        let mut http_client: Arc<dyn HttpClient> = match &cache {
+            Some(cache) => cache.clone(),
+            None => reqwest_client,
+        };
+        // Outermost, so responses served from the cache are archived too
+        if let Some(writer) = warc {
+            http_client = Arc::new(WarcRecorder::new(http_client, writer));
+        }
+
+        let crawler = TokioCrawler::new(config, http_client, html_processor, url_parser, scheduler, blacklist)
+            .with_scope(scope)
+            .with_traps(traps);
+        Ok(match cache {
+            Some(cache) => crawler.with_cache(cache),
+            None => crawler,
+        })
     }
 }
@@ -2,10 +2,22 @@
 
 pub mod engines;
 pub mod url_parser;
+pub mod canonical;
+pub mod scope;
+pub mod traps;
+pub mod url_filter;
+pub mod robots;
+pub mod sitemap;
 pub mod r#trait;
 
 // Re-exports
 pub use r#trait::Crawler;
 pub use url_parser::{UrlParser, StandardUrlParser};
+pub use canonical::UrlCanonicalizer;
+pub use scope::{ScopeVerdict, UrlScope};
+pub use traps::TrapDetector;
+pub use url_filter::UrlFilter;
+pub use robots::RobotsCache;
+pub use sitemap::SitemapLoader;
 pub use engines::tokio_crawler::CrawlerFactory;
 pub use engines::CrawlExecutor;
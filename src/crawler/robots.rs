@@ -0,0 +1,411 @@
+//! robots.txt fetching, parsing and per-host caching
+
+use crate::http::{HttpClient, PolitenessScheduler};
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use url::Url;
+
+/// A single Allow/Disallow rule from a robots.txt group
+#[derive(Debug, Clone)]
+struct RobotsRule {
+    pattern: String,
+    allow: bool,
+}
+
+/// A `User-agent` group and the directives that follow it
+#[derive(Debug, Clone, Default)]
+struct RobotsGroup {
+    agents: Vec<String>,
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<Duration>,
+}
+
+/// Rules from a robots.txt file that apply to our user agent
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<RobotsRule>,
+    disallow_all: bool,
+    crawl_delay: Option<Duration>,
+    sitemaps: Vec<String>,
+}
+
+impl RobotsRules {
+    /// Rules that allow every path (missing or unreadable robots.txt)
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Rules that deny every path (server error while fetching robots.txt)
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: Vec::new(),
+            disallow_all: true,
+            crawl_delay: None,
+            sitemaps: Vec::new(),
+        }
+    }
+
+    /// Parse a robots.txt body, keeping only the group that best matches `user_agent`
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let product = Self::product_token(user_agent);
+        let groups = Self::parse_groups(content);
+
+        // Groups naming our product token win over `*`; groups naming the
+        // same agent are merged
+        let mut selected: Option<RobotsGroup> = None;
+        let mut wildcard = RobotsGroup::default();
+
+        for group in groups {
+            let target = if group.agents.iter().any(|agent| Self::agent_token(agent) == product) {
+                selected.get_or_insert_with(RobotsGroup::default)
+            } else if group.agents.iter().any(|agent| agent == "*") {
+                &mut wildcard
+            } else {
+                continue;
+            };
+            target.rules.extend(group.rules.iter().cloned());
+            target.crawl_delay = target.crawl_delay.or(group.crawl_delay);
+        }
+
+        let group = selected.unwrap_or(wildcard);
+        Self {
+            rules: group.rules,
+            disallow_all: false,
+            crawl_delay: group.crawl_delay,
+            sitemaps: Self::parse_sitemaps(content),
+        }
+    }
+
+    /// Product token robots.txt groups are matched against: `rustcrawler` for
+    /// `Mozilla/5.0 (compatible; RustCrawler/1.0)`
+    fn product_token(user_agent: &str) -> String {
+        let mut products = user_agent
+            .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ';'))
+            .filter_map(|token| token.split_once('/').map(|(name, _)| name))
+            .filter(|name| !name.is_empty() && !name.contains(['+', ':']));
+        let first = products.clone().next();
+        products
+            .find(|name| !name.eq_ignore_ascii_case("mozilla"))
+            .or(first)
+            .or_else(|| user_agent.split_whitespace().next())
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    }
+
+    /// Agent named by a `User-agent` line, without any version
+    fn agent_token(agent: &str) -> &str {
+        agent.split('/').next().unwrap_or(agent).trim()
+    }
+
+    /// Collect `Sitemap:` lines, which apply regardless of user-agent group
+    fn parse_sitemaps(content: &str) -> Vec<String> {
+        content
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(key, _)| key.trim().eq_ignore_ascii_case("sitemap"))
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    /// Split a robots.txt body into user-agent groups
+    fn parse_groups(content: &str) -> Vec<RobotsGroup> {
+        let mut groups: Vec<RobotsGroup> = Vec::new();
+        let mut in_agent_block = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    // Consecutive user-agent lines share the same group
+                    if !in_agent_block {
+                        groups.push(RobotsGroup::default());
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                    in_agent_block = true;
+                }
+                "allow" | "disallow" => {
+                    in_agent_block = false;
+                    // An empty Disallow means "allow everything" and adds no rule
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.rules.push(RobotsRule {
+                            pattern: value.to_string(),
+                            allow: key == "allow",
+                        });
+                    }
+                }
+                "sitemap" => {
+                    // Handled by parse_sitemaps; does not end a user-agent block
+                }
+                "crawl-delay" => {
+                    in_agent_block = false;
+                    let delay = value.parse::<f64>().ok().filter(|d| d.is_finite() && *d >= 0.0);
+                    if let (Some(group), Some(delay)) = (groups.last_mut(), delay) {
+                        group.crawl_delay = Some(Duration::from_secs_f64(delay));
+                    }
+                }
+                _ => {
+                    in_agent_block = false;
+                }
+            }
+        }
+
+        groups
+    }
+
+    /// Crawl-delay requested for our user agent, if any
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// Sitemap URLs advertised by the robots.txt file
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+
+    /// Check whether a path (including query string) may be fetched
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if self.disallow_all {
+            return false;
+        }
+
+        // Longest matching pattern wins; on a tie Allow takes precedence
+        let mut best: Option<(usize, bool)> = None;
+        for rule in &self.rules {
+            if Self::pattern_matches(&rule.pattern, path) {
+                let len = rule.pattern.len();
+                best = match best {
+                    Some((best_len, best_allow))
+                        if best_len > len || (best_len == len && best_allow) =>
+                    {
+                        Some((best_len, best_allow))
+                    }
+                    _ => Some((len, rule.allow)),
+                };
+            }
+        }
+
+        best.is_none_or(|(_, allow)| allow)
+    }
+
+    /// Match a robots.txt path pattern supporting `*` wildcards and a trailing `$` anchor
+    fn pattern_matches(pattern: &str, path: &str) -> bool {
+        let (pattern, anchored) = match pattern.strip_suffix('$') {
+            Some(p) => (p, true),
+            None => (pattern, false),
+        };
+
+        let parts: Vec<&str> = pattern.split('*').collect();
+        let mut pos = 0;
+
+        for (i, part) in parts.iter().enumerate() {
+            if i == 0 {
+                if !path.starts_with(part) {
+                    return false;
+                }
+                pos = part.len();
+            } else if i == parts.len() - 1 && anchored {
+                // The last literal must sit at the very end of the path
+                return path.len() >= pos + part.len() && path.ends_with(part);
+            } else {
+                match path[pos..].find(part) {
+                    Some(idx) => pos += idx + part.len(),
+                    None => return false,
+                }
+            }
+        }
+
+        !anchored || pos == path.len()
+    }
+}
+
+/// Per-host cache of robots.txt rules fetched through the crawler's `HttpClient`
+pub struct RobotsCache {
+    http_client: Arc<dyn HttpClient>,
+    scheduler: Arc<PolitenessScheduler>,
+    user_agent: String,
+    enabled: bool,
+    hosts: DashMap<String, Arc<OnceCell<Arc<RobotsRules>>>>,
+}
+
+impl RobotsCache {
+    pub fn new(
+        http_client: Arc<dyn HttpClient>,
+        scheduler: Arc<PolitenessScheduler>,
+        user_agent: &str,
+        enabled: bool,
+    ) -> Self {
+        Self {
+            http_client,
+            scheduler,
+            user_agent: user_agent.to_string(),
+            enabled,
+            hosts: DashMap::new(),
+        }
+    }
+
+    /// Check a URL against robots.txt, fetching the host's file on first use
+    pub async fn is_allowed(&self, url: &str) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        match self.rules_for(url).await {
+            Some((rules, path)) => rules.is_allowed(&path),
+            None => true,
+        }
+    }
+
+    /// Sitemap URLs listed in the robots.txt of a URL's host
+    pub async fn sitemaps(&self, url: &str) -> Vec<String> {
+        self.rules_for(url)
+            .await
+            .map(|(rules, _)| rules.sitemaps().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Get the rules for a URL's host (fetching them on first use) and the URL's path
+    async fn rules_for(&self, url: &str) -> Option<(Arc<RobotsRules>, String)> {
+        let (origin, path) = Self::split_url(url)?;
+
+        let cell = self.hosts
+            .entry(origin.clone())
+            .or_insert_with(|| Arc::new(OnceCell::new()))
+            .clone();
+
+        let rules = cell.get_or_init(|| self.fetch_rules(&origin, url)).await;
+        Some((Arc::clone(rules), path))
+    }
+
+    /// Check a URL against already-fetched rules only; `None` if the host is not cached yet
+    pub fn cached_verdict(&self, url: &str) -> Option<bool> {
+        if !self.enabled {
+            return Some(true);
+        }
+        let (origin, path) = Self::split_url(url)?;
+        let cell = self.hosts.get(&origin)?;
+        cell.get().map(|rules| rules.is_allowed(&path))
+    }
+
+    /// Download and parse robots.txt for an origin
+    async fn fetch_rules(&self, origin: &str, url: &str) -> Arc<RobotsRules> {
+        let robots_url = format!("{}/robots.txt", origin);
+        log::debug!("Fetching robots.txt: {}", robots_url);
+
+        let rules = match self.http_client.fetch(&robots_url).await {
+            Ok(response) if response.is_success() => {
+                RobotsRules::parse(&String::from_utf8_lossy(&response.body), &self.user_agent)
+            }
+            Ok(response) if response.status >= 500 => {
+                log::warn!("robots.txt for {} returned {}, treating host as disallowed", origin, response.status);
+                RobotsRules::disallow_all()
+            }
+            Ok(_) => RobotsRules::allow_all(),
+            Err(e) => {
+                log::warn!("Failed to fetch robots.txt for {}: {}", origin, e);
+                RobotsRules::allow_all()
+            }
+        };
+
+        if self.enabled
+            && let (Some(delay), Ok(parsed)) = (rules.crawl_delay(), Url::parse(url))
+            && let Some(host) = parsed.host_str()
+        {
+            self.scheduler.set_crawl_delay(host, delay);
+        }
+
+        Arc::new(rules)
+    }
+
+    /// Split a URL into its origin (`scheme://host[:port]`) and path with query
+    fn split_url(url: &str) -> Option<(String, String)> {
+        let parsed = Url::parse(url).ok()?;
+        let host = parsed.host_str()?;
+        let origin = match parsed.port() {
+            Some(port) => format!("{}://{}:{}", parsed.scheme(), host, port),
+            None => format!("{}://{}", parsed.scheme(), host),
+        };
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        Some((origin, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UA: &str = "Mozilla/5.0 (compatible; RustCrawler/1.0)";
+
+    #[test]
+    fn test_wildcard_group_rules() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /private/\nAllow: /private/public.html\n", UA);
+        assert!(rules.is_allowed("/index.html"));
+        assert!(!rules.is_allowed("/private/secret.html"));
+        assert!(rules.is_allowed("/private/public.html"));
+    }
+
+    #[test]
+    fn test_specific_agent_overrides_wildcard() {
+        let content = "User-agent: *\nDisallow: /\n\nUser-agent: RustCrawler\nDisallow: /admin\n";
+        let rules = RobotsRules::parse(content, UA);
+        assert!(rules.is_allowed("/docs/"));
+        assert!(!rules.is_allowed("/admin/login"));
+    }
+
+    #[test]
+    fn test_groups_match_the_product_token_only() {
+        let content = "User-agent: Mozilla\nDisallow: /\n\nUser-agent: compatible\nDisallow: /\n\n\
+                       User-agent: *\nDisallow: /private\n";
+        let rules = RobotsRules::parse(content, UA);
+        assert!(rules.is_allowed("/docs/"));
+        assert!(!rules.is_allowed("/private/x"));
+
+        let rules = RobotsRules::parse("User-agent: RUSTCRAWLER/2.0\nDisallow: /admin\n", UA);
+        assert!(!rules.is_allowed("/admin"));
+        assert_eq!(RobotsRules::product_token("Googlebot/2.1 (+http://www.google.com/bot.html)"), "googlebot");
+    }
+
+    #[test]
+    fn test_wildcards_and_end_anchor() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow: /*.pdf$\nDisallow: /*?sessionid=\n", UA);
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf.html"));
+        assert!(!rules.is_allowed("/page?sessionid=42"));
+        assert!(rules.is_allowed("/page?id=42"));
+    }
+
+    #[test]
+    fn test_crawl_delay_for_selected_group() {
+        let content = "User-agent: *\nCrawl-delay: 10\n\nUser-agent: RustCrawler\nCrawl-delay: 2.5\nDisallow: /tmp\n";
+        let rules = RobotsRules::parse(content, UA);
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn test_sitemap_lines_are_global() {
+        let content = "Sitemap: https://example.com/sitemap.xml\nUser-agent: *\nDisallow: /x\nsitemap: https://example.com/news.xml\n";
+        let rules = RobotsRules::parse(content, UA);
+        assert_eq!(rules.sitemaps(), ["https://example.com/sitemap.xml", "https://example.com/news.xml"]);
+    }
+
+    #[test]
+    fn test_empty_disallow_allows_everything() {
+        let rules = RobotsRules::parse("User-agent: *\nDisallow:\n", UA);
+        assert!(rules.is_allowed("/anything"));
+    }
+}
@@ -0,0 +1,274 @@
+//! Crawl scope: allowed domains plus include/exclude rules on full URLs
+
+use crate::config::{RuleAction, RulePrecedence, ScopeConfig, ScopeRule};
+use crate::error::{AppError, Result};
+use crate::filtering::FilterDecision;
+use regex::Regex;
+use url::Url;
+
+/// Extensions fetched from outside the allowed domains when `external_assets` is set
+const ASSET_EXTENSIONS: &[&str] = &[
+    "css", "js", "mjs", "png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "ico", "bmp",
+    "woff", "woff2", "ttf", "otf", "eot",
+];
+
+/// How a URL relates to the crawl scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeVerdict {
+    /// Fetch it and follow its links
+    Crawl,
+    /// Fetch it as an asset of an in-scope page, but do not follow its links
+    AssetOnly,
+    /// Leave it alone
+    Out,
+}
+
+/// URL pattern of a compiled rule
+#[derive(Debug)]
+enum Matcher {
+    PathPrefix(String),
+    UrlPrefix(String),
+    Pattern(Regex),
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    action: RuleAction,
+    matcher: Matcher,
+    /// The rule as configured, e.g. `exclude glob **/print`
+    text: String,
+}
+
+/// Compiled scope shared by all workers
+#[derive(Debug, Default)]
+pub struct UrlScope {
+    domains: Vec<String>,
+    rules: Vec<CompiledRule>,
+    precedence: RulePrecedence,
+    external_assets: bool,
+    has_includes: bool,
+}
+
+impl UrlScope {
+    /// Compile `config`'s rules for the given domain patterns
+    pub fn new(domains: &[String], config: &ScopeConfig) -> Result<Self> {
+        let rules = config.rules.iter().map(Self::compile).collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            domains: domains.to_vec(),
+            has_includes: rules.iter().any(|r| r.action == RuleAction::Include),
+            rules,
+            precedence: config.precedence,
+            external_assets: config.external_assets,
+        })
+    }
+
+    /// Decide whether `url` may be fetched
+    pub fn check(&self, url: &str) -> ScopeVerdict {
+        self.verdict(url).0
+    }
+
+    /// Like `check`, naming the rule or host that put a URL out of scope
+    pub fn decide(&self, url: &str) -> FilterDecision {
+        self.verdict(url).1
+    }
+
+    fn verdict(&self, url: &str) -> (ScopeVerdict, FilterDecision) {
+        let Ok(parsed) = Url::parse(url) else {
+            return (ScopeVerdict::Out, FilterDecision::reject("unparsable"));
+        };
+        match self.deciding_rule(url, parsed.path()) {
+            Some(rule) if rule.action == RuleAction::Exclude => {
+                return (ScopeVerdict::Out, FilterDecision::reject_matching("scope-rule", rule.text.as_str()));
+            }
+            None if self.has_includes => {
+                return (ScopeVerdict::Out, FilterDecision::reject_matching("scope-rule", "no include rule matched"));
+            }
+            _ => {}
+        }
+        let host = parsed.host_str().unwrap_or_default();
+        if self.domains.is_empty() || self.domains.iter().any(|pattern| Self::matches_domain(host, pattern)) {
+            (ScopeVerdict::Crawl, FilterDecision::Accepted)
+        } else if self.external_assets && Self::is_asset(parsed.path()) {
+            (ScopeVerdict::AssetOnly, FilterDecision::Accepted)
+        } else {
+            (ScopeVerdict::Out, FilterDecision::reject_matching("scope-domain", host))
+        }
+    }
+
+    /// Check if a host matches a domain pattern (supports wildcards like *.google.com)
+    fn matches_domain(host: &str, pattern: &str) -> bool {
+        match pattern.strip_prefix("*.") {
+            // Wildcard pattern: *.google.com matches google.com, sub.google.com, etc.
+            Some(suffix) => host == suffix || host.ends_with(&format!(".{}", suffix)),
+            None => host == pattern,
+        }
+    }
+
+    /// The rule that decides `url` under the configured precedence, if any matches
+    fn deciding_rule(&self, url: &str, path: &str) -> Option<&CompiledRule> {
+        let mut matching = self.rules.iter().filter(|rule| rule.matches(url, path));
+        match self.precedence {
+            RulePrecedence::FirstMatch => matching.next(),
+            RulePrecedence::DenyOverrides => {
+                matching.reduce(|a, b| if a.action != RuleAction::Exclude && b.action == RuleAction::Exclude { b } else { a })
+            }
+        }
+    }
+
+    fn is_asset(path: &str) -> bool {
+        path.rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .is_some_and(|(_, ext)| ASSET_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    }
+
+    fn compile(rule: &ScopeRule) -> Result<CompiledRule> {
+        let action = match rule.action {
+            RuleAction::Include => "include",
+            RuleAction::Exclude => "exclude",
+        };
+        let (matcher, text) = match (&rule.prefix, &rule.glob, &rule.regex) {
+            (Some(prefix), None, None) if prefix.contains("://") => (Matcher::UrlPrefix(prefix.clone()), ("prefix", prefix)),
+            (Some(prefix), None, None) => (Matcher::PathPrefix(prefix.clone()), ("prefix", prefix)),
+            (None, Some(glob), None) => (Matcher::Pattern(Self::glob_regex(glob)?), ("glob", glob)),
+            (None, None, Some(regex)) => (
+                Matcher::Pattern(
+                    Regex::new(regex)
+                        .map_err(|e| AppError::RegexCompilation(format!("scope rule '{}': {}", regex, e)))?,
+                ),
+                ("regex", regex),
+            ),
+            _ => {
+                return Err(AppError::ConfigFile(
+                    "each [[scope.rules]] entry needs exactly one of prefix, glob or regex".to_string(),
+                ));
+            }
+        };
+        Ok(CompiledRule { action: rule.action, matcher, text: format!("{} {} {}", action, text.0, text.1) })
+    }
+
+    /// Translate a glob into an anchored regex
+    fn glob_regex(glob: &str) -> Result<Regex> {
+        let mut pattern = String::from("^");
+        let mut chars = glob.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    pattern.push_str(".*");
+                }
+                '*' => pattern.push_str("[^/]*"),
+                '?' => pattern.push_str("[^/]"),
+                c => pattern.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        pattern.push('$');
+        Regex::new(&pattern).map_err(|e| AppError::RegexCompilation(format!("scope glob '{}': {}", glob, e)))
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, url: &str, path: &str) -> bool {
+        match &self.matcher {
+            Matcher::PathPrefix(prefix) => path.starts_with(prefix.as_str()),
+            Matcher::UrlPrefix(prefix) => url.starts_with(prefix.as_str()),
+            Matcher::Pattern(regex) => regex.is_match(url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: RuleAction, prefix: Option<&str>, glob: Option<&str>, regex: Option<&str>) -> ScopeRule {
+        ScopeRule {
+            action,
+            prefix: prefix.map(String::from),
+            glob: glob.map(String::from),
+            regex: regex.map(String::from),
+        }
+    }
+
+    #[test]
+    fn domain_patterns_support_wildcards() {
+        let scope = UrlScope::new(&["*.example.com".to_string()], &ScopeConfig::default()).unwrap();
+        assert_eq!(scope.check("https://example.com/"), ScopeVerdict::Crawl);
+        assert_eq!(scope.check("https://docs.example.com/a"), ScopeVerdict::Crawl);
+        assert_eq!(scope.check("https://badexample.com/"), ScopeVerdict::Out);
+    }
+
+    #[test]
+    fn first_match_wins_in_order() {
+        let config = ScopeConfig {
+            rules: vec![
+                rule(RuleAction::Exclude, Some("/blog/drafts/"), None, None),
+                rule(RuleAction::Include, Some("/blog/"), None, None),
+            ],
+            ..ScopeConfig::default()
+        };
+        let scope = UrlScope::new(&[], &config).unwrap();
+        assert_eq!(scope.check("https://a.test/blog/post"), ScopeVerdict::Crawl);
+        assert_eq!(scope.check("https://a.test/blog/drafts/x"), ScopeVerdict::Out);
+        // Include rules exist, so unmatched URLs are out
+        assert_eq!(scope.check("https://a.test/shop/"), ScopeVerdict::Out);
+    }
+
+    #[test]
+    fn deny_overrides_ignores_rule_order() {
+        let config = ScopeConfig {
+            rules: vec![
+                rule(RuleAction::Include, None, Some("https://a.test/**"), None),
+                rule(RuleAction::Exclude, None, None, Some(r"\?sort=")),
+            ],
+            precedence: RulePrecedence::DenyOverrides,
+            ..ScopeConfig::default()
+        };
+        let scope = UrlScope::new(&[], &config).unwrap();
+        assert_eq!(scope.check("https://a.test/list"), ScopeVerdict::Crawl);
+        assert_eq!(scope.check("https://a.test/list?sort=asc"), ScopeVerdict::Out);
+    }
+
+    #[test]
+    fn single_star_glob_stays_in_one_segment() {
+        let config = ScopeConfig {
+            rules: vec![rule(RuleAction::Exclude, None, Some("https://a.test/*/print"), None)],
+            ..ScopeConfig::default()
+        };
+        let scope = UrlScope::new(&[], &config).unwrap();
+        assert_eq!(scope.check("https://a.test/news/print"), ScopeVerdict::Out);
+        assert_eq!(scope.check("https://a.test/news/2024/print"), ScopeVerdict::Crawl);
+    }
+
+    #[test]
+    fn external_assets_are_fetched_but_not_crawled() {
+        let config = ScopeConfig { external_assets: true, ..ScopeConfig::default() };
+        let scope = UrlScope::new(&["a.test".to_string()], &config).unwrap();
+        assert_eq!(scope.check("https://cdn.test/lib/app.JS"), ScopeVerdict::AssetOnly);
+        assert_eq!(scope.check("https://cdn.test/page.html"), ScopeVerdict::Out);
+    }
+
+    #[test]
+    fn decide_names_the_excluding_rule() {
+        let config = ScopeConfig {
+            rules: vec![rule(RuleAction::Exclude, None, Some("**/print"), None)],
+            ..ScopeConfig::default()
+        };
+        let scope = UrlScope::new(&["a.test".to_string()], &config).unwrap();
+        assert_eq!(
+            scope.decide("https://a.test/news/print"),
+            FilterDecision::reject_matching("scope-rule", "exclude glob **/print")
+        );
+        assert_eq!(scope.decide("https://b.test/"), FilterDecision::reject_matching("scope-domain", "b.test"));
+        assert!(scope.decide("https://a.test/news").is_accepted());
+    }
+
+    #[test]
+    fn rule_needs_exactly_one_pattern() {
+        let config = ScopeConfig {
+            rules: vec![rule(RuleAction::Include, Some("/a"), Some("*"), None)],
+            ..ScopeConfig::default()
+        };
+        assert!(UrlScope::new(&[], &config).is_err());
+    }
+}
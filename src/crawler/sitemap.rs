@@ -0,0 +1,239 @@
+//! Sitemap discovery and parsing (urlset, sitemapindex, gzip-compressed files)
+
+use crate::crawler::RobotsCache;
+use crate::http::HttpClient;
+use flate2::read::GzDecoder;
+use regex::Regex;
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use std::sync::{Arc, OnceLock};
+use url::Url;
+
+/// Maximum decompressed sitemap size accepted (the sitemap protocol limit is 50MB)
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+/// Maximum number of sitemap documents fetched for one crawl
+const MAX_SITEMAPS: usize = 1000;
+
+/// A page URL listed in a sitemap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SitemapEntry {
+    pub url: String,
+    pub lastmod: Option<String>,
+}
+
+/// Parsed contents of a sitemap document
+#[derive(Debug, Default)]
+pub struct SitemapDocument {
+    /// Page URLs from a `<urlset>`
+    pub urls: Vec<SitemapEntry>,
+    /// Child sitemap URLs from a `<sitemapindex>`
+    pub sitemaps: Vec<String>,
+}
+
+static ENTRY_REGEX: OnceLock<Regex> = OnceLock::new();
+static LOC_REGEX: OnceLock<Regex> = OnceLock::new();
+static LASTMOD_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn tag_regex(cell: &'static OnceLock<Regex>, tag: &str) -> &'static Regex {
+    cell.get_or_init(|| {
+        // Optional namespace prefix on both tags; content is matched lazily
+        let pattern = format!(r"(?is)<(?:[\w-]+:)?{tag}\b[^>]*>(.*?)</(?:[\w-]+:)?{tag}\s*>");
+        Regex::new(&pattern).expect("sitemap tag regex is valid")
+    })
+}
+
+/// Parses sitemap XML without a full XML parser
+pub struct SitemapParser;
+
+impl SitemapParser {
+    /// Parse a sitemap body, transparently decompressing gzip data
+    pub fn parse_bytes(body: &[u8]) -> SitemapDocument {
+        match Self::decompress(body) {
+            Some(xml) => Self::parse(&xml),
+            None => SitemapDocument::default(),
+        }
+    }
+
+    /// Parse an uncompressed sitemap or sitemap index
+    pub fn parse(xml: &str) -> SitemapDocument {
+        let entry_regex = ENTRY_REGEX.get_or_init(|| {
+            Regex::new(r"(?is)<(?:[\w-]+:)?(url|sitemap)\b[^>]*>(.*?)</(?:[\w-]+:)?(?:url|sitemap)\s*>")
+                .expect("sitemap entry regex is valid")
+        });
+        let loc_regex = tag_regex(&LOC_REGEX, "loc");
+        let lastmod_regex = tag_regex(&LASTMOD_REGEX, "lastmod");
+
+        let mut document = SitemapDocument::default();
+        for cap in entry_regex.captures_iter(xml) {
+            let kind = cap[1].to_ascii_lowercase();
+            let body = &cap[2];
+
+            let Some(loc) = loc_regex.captures(body).map(|c| Self::decode_text(&c[1])) else {
+                continue;
+            };
+            if loc.is_empty() {
+                continue;
+            }
+
+            if kind == "sitemap" {
+                document.sitemaps.push(loc);
+            } else {
+                let lastmod = lastmod_regex
+                    .captures(body)
+                    .map(|c| Self::decode_text(&c[1]))
+                    .filter(|s| !s.is_empty());
+                document.urls.push(SitemapEntry { url: loc, lastmod });
+            }
+        }
+
+        document
+    }
+
+    /// Decompress gzip bodies (detected by magic bytes) and decode as UTF-8
+    fn decompress(body: &[u8]) -> Option<String> {
+        if body.starts_with(&[0x1f, 0x8b]) {
+            let mut xml = String::new();
+            match GzDecoder::new(body).take(MAX_SITEMAP_BYTES).read_to_string(&mut xml) {
+                Ok(_) => Some(xml),
+                Err(e) => {
+                    log::warn!("Failed to decompress sitemap: {}", e);
+                    None
+                }
+            }
+        } else {
+            Some(String::from_utf8_lossy(body).into_owned())
+        }
+    }
+
+    /// Strip CDATA wrappers and decode the predefined XML entities
+    fn decode_text(raw: &str) -> String {
+        let text = raw.trim();
+        let text = text
+            .strip_prefix("<![CDATA[")
+            .and_then(|t| t.strip_suffix("]]>"))
+            .unwrap_or(text);
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+            .trim()
+            .to_string()
+    }
+}
+
+/// Discovers sitemaps for a site and collects the page URLs they list
+pub struct SitemapLoader {
+    http_client: Arc<dyn HttpClient>,
+    robots: Arc<RobotsCache>,
+}
+
+impl SitemapLoader {
+    pub fn new(http_client: Arc<dyn HttpClient>, robots: Arc<RobotsCache>) -> Self {
+        Self { http_client, robots }
+    }
+
+    /// Collect page URLs from explicit sitemaps and, if `discover` is set,
+    /// from `/sitemap.xml` and robots.txt `Sitemap:` lines of the base URL's host
+    pub async fn load(&self, base_url: &str, explicit: &[String], discover: bool) -> Vec<SitemapEntry> {
+        let mut pending: VecDeque<String> = explicit.iter().cloned().collect();
+
+        if discover && let Ok(base) = Url::parse(base_url) {
+            if let Ok(default_sitemap) = base.join("/sitemap.xml") {
+                pending.push_back(default_sitemap.to_string());
+            }
+            pending.extend(self.robots.sitemaps(base_url).await);
+        }
+
+        let mut seen_sitemaps = HashSet::new();
+        let mut seen_urls = HashSet::new();
+        let mut entries = Vec::new();
+
+        while let Some(sitemap_url) = pending.pop_front() {
+            if seen_sitemaps.len() >= MAX_SITEMAPS {
+                log::warn!("Sitemap limit of {} reached, ignoring the rest", MAX_SITEMAPS);
+                break;
+            }
+            if !seen_sitemaps.insert(sitemap_url.clone()) {
+                continue;
+            }
+
+            let Some(document) = self.fetch_sitemap(&sitemap_url).await else {
+                continue;
+            };
+            log::info!(
+                "Sitemap {}: {} URLs, {} child sitemaps",
+                sitemap_url, document.urls.len(), document.sitemaps.len()
+            );
+
+            pending.extend(document.sitemaps);
+            for entry in document.urls {
+                if seen_urls.insert(entry.url.clone()) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries
+    }
+
+    /// Fetch and parse a single sitemap document
+    async fn fetch_sitemap(&self, url: &str) -> Option<SitemapDocument> {
+        match self.http_client.fetch(url).await {
+            Ok(response) if response.is_success() => Some(SitemapParser::parse_bytes(&response.body)),
+            Ok(response) => {
+                log::debug!("Sitemap {} returned status {}", url, response.status);
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to fetch sitemap {}: {}", url, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/</loc><lastmod>2024-01-02</lastmod></url>
+  <url>
+    <loc><![CDATA[https://example.com/a?x=1&amp;y=2]]></loc>
+  </url>
+</urlset>"#;
+
+    #[test]
+    fn test_parse_urlset() {
+        let doc = SitemapParser::parse(URLSET);
+        assert!(doc.sitemaps.is_empty());
+        assert_eq!(doc.urls.len(), 2);
+        assert_eq!(doc.urls[0].lastmod.as_deref(), Some("2024-01-02"));
+        assert_eq!(doc.urls[1].url, "https://example.com/a?x=1&y=2");
+        assert_eq!(doc.urls[1].lastmod, None);
+    }
+
+    #[test]
+    fn test_parse_sitemap_index() {
+        let xml = r#"<sitemapindex><sitemap><loc>https://example.com/s1.xml.gz</loc>
+            <lastmod>2024-01-01</lastmod></sitemap></sitemapindex>"#;
+        let doc = SitemapParser::parse(xml);
+        assert_eq!(doc.sitemaps, vec!["https://example.com/s1.xml.gz".to_string()]);
+        assert!(doc.urls.is_empty());
+    }
+
+    #[test]
+    fn test_parse_gzip_body() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let doc = SitemapParser::parse_bytes(&compressed);
+        assert_eq!(doc.urls.len(), 2);
+    }
+}
@@ -1,14 +1,46 @@
 //! Crawler trait definition
 
-use crate::models::PageInfo;
+use crate::models::{CacheStats, PageInfo, QueueStats, WorkerStats};
 use std::future::Future;
 use std::pin::Pin;
 use crate::error::Result;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// Crawler trait using manual future implementation
 pub trait Crawler: Send + Sync {
-    /// Start crawling and call the callback for each PageInfo as it is crawled
+    /// Start crawling and call the callback for each PageInfo as it is crawled.
+    /// The callback runs on a blocking thread, so it may do synchronous I/O.
     fn crawl_with_callback<'a, F>(&'a self, callback: F) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>
     where
         F: FnMut(PageInfo) + Send + 'static;
+
+    /// URLs skipped during the last crawl, paired with the reason they were skipped
+    fn skipped_urls(&self) -> BTreeSet<(String, String)> {
+        BTreeSet::new()
+    }
+
+    /// URLs rejected by each filter rule during the last crawl
+    fn filter_counts(&self) -> BTreeMap<String, usize> {
+        BTreeMap::new()
+    }
+
+    /// Fetch and parse failures of the last crawl, paired with a categorized reason
+    fn fetch_errors(&self) -> BTreeSet<(String, String)> {
+        BTreeSet::new()
+    }
+
+    /// Per-worker statistics of the last crawl, keyed by worker ID
+    fn worker_stats(&self) -> HashMap<usize, WorkerStats> {
+        HashMap::new()
+    }
+
+    /// Response cache counters for the last crawl, if a cache is in use
+    fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
+
+    /// Peak queue depths of the last crawl
+    fn queue_stats(&self) -> QueueStats {
+        QueueStats::default()
+    }
 }
@@ -0,0 +1,239 @@
+//! Spider-trap detection: URL shapes and link patterns that generate endless pages
+
+use crate::config::TrapConfig;
+use crate::error::{AppError, Result};
+use crate::filtering::FilterDecision;
+use dashmap::DashMap;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use url::Url;
+
+/// Path fragments of broken relative links: `///` and encoded `//`. `../`
+/// chains never reach the check, since parsing resolves them.
+const MALFORMED_PATTERNS: &[&str] = &["///", "%2F%2F", "%2f%2f"];
+
+/// URLs `chains` holds before the shortest chains are forgotten
+const MAX_TRACKED_CHAINS: usize = 100_000;
+
+/// A `[[traps.pattern_caps]]` entry, compiled
+#[derive(Debug)]
+struct PatternCap {
+    regex: Regex,
+    max_pages: usize,
+    fetched: AtomicUsize,
+}
+
+/// Trap rules from `TrapConfig`, with the counters they need, shared by all workers
+#[derive(Debug, Default)]
+pub struct TrapDetector {
+    config: TrapConfig,
+    pattern_caps: Vec<PatternCap>,
+    /// Length of the incrementing-number chain that led to each URL, for URLs
+    /// reached through a numeric step link
+    chains: DashMap<String, usize>,
+}
+
+impl TrapDetector {
+    pub fn new(config: &TrapConfig) -> Result<Self> {
+        let pattern_caps = config
+            .pattern_caps
+            .iter()
+            .map(|cap| {
+                let regex = Regex::new(&cap.pattern)
+                    .map_err(|e| AppError::RegexCompilation(format!("trap pattern '{}': {}", cap.pattern, e)))?;
+                Ok(PatternCap { regex, max_pages: cap.max_pages, fetched: AtomicUsize::new(0) })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { config: config.clone(), pattern_caps, chains: DashMap::new() })
+    }
+
+    /// Checks on the shape of a single URL
+    pub fn check(&self, url: &str) -> FilterDecision {
+        let Ok(parsed) = Url::parse(url) else {
+            return FilterDecision::Accepted;
+        };
+        let path = parsed.path();
+        if self.config.malformed_paths
+            && let Some(pattern) = MALFORMED_PATTERNS.iter().find(|pattern| path.contains(*pattern))
+        {
+            return FilterDecision::reject_matching("trap-malformed", *pattern);
+        }
+
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let max_depth = self.config.max_path_depth;
+        if max_depth > 0 && segments.len() > max_depth {
+            return FilterDecision::reject_matching("trap-path-depth", format!("{} > {} segments", segments.len(), max_depth));
+        }
+        if let Some(segment) = Self::repeated_segment(&segments, self.config.max_segment_repeats) {
+            return FilterDecision::reject_matching("trap-repeated-segment", segment);
+        }
+
+        let max_params = self.config.max_query_params;
+        let params = parsed.query().map_or(0, |q| q.split('&').filter(|p| !p.is_empty()).count());
+        if max_params > 0 && params > max_params {
+            return FilterDecision::reject_matching("trap-query-params", format!("{} > {} parameters", params, max_params));
+        }
+        FilterDecision::Accepted
+    }
+
+    /// Count a fetch of `url` against the pattern caps
+    pub fn claim(&self, url: &str) -> FilterDecision {
+        for cap in self.pattern_caps.iter().filter(|cap| cap.max_pages > 0 && cap.regex.is_match(url)) {
+            if cap.fetched.fetch_add(1, Ordering::SeqCst) >= cap.max_pages {
+                return FilterDecision::reject_matching("trap-pattern-cap", cap.regex.as_str());
+            }
+        }
+        FilterDecision::Accepted
+    }
+
+    /// Check a link found on `page_url`. A link that only bumps one number of
+    /// the page's URL by one (next day, next page) extends the page's chain;
+    /// chains longer than `max_numeric_chain` are cut off.
+    pub fn follow_link(&self, page_url: &str, link: &str) -> FilterDecision {
+        let max_chain = self.config.max_numeric_chain;
+        if max_chain == 0 || !Self::is_numeric_step(page_url, link) {
+            return FilterDecision::Accepted;
+        }
+        let chain = self.chains.get(page_url).map_or(0, |c| *c) + 1;
+        if chain > max_chain {
+            return FilterDecision::reject_matching("trap-numeric-sequence", format!("{} incrementing links", chain));
+        }
+        if self.chains.len() >= MAX_TRACKED_CHAINS {
+            self.forget_short_chains();
+        }
+        // A URL reached by several chains keeps the shortest
+        self.chains.entry(link.to_string()).and_modify(|c| *c = (*c).min(chain)).or_insert(chain);
+        FilterDecision::Accepted
+    }
+
+    /// Make room in `chains`. Single steps, such as page 2 of a listing, are
+    /// most of the entries and the least likely to be traps, so they go first;
+    /// if that frees too little, every chain starts over.
+    fn forget_short_chains(&self) {
+        self.chains.retain(|_, chain| *chain > 1);
+        if self.chains.len() >= MAX_TRACKED_CHAINS / 2 {
+            self.chains.clear();
+        }
+    }
+
+    /// First segment that occurs more often than allowed
+    fn repeated_segment<'a>(segments: &[&'a str], max_repeats: usize) -> Option<&'a str> {
+        if max_repeats == 0 {
+            return None;
+        }
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        segments.iter().copied().find(|segment| {
+            let count = counts.entry(segment).or_insert(0);
+            *count += 1;
+            *count > max_repeats
+        })
+    }
+
+    /// Whether two URLs are identical except for one number that differs by one
+    fn is_numeric_step(from: &str, to: &str) -> bool {
+        let (from_text, from_numbers) = Self::split_numbers(from);
+        let (to_text, to_numbers) = Self::split_numbers(to);
+        if from_text != to_text || from_numbers.len() != to_numbers.len() {
+            return false;
+        }
+        let mut differing = from_numbers.iter().zip(&to_numbers).filter(|(a, b)| a != b);
+        matches!((differing.next(), differing.next()), (Some((a, b)), None) if a.abs_diff(*b) == 1)
+    }
+
+    /// URL text with digit runs replaced by `#`, and the numbers themselves
+    fn split_numbers(url: &str) -> (String, Vec<u64>) {
+        let mut text = String::with_capacity(url.len());
+        let mut numbers = Vec::new();
+        let mut digits = String::new();
+        for c in url.chars().chain(std::iter::once('\0')) {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+            if !digits.is_empty() {
+                match digits.parse() {
+                    Ok(number) => {
+                        text.push('#');
+                        numbers.push(number);
+                    }
+                    // Too long to be a counter; compare it as text
+                    Err(_) => text.push_str(&digits),
+                }
+                digits.clear();
+            }
+            text.push(c);
+        }
+        (text, numbers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TrapPatternCap;
+
+    fn detector(config: TrapConfig) -> TrapDetector {
+        TrapDetector::new(&config).unwrap()
+    }
+
+    #[test]
+    fn deep_documentation_paths_are_fine() {
+        let traps = detector(TrapConfig::default());
+        assert!(traps.check("https://a.test/docs/api/v2/reference/client/methods/list.html").is_accepted());
+    }
+
+    #[test]
+    fn shape_rules_reject_and_can_be_disabled() {
+        let traps = detector(TrapConfig::default());
+        assert_eq!(traps.check("https://a.test/a/b/a/b/a/b/a/b").rule(), Some("trap-repeated-segment"));
+        assert_eq!(traps.check("https://a.test/x///y").rule(), Some("trap-malformed"));
+        assert_eq!(traps.check("https://a.test/x%2F%2Fy").rule(), Some("trap-malformed"));
+        // Only the path counts; an encoded URL in the query is an ordinary parameter
+        assert!(traps.check("https://a.test/login?next=https%3A%2F%2Fa.test%2F").is_accepted());
+        assert!(traps.check("https://a.test/go?to=https:///a.test/").is_accepted());
+        let query = (0..20).map(|i| format!("p{}=1", i)).collect::<Vec<_>>().join("&");
+        assert_eq!(traps.check(&format!("https://a.test/?{}", query)).rule(), Some("trap-query-params"));
+
+        let off = detector(TrapConfig {
+            malformed_paths: false,
+            max_segment_repeats: 0,
+            max_path_depth: 0,
+            max_query_params: 0,
+            ..TrapConfig::default()
+        });
+        assert!(off.check("https://a.test/a/b/a/b/a/b/a/b").is_accepted());
+        assert!(off.check(&format!("https://a.test/x///y?{}", query)).is_accepted());
+    }
+
+    #[test]
+    fn pattern_caps_limit_fetches() {
+        let traps = detector(TrapConfig {
+            pattern_caps: vec![TrapPatternCap { pattern: "/calendar/".into(), max_pages: 2 }],
+            ..TrapConfig::default()
+        });
+        assert!(traps.claim("https://a.test/calendar/2024-01").is_accepted());
+        assert!(traps.claim("https://a.test/calendar/2024-02").is_accepted());
+        assert_eq!(traps.claim("https://a.test/calendar/2024-03").rule(), Some("trap-pattern-cap"));
+        assert!(traps.claim("https://a.test/blog/").is_accepted());
+    }
+
+    #[test]
+    fn incrementing_link_chains_are_cut_off() {
+        let traps = detector(TrapConfig { max_numeric_chain: 3, ..TrapConfig::default() });
+        let day = |n: u32| format!("https://a.test/cal?day={}", n);
+        for n in 1..4 {
+            assert!(traps.follow_link(&day(n), &day(n + 1)).is_accepted());
+        }
+        assert_eq!(traps.follow_link(&day(4), &day(5)).rule(), Some("trap-numeric-sequence"));
+        // Links that change more than one number start no chain
+        assert!(traps.follow_link("https://a.test/2024/05/a", "https://a.test/2024/06/b").is_accepted());
+        assert!(traps.follow_link(&day(4), "https://a.test/about").is_accepted());
+
+        // Making room keeps the chains that are under way
+        traps.forget_short_chains();
+        assert_eq!(traps.chains.len(), 2);
+        assert!(traps.chains.iter().all(|chain| *chain.value() > 1));
+        assert_eq!(traps.follow_link(&day(4), &day(5)).rule(), Some("trap-numeric-sequence"));
+    }
+}
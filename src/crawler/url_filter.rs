@@ -0,0 +1,73 @@
+//! Checks a discovered URL must pass before it is queued
+
+use crate::filtering::FilterDecision;
+use super::scope::UrlScope;
+use super::traps::TrapDetector;
+
+/// Longer URLs are almost always generated loops or tracking junk
+pub const MAX_URL_LENGTH: usize = 500;
+
+/// Depth, scope, length and spider-trap checks, in the order the crawler applies them
+pub struct UrlFilter<'a> {
+    max_depth: usize,
+    scope: &'a UrlScope,
+    traps: &'a TrapDetector,
+}
+
+impl<'a> UrlFilter<'a> {
+    pub fn new(max_depth: usize, scope: &'a UrlScope, traps: &'a TrapDetector) -> Self {
+        Self { max_depth, scope, traps }
+    }
+
+    /// Check a normalized URL found at `depth`
+    pub fn check(&self, url: &str, depth: usize) -> FilterDecision {
+        self.check_depth(depth)
+            .and_then(|| self.scope.decide(url))
+            .and_then(|| Self::check_length(url))
+            .and_then(|| self.traps.check(url))
+    }
+
+    /// Every check's decision, labelled, without stopping at the first rejection
+    pub fn explain(&self, url: &str, depth: usize) -> [(&'static str, FilterDecision); 4] {
+        [
+            ("depth", self.check_depth(depth)),
+            ("scope", self.scope.decide(url)),
+            ("length", Self::check_length(url)),
+            ("traps", self.traps.check(url)),
+        ]
+    }
+
+    fn check_depth(&self, depth: usize) -> FilterDecision {
+        if depth > self.max_depth {
+            FilterDecision::reject_matching("max-depth", format!("depth {} > {}", depth, self.max_depth))
+        } else {
+            FilterDecision::Accepted
+        }
+    }
+
+    fn check_length(url: &str) -> FilterDecision {
+        if url.len() > MAX_URL_LENGTH {
+            FilterDecision::reject_matching("url-length", format!("{} > {} chars", url.len(), MAX_URL_LENGTH))
+        } else {
+            FilterDecision::Accepted
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScopeConfig;
+
+    #[test]
+    fn reports_the_first_failing_check() {
+        let scope = UrlScope::new(&["a.test".to_string()], &ScopeConfig::default()).unwrap();
+        let traps = TrapDetector::default();
+        let filter = UrlFilter::new(2, &scope, &traps);
+        assert!(filter.check("https://a.test/docs/", 2).is_accepted());
+        assert_eq!(filter.check("https://b.test/", 3).rule(), Some("max-depth"));
+        assert_eq!(filter.check("https://b.test/", 1).rule(), Some("scope-domain"));
+        assert_eq!(filter.check(&format!("https://a.test/{}", "x".repeat(500)), 1).rule(), Some("url-length"));
+        assert_eq!(filter.check("https://a.test/a///b", 1).rule(), Some("trap-malformed"));
+    }
+}
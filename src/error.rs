@@ -14,6 +14,15 @@ pub enum AppError {
     #[error("HTTP error: {0}")]
     Reqwest(#[from] reqwest::Error),
 
+    #[error("Redirect error: {0}")]
+    Redirect(String),
+
+    #[error("Cache error: {0}")]
+    Cache(String),
+
+    #[error("Response too large: {0}")]
+    TooLarge(String),
+
     #[error("Regex error: {0}")]
     Regex(#[from] regex::Error),
 
@@ -57,6 +66,37 @@ pub enum AppError {
 /// Type alias for Result with AppError
 pub type Result<T> = std::result::Result<T, AppError>;
 
+impl AppError {
+    /// Short category of a fetch failure (timeout, connect, dns, ...) for reports
+    pub fn category(&self) -> &'static str {
+        match self {
+            AppError::Reqwest(e) if e.is_timeout() => "timeout",
+            AppError::Reqwest(e) if e.is_connect() && Self::is_dns_failure(e) => "dns",
+            AppError::Reqwest(e) if e.is_connect() => "connect",
+            AppError::Reqwest(e) if e.is_status() => "http status",
+            AppError::Reqwest(_) => "request",
+            AppError::Redirect(_) => "redirect",
+            AppError::Cache(_) => "cache",
+            AppError::TooLarge(_) => "too large",
+            AppError::HtmlParse(_) => "parse",
+            _ => "other",
+        }
+    }
+
+    /// Check the error's source chain for a name resolution failure
+    fn is_dns_failure(error: &reqwest::Error) -> bool {
+        let mut source: Option<&dyn std::error::Error> = Some(error);
+        while let Some(e) = source {
+            let message = e.to_string().to_ascii_lowercase();
+            if message.contains("dns error") || message.contains("failed to lookup address") {
+                return true;
+            }
+            source = e.source();
+        }
+        false
+    }
+}
+
 impl From<Box<dyn std::error::Error>> for AppError {
     fn from(error: Box<dyn std::error::Error>) -> Self {
         AppError::Unknown(error.to_string())
@@ -2,6 +2,7 @@
 
 use crate::extraction::core::SimpleResourceExtractor;
 use crate::blacklist::Blacklist;
+use crate::filtering::FilterCounts;
 use scraper::Html;
 use std::sync::Arc;
 use crate::error::Result;
@@ -21,6 +22,11 @@ impl ResourceExtractor {
         })
     }
 
+    /// Links rejected by each filter so far
+    pub fn filter_counts(&self) -> Arc<FilterCounts> {
+        self.inner.filter_counts()
+    }
+
     /// Extract all resources from an HTML document (old interface)
     pub fn extract_resources(
         &self,
@@ -40,6 +46,15 @@ impl ResourceExtractor {
         }).collect()
     }
 
+    /// Extract the resources a stylesheet refers to
+    pub fn extract_css_resources(&self, css: &str, base: &str, next_depth: usize) -> Vec<LegacyResource> {
+        self.inner
+            .extract_css_resources(css, base, next_depth, &self.blacklist)
+            .into_iter()
+            .map(|r| LegacyResource { url: r.url, depth: r.depth })
+            .collect()
+    }
+
     /// Extract resources from legacy HTML using regex patterns (old interface)
     pub fn extract_legacy_resources(
         &self,
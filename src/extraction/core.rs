@@ -3,9 +3,11 @@
 use crate::extraction::validation::ResourceValidator;
 use crate::crawler::StandardUrlParser;
 use crate::crawler::UrlParser;
-use crate::blacklist::Blacklist;
+use crate::blacklist::{Blacklist, RequestContext, ResourceKind};
+use crate::filtering::{FilterCounts, FilterDecision};
 use scraper::{Html, Selector};
 use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
 
 /// Simple resource with minimal data
 #[derive(Debug, Clone)]
@@ -39,38 +41,52 @@ pub struct SimpleResourceProcessor<'a> {
     resources: Vec<SimpleResource>,
     seen_urls: HashSet<String>,
     blacklist: &'a Blacklist,
+    filter_counts: &'a FilterCounts,
 }
 
 impl<'a> SimpleResourceProcessor<'a> {
-    pub fn new(url_parser: &'a StandardUrlParser, blacklist: &'a Blacklist) -> Self {
+    pub fn new(url_parser: &'a StandardUrlParser, blacklist: &'a Blacklist, filter_counts: &'a FilterCounts) -> Self {
         Self {
             url_parser,
             resources: Vec::new(),
             seen_urls: HashSet::new(),
             blacklist,
+            filter_counts,
         }
     }
 
     /// Add a resource if valid
-    pub fn try_add_resource(&mut self, url: &str, ctx: &ExtractionContext) {
-        if self.is_valid_url(url) {
-            if let Some(resolved_url) = self.url_parser.resolve_url(ctx.base_url, url) {
-                let normalized = self.url_parser.normalize_url(&resolved_url);
-                
-                if !self.seen_urls.contains(&normalized) {
-                    self.resources.push(SimpleResource::new(normalized.clone(), ctx.depth));
-                    self.seen_urls.insert(normalized);
-                }
-            }
+    pub fn try_add_resource(&mut self, url: &str, kind: ResourceKind, ctx: &ExtractionContext) {
+        let (decision, normalized) = self.check(url, kind, ctx);
+        self.filter_counts.record(&decision);
+        if let Some(normalized) = normalized
+            && decision.is_accepted()
+            && !self.seen_urls.contains(&normalized)
+        {
+            self.resources.push(SimpleResource::new(normalized.clone(), ctx.depth));
+            self.seen_urls.insert(normalized);
         }
     }
 
-    /// Simple URL validation
-    fn is_valid_url(&self, url: &str) -> bool {
-        !self.blacklist.is_blacklisted(url)
-            && !self.url_parser.is_event_handler(url)
-            && !self.url_parser.is_invalid_url_pattern(url)
-            && ResourceValidator::is_valid_resource_url(url)
+    /// Run an href through the syntax, validity and blacklist checks,
+    /// returning the decision and the normalized URL once it resolves
+    pub fn check(&self, url: &str, kind: ResourceKind, ctx: &ExtractionContext) -> (FilterDecision, Option<String>) {
+        let decision = self.url_parser.check_href(url).and_then(|| ResourceValidator::check(url));
+        if !decision.is_accepted() {
+            return (decision, None);
+        }
+        let Some(normalized) = self.resolve(url, ctx) else {
+            return (FilterDecision::reject("unresolvable"), None);
+        };
+        // Rules like `||cdn.test^$image,third-party` need the absolute URL and its referrer
+        let decision = self.blacklist.check(&normalized, &RequestContext { source: Some(ctx.base_url), kind });
+        (decision, Some(normalized))
+    }
+
+    /// Absolute, normalized form of an href
+    fn resolve(&self, url: &str, ctx: &ExtractionContext) -> Option<String> {
+        let resolved_url = self.url_parser.resolve_url(ctx.base_url, url)?;
+        Some(self.url_parser.normalize_url(&resolved_url))
     }
 
     pub fn into_resources(self) -> Vec<SimpleResource> {
@@ -78,6 +94,9 @@ impl<'a> SimpleResourceProcessor<'a> {
     }
 }
 
+/// `url()` references and `@import` strings
+static CSS_URL_REGEX: OnceLock<regex::Regex> = OnceLock::new();
+
 /// Simplified CSS extractor using basic regex
 struct SimpleCssExtractor;
 
@@ -87,12 +106,13 @@ impl SimpleCssExtractor {
     where
         F: FnMut(&str),
     {
-        // Simple URL extraction - look for url() patterns
-        if let Ok(regex) = regex::Regex::new(r#"url\s*\(\s*["']?([^"')]+)["']?\s*\)"#) {
-            for cap in regex.captures_iter(css_content) {
-                if let Some(url) = cap.get(1) {
-                    add_url(url.as_str());
-                }
+        let regex = CSS_URL_REGEX.get_or_init(|| {
+            regex::Regex::new(r#"(?i:url)\s*\(\s*["']?([^"')]+)["']?\s*\)|@(?i:import)\s*["']([^"']+)["']"#)
+                .expect("css url regex is valid")
+        });
+        for cap in regex.captures_iter(css_content) {
+            if let Some(url) = cap.get(1).or_else(|| cap.get(2)) {
+                add_url(url.as_str());
             }
         }
     }
@@ -102,15 +122,23 @@ impl SimpleCssExtractor {
 #[derive(Clone)]
 pub struct SimpleResourceExtractor {
     url_parser: StandardUrlParser,
+    /// Shared by clones, so every worker's rejections add up
+    filter_counts: Arc<FilterCounts>,
 }
 
 impl SimpleResourceExtractor {
     pub fn new() -> Self {
         Self {
             url_parser: StandardUrlParser,
+            filter_counts: Arc::new(FilterCounts::default()),
         }
     }
 
+    /// Links rejected by each filter so far
+    pub fn filter_counts(&self) -> Arc<FilterCounts> {
+        Arc::clone(&self.filter_counts)
+    }
+
     /// Extract resources from HTML with minimal complexity
     pub fn extract_resources(
         &self,
@@ -122,7 +150,7 @@ impl SimpleResourceExtractor {
         blacklist: &Blacklist,
     ) -> Vec<SimpleResource> {
         let ctx = ExtractionContext::new(base, next_depth, base_domain, base_path);
-        let mut processor = SimpleResourceProcessor::new(&self.url_parser, blacklist);
+        let mut processor = SimpleResourceProcessor::new(&self.url_parser, blacklist, &self.filter_counts);
 
         // Extract from common HTML elements
         self.extract_from_html_elements(doc, &mut processor, &ctx);
@@ -133,36 +161,61 @@ impl SimpleResourceExtractor {
         processor.into_resources()
     }
 
+    /// Extract the images, fonts and imports a stylesheet refers to
+    pub fn extract_css_resources(
+        &self,
+        css: &str,
+        base: &str,
+        next_depth: usize,
+        blacklist: &Blacklist,
+    ) -> Vec<SimpleResource> {
+        let ctx = ExtractionContext::new(base, next_depth, "", "");
+        let mut processor = SimpleResourceProcessor::new(&self.url_parser, blacklist, &self.filter_counts);
+        SimpleCssExtractor::extract_urls(css, |url| {
+            let kind = if url.to_ascii_lowercase().split(['?', '#']).next().unwrap_or_default().ends_with(".css") {
+                ResourceKind::Stylesheet
+            } else {
+                Self::css_url_kind(url)
+            };
+            processor.try_add_resource(url, kind, &ctx);
+        });
+        processor.into_resources()
+    }
+
     /// Extract from standard HTML elements
     fn extract_from_html_elements(&self, doc: &Html, processor: &mut SimpleResourceProcessor, ctx: &ExtractionContext) {
         // Define selectors as simple pairs
         let selectors = [
-            ("a[href]", "href"),
-            ("img[src]", "src"),
-            ("link[href]", "href"),
-            ("script[src]", "src"),
-            ("iframe[src]", "src"),
-            ("frame[src]", "src"),
-            ("embed[src]", "src"),
-            ("object[data]", "data"),
-            ("audio[src]", "src"),
-            ("video[src]", "src"),
-            ("source[src]", "src"),
+            ("a[href]", "href", ResourceKind::Document),
+            ("img[src]", "src", ResourceKind::Image),
+            ("link[href]", "href", ResourceKind::Other),
+            ("script[src]", "src", ResourceKind::Script),
+            ("iframe[src]", "src", ResourceKind::Subdocument),
+            ("frame[src]", "src", ResourceKind::Subdocument),
+            ("embed[src]", "src", ResourceKind::Other),
+            ("object[data]", "data", ResourceKind::Other),
+            ("audio[src]", "src", ResourceKind::Media),
+            ("video[src]", "src", ResourceKind::Media),
+            ("source[src]", "src", ResourceKind::Media),
         ];
 
-        for (selector_str, attr) in &selectors {
+        for (selector_str, attr, kind) in &selectors {
             if let Ok(selector) = Selector::parse(selector_str) {
                 for element in doc.select(&selector) {
                     if let Some(url) = element.value().attr(attr) {
+                        let kind = match element.value().attr("rel") {
+                            Some(rel) if *selector_str == "link[href]" => Self::link_kind(rel),
+                            _ => *kind,
+                        };
                         if attr == &"srcset" {
                             // Handle srcset specially
                             for part in url.split(',') {
-                                if let Some(url_part) = part.trim().split_whitespace().next() {
-                                    processor.try_add_resource(url_part, ctx);
+                                if let Some(url_part) = part.split_whitespace().next() {
+                                    processor.try_add_resource(url_part, kind, ctx);
                                 }
                             }
                         } else {
-                            processor.try_add_resource(url, ctx);
+                            processor.try_add_resource(url, kind, ctx);
                         }
                     }
                 }
@@ -177,7 +230,7 @@ impl SimpleResourceExtractor {
             for element in doc.select(&selector) {
                 let css_content = element.text().collect::<String>();
                 SimpleCssExtractor::extract_urls(&css_content, |url| {
-                    processor.try_add_resource(url, ctx);
+                    processor.try_add_resource(url, Self::css_url_kind(url), ctx);
                 });
             }
         }
@@ -187,7 +240,7 @@ impl SimpleResourceExtractor {
             for element in doc.select(&selector) {
                 if let Some(style) = element.value().attr("style") {
                     SimpleCssExtractor::extract_urls(style, |url| {
-                        processor.try_add_resource(url, ctx);
+                        processor.try_add_resource(url, Self::css_url_kind(url), ctx);
                     });
                 }
             }
@@ -195,6 +248,30 @@ impl SimpleResourceExtractor {
     }
 }
 
+impl SimpleResourceExtractor {
+    /// Resource kind of a `<link>` from its `rel` attribute
+    fn link_kind(rel: &str) -> ResourceKind {
+        let rel = rel.to_ascii_lowercase();
+        if rel.split_whitespace().any(|r| r == "stylesheet") {
+            ResourceKind::Stylesheet
+        } else if rel.contains("icon") {
+            ResourceKind::Image
+        } else {
+            ResourceKind::Other
+        }
+    }
+
+    /// CSS `url()` references are fonts or images
+    fn css_url_kind(url: &str) -> ResourceKind {
+        let path = url.split(['?', '#']).next().unwrap_or_default().to_ascii_lowercase();
+        if [".woff", ".woff2", ".ttf", ".otf", ".eot"].iter().any(|ext| path.ends_with(ext)) {
+            ResourceKind::Font
+        } else {
+            ResourceKind::Image
+        }
+    }
+}
+
 impl Default for SimpleResourceExtractor {
     fn default() -> Self {
         Self::new()
@@ -218,4 +295,19 @@ mod tests {
         assert_eq!(resource.url, "https://example.com");
         assert_eq!(resource.depth, 1);
     }
+
+    #[test]
+    fn test_stylesheet_urls_and_imports_resolve_against_the_stylesheet() {
+        let css = "@import \"print.css\";\n@import url(/base.css);\n\
+                   body { background: URL('../img/bg.png') } .x { background: url(data:image/png;base64,AA) }";
+        let resources = SimpleResourceExtractor::new().extract_css_resources(
+            css,
+            "https://a.test/css/site.css",
+            2,
+            &Blacklist::new(),
+        );
+        let urls: Vec<&str> = resources.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(urls, ["https://a.test/css/print.css", "https://a.test/base.css", "https://a.test/img/bg.png"]);
+        assert!(resources.iter().all(|r| r.depth == 2));
+    }
 }
\ No newline at end of file
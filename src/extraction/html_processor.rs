@@ -2,10 +2,11 @@
 
 use crate::html::standard_parser::StandardHtmlParser;
 use crate::html::preprocessor::HtmlPreprocessor;
-use crate::html::HtmlParser;
+use crate::html::{HtmlParser, ParsedHtml};
 use std::sync::Arc;
 
 use crate::blacklist::Blacklist;
+use crate::filtering::FilterCounts;
 use crate::error::{AppError, Result};
 
 /// Unified HTML processor for cleaning and extracting data from HTML
@@ -29,6 +30,11 @@ impl HtmlProcessor {
         })
     }
 
+    /// Rejection counts of the filters applied to extracted links
+    pub fn filter_counts(&self) -> Arc<FilterCounts> {
+        self.parser.filter_counts()
+    }
+
     /// Preprocess and parse HTML, returning links, title, and new URLs
     pub fn process(
         &self,
@@ -37,7 +43,7 @@ impl HtmlProcessor {
         next_depth: usize,
         base_domain: &str,
         base_path: &str,
-    ) -> Result<(Vec<String>, Option<String>, Vec<(String, usize)>)> {
+    ) -> Result<ParsedHtml> {
         // Preprocess HTML
         let cleaned_html = self.preprocessor.preprocess(html);
         // Parse and extract data
@@ -45,6 +51,11 @@ impl HtmlProcessor {
             .parse_html(base, &cleaned_html, next_depth, base_domain, base_path)
             .map_err(|e| AppError::HtmlParse(e.to_string()))
     }
+
+    /// URLs of the images, fonts and imports a stylesheet refers to
+    pub fn process_css(&self, base: &str, css: &str, next_depth: usize) -> Vec<(String, usize)> {
+        self.parser.parse_css(base, css, next_depth)
+    }
 }
 
 impl Default for HtmlProcessor {
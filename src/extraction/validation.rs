@@ -1,5 +1,7 @@
 //! URL and resource validation utilities
 
+use crate::filtering::FilterDecision;
+
 /// Constants for file extension validation
 pub const ALLOWED_EXTENSIONS: &[&str] = &[
     "html", "htm", "css", "js", "png", "jpg", "jpeg", "svg", "gif", "webp",
@@ -22,13 +24,29 @@ pub struct ResourceValidator;
 impl ResourceValidator {
     /// Main validation entry point
     pub fn is_valid_resource_url(url: &str) -> bool {
+        Self::check(url).is_accepted()
+    }
+
+    /// Validate a URL, naming the first check it fails
+    pub fn check(url: &str) -> FilterDecision {
         let path = Self::extract_path(url);
-        
-        Self::is_non_empty_path(&path)
-            && Self::has_no_suspicious_patterns(&path)
-            && Self::has_no_hidden_files(&path)
-            && Self::has_valid_characters(&path)
-            && Self::has_valid_extension(&path)
+
+        if !Self::is_non_empty_path(&path) {
+            return FilterDecision::reject("empty-path");
+        }
+        if let Some(pattern) = SUSPICIOUS_PATTERNS.iter().find(|pattern| path.contains(*pattern)) {
+            return FilterDecision::reject_matching("suspicious-pattern", *pattern);
+        }
+        if let Some(segment) = Self::hidden_segment(&path) {
+            return FilterDecision::reject_matching("hidden-file", segment);
+        }
+        if let Some(c) = Self::invalid_character(&path) {
+            return FilterDecision::reject_matching("invalid-character", c.to_string());
+        }
+        if let Some(extension) = Self::disallowed_extension(&path) {
+            return FilterDecision::reject_matching("extension", extension);
+        }
+        FilterDecision::Accepted
     }
     
     /// Extract path from URL, handling both absolute and relative URLs
@@ -45,45 +63,31 @@ impl ResourceValidator {
         !path.trim().is_empty()
     }
     
-    /// Check for suspicious patterns that indicate malicious or broken URLs
-    fn has_no_suspicious_patterns(path: &str) -> bool {
-        !SUSPICIOUS_PATTERNS.iter().any(|pattern| path.contains(pattern))
-    }
-    
-    /// Check for hidden files (starting with .) except .well-known
-    fn has_no_hidden_files(path: &str) -> bool {
-        !path.split('/').any(|segment| {
-            segment.starts_with('.') 
-                && segment != ".well-known" 
+    /// Find a hidden file or directory (starting with .) other than .well-known,
+    /// `.` and `..` of a relative URL
+    fn hidden_segment(path: &str) -> Option<&str> {
+        path.split('/').find(|segment| {
+            segment.starts_with('.')
+                && !matches!(*segment, ".well-known" | "." | "..")
                 && !segment.is_empty()
         })
     }
-    
-    /// Check if path contains only allowed characters
-    fn has_valid_characters(path: &str) -> bool {
-        path.chars().all(|c| {
-            c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '/' | '.' | '%')
+
+    /// Find a character outside the allowed set
+    fn invalid_character(path: &str) -> Option<char> {
+        path.chars().find(|&c| {
+            !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '/' | '.' | '%'))
         })
     }
-    
-    /// Validate file extension if the path represents a file
-    fn has_valid_extension(path: &str) -> bool {
-        if let Some(filename) = path.rsplit('/').next() {
-            if filename.contains('.') && !filename.ends_with('/') {
-                return Self::is_allowed_extension(filename);
-            }
-        }
-        true // Not a file, so extension validation passes
-    }
-    
-    /// Check if the file extension is in the allowed list
-    fn is_allowed_extension(filename: &str) -> bool {
-        if let Some(extension) = filename.rsplit('.').next() {
-            let ext_lower = extension.to_ascii_lowercase();
-            ALLOWED_EXTENSIONS.contains(&ext_lower.as_str())
-        } else {
-            false
+
+    /// Extension of the file the path names, if it is not in the allowed list
+    fn disallowed_extension(path: &str) -> Option<String> {
+        let filename = path.rsplit('/').next()?;
+        if !filename.contains('.') {
+            return None; // Not a file, so extension validation passes
         }
+        let extension = filename.rsplit('.').next()?.to_ascii_lowercase();
+        (!ALLOWED_EXTENSIONS.contains(&extension.as_str())).then_some(extension)
     }
 }
 
@@ -106,4 +110,21 @@ mod tests {
         assert!(!ResourceValidator::is_valid_resource_url("/.hidden/file.html"));
     }
 
+    #[test]
+    fn test_check_names_failing_rule() {
+        assert_eq!(
+            ResourceValidator::check("https://example.com/setup.exe"),
+            FilterDecision::reject_matching("extension", "exe")
+        );
+        assert_eq!(
+            ResourceValidator::check("https://example.com/page(1).html"),
+            FilterDecision::reject_matching("invalid-character", "(")
+        );
+        assert_eq!(
+            ResourceValidator::check("/.git/config"),
+            FilterDecision::reject_matching("hidden-file", ".git")
+        );
+        assert!(ResourceValidator::check("https://example.com/.well-known/security.txt").is_accepted());
+    }
+
 }
\ No newline at end of file
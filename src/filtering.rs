@@ -0,0 +1,95 @@
+//! Outcomes of the checks a discovered URL passes before it is crawled
+
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What one URL check decided
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    Accepted,
+    /// Rejected by `rule`, with the pattern or value that triggered it if there is one
+    Rejected { rule: &'static str, matched: Option<String> },
+}
+
+impl FilterDecision {
+    pub fn reject(rule: &'static str) -> Self {
+        Self::Rejected { rule, matched: None }
+    }
+
+    pub fn reject_matching(rule: &'static str, matched: impl Into<String>) -> Self {
+        Self::Rejected { rule, matched: Some(matched.into()) }
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted)
+    }
+
+    /// Name of the rule that rejected the URL
+    pub fn rule(&self) -> Option<&'static str> {
+        match self {
+            Self::Accepted => None,
+            Self::Rejected { rule, .. } => Some(rule),
+        }
+    }
+
+    /// Run the next check only if this one accepted
+    pub fn and_then(self, next: impl FnOnce() -> Self) -> Self {
+        match self {
+            Self::Accepted => next(),
+            rejected => rejected,
+        }
+    }
+}
+
+impl fmt::Display for FilterDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accepted => write!(f, "accepted"),
+            Self::Rejected { rule, matched: None } => write!(f, "rejected by {}", rule),
+            Self::Rejected { rule, matched: Some(matched) } => write!(f, "rejected by {} ({})", rule, matched),
+        }
+    }
+}
+
+/// Rejections per rule, shared by link extraction and the crawl engine
+#[derive(Debug, Default)]
+pub struct FilterCounts {
+    counts: DashMap<&'static str, usize>,
+}
+
+impl FilterCounts {
+    /// Count `decision` if it is a rejection
+    pub fn record(&self, decision: &FilterDecision) {
+        if let Some(rule) = decision.rule() {
+            *self.counts.entry(rule).or_insert(0) += 1;
+        }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, usize> {
+        self.counts.iter().map(|entry| (entry.key().to_string(), *entry.value())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn and_then_stops_at_first_rejection() {
+        let decision = FilterDecision::Accepted
+            .and_then(|| FilterDecision::reject_matching("extension", "exe"))
+            .and_then(|| FilterDecision::reject("recursive"));
+        assert_eq!(decision.rule(), Some("extension"));
+        assert_eq!(decision.to_string(), "rejected by extension (exe)");
+    }
+
+    #[test]
+    fn counts_only_rejections() {
+        let counts = FilterCounts::default();
+        counts.record(&FilterDecision::Accepted);
+        counts.record(&FilterDecision::reject("scope"));
+        counts.record(&FilterDecision::reject("scope"));
+        assert_eq!(counts.snapshot(), BTreeMap::from([("scope".to_string(), 2)]));
+    }
+}
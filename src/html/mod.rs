@@ -5,4 +5,4 @@ pub mod preprocessor;
 pub mod r#trait;
 
 // Traits
-pub use r#trait::HtmlParser;
\ No newline at end of file
+pub use r#trait::{HtmlParser, ParsedHtml};
\ No newline at end of file
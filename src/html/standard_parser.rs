@@ -2,10 +2,12 @@
 
 use crate::html::preprocessor::HtmlPreprocessor;
 use crate::extraction::ResourceExtractor;
-use crate::html::HtmlParser;
+use crate::html::{HtmlParser, ParsedHtml};
 use crate::blacklist::Blacklist;
+use crate::filtering::FilterCounts;
 use scraper::{Html, Selector};
 use std::sync::Arc;
+use url::Url;
 use crate::error::Result;
 
 /// Standard HTML parser implementation with comprehensive resource extraction
@@ -29,6 +31,20 @@ impl StandardHtmlParser {
             resource_extractor: ResourceExtractor::new(blacklist)?,
         })
     }
+
+    /// Rejection counts of the filters applied to extracted links
+    pub fn filter_counts(&self) -> Arc<FilterCounts> {
+        self.resource_extractor.filter_counts()
+    }
+
+    /// URLs a stylesheet refers to, resolved against its URL
+    pub fn parse_css(&self, base: &str, css: &str, next_depth: usize) -> Vec<(String, usize)> {
+        self.resource_extractor
+            .extract_css_resources(css, base, next_depth)
+            .into_iter()
+            .map(|r| (r.url, r.depth))
+            .collect()
+    }
 }
 
 impl HtmlParser for StandardHtmlParser {
@@ -39,7 +55,7 @@ impl HtmlParser for StandardHtmlParser {
         next_depth: usize,
         base_domain: &str,
         base_path: &str,
-    ) -> Result<(Vec<String>, Option<String>, Vec<(String, usize)>)> {
+    ) -> Result<ParsedHtml> {
         // Preprocess HTML to handle malformed patterns
         let preprocessed_html = self.preprocessor.preprocess(html);
 
@@ -52,6 +68,9 @@ impl HtmlParser for StandardHtmlParser {
         // Extract title
         let title = self.extract_title(&doc);
 
+        // Extract the canonical URL the page declares for itself
+        let canonical = self.extract_canonical(&doc, &base_href);
+
         // Extract resources using the refactored extractor
         let mut resources = self.resource_extractor.extract_resources(
             &doc,
@@ -80,23 +99,38 @@ impl HtmlParser for StandardHtmlParser {
             .map(|r| (r.url.clone(), r.depth))
             .collect();
 
-        Ok((links, title, new_urls))
+        Ok((links, title, new_urls, canonical))
     }
 }
 
 impl StandardHtmlParser {
     /// Extract base href from HTML document if present
     fn extract_base_href(&self, doc: &Html, default_base: &str) -> String {
-        if let Ok(base_selector) = Selector::parse("base[href]") {
-            if let Some(base_element) = doc.select(&base_selector).next() {
-                if let Some(href) = base_element.value().attr("href") {
-                    return href.to_string();
-                }
-            }
+        if let Ok(base_selector) = Selector::parse("base[href]")
+            && let Some(base_element) = doc.select(&base_selector).next()
+            && let Some(href) = base_element.value().attr("href")
+        {
+            return href.to_string();
         }
         default_base.to_string()
     }
 
+    /// Extract `<link rel="canonical">`, resolved against the base URL
+    fn extract_canonical(&self, doc: &Html, base: &str) -> Option<String> {
+        let selector = Selector::parse("link[rel][href]").ok()?;
+        let href = doc
+            .select(&selector)
+            .find(|link| {
+                link.value()
+                    .attr("rel")
+                    .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("canonical")))
+            })?
+            .value()
+            .attr("href")?
+            .trim();
+        Url::parse(base).and_then(|base| base.join(href)).ok().map(String::from)
+    }
+
     /// Extract title from HTML document
     fn extract_title(&self, doc: &Html) -> Option<String> {
         Selector::parse("title")
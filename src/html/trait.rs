@@ -2,6 +2,10 @@
 
 use crate::error::Result;
 
+/// Links, title, newly discovered `(url, depth)` pairs and the absolute
+/// `rel="canonical"` URL extracted from a page
+pub type ParsedHtml = (Vec<String>, Option<String>, Vec<(String, usize)>, Option<String>);
+
 /// HTML parser trait
 pub trait HtmlParser: Send + Sync {
     /// Parse HTML and extract links and title
@@ -12,5 +16,5 @@ pub trait HtmlParser: Send + Sync {
         next_depth: usize,
         base_domain: &str,
         base_path: &str,
-    ) -> Result<(Vec<String>, Option<String>, Vec<(String, usize)>)>;
+    ) -> Result<ParsedHtml>;
 }
@@ -0,0 +1,325 @@
+//! Disk-backed HTTP response cache with conditional revalidation
+//!
+//! Each successful response is stored as two files named after a hash of the
+//! normalized URL: `<hash>.json` with status, headers and redirects, and
+//! `<hash>.body` with the raw body. Later runs send the stored `ETag` and
+//! `Last-Modified` validators and serve the cached copy on `304 Not Modified`.
+
+use crate::crawler::StandardUrlParser;
+use crate::error::{AppError, Result};
+use crate::http::{FetchResponse, HttpClient, RedirectHop, ReqwestClient, ResponseSource};
+use crate::models::CacheStats;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use reqwest::Version;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Metadata stored next to a cached body
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    status: u16,
+    final_url: String,
+    headers: Vec<(String, String)>,
+    #[serde(default)]
+    redirects: Vec<(String, u16)>,
+}
+
+impl CacheEntry {
+    fn from_response(url: &str, response: &FetchResponse) -> Self {
+        Self {
+            url: url.to_string(),
+            status: response.status,
+            final_url: response.final_url.clone(),
+            headers: response
+                .headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect(),
+            redirects: response.redirects.iter().map(|hop| (hop.url.clone(), hop.status)).collect(),
+        }
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// `If-None-Match` / `If-Modified-Since` headers built from the stored validators
+    fn conditional_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = self.header("etag").and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_NONE_MATCH, value);
+        }
+        if let Some(value) = self.header("last-modified").and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(IF_MODIFIED_SINCE, value);
+        }
+        headers
+    }
+
+    fn into_response(self, body: Vec<u8>, elapsed: Duration, source: ResponseSource) -> FetchResponse {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                headers.append(name, value);
+            }
+        }
+        FetchResponse {
+            status: self.status,
+            version: Version::HTTP_11,
+            headers,
+            request_headers: HeaderMap::new(),
+            final_url: self.final_url,
+            redirects: self
+                .redirects
+                .into_iter()
+                .map(|(url, status)| RedirectHop { url, status, headers: HeaderMap::new() })
+                .collect(),
+            elapsed,
+            remote_addr: None,
+            body,
+            source,
+        }
+    }
+}
+
+/// Hit/miss counters shared by all workers
+#[derive(Default)]
+struct CacheCounters {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    revalidated: AtomicUsize,
+}
+
+/// `HttpClient` that serves and revalidates responses from a disk cache
+pub struct CachingClient {
+    inner: Arc<ReqwestClient>,
+    dir: PathBuf,
+    offline: bool,
+    counters: CacheCounters,
+}
+
+impl CachingClient {
+    /// Open (creating if needed) a cache directory. With `offline`, the network
+    /// is never used and URLs missing from the cache fail to fetch.
+    pub fn open<P: AsRef<Path>>(dir: P, inner: Arc<ReqwestClient>, offline: bool) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            inner,
+            dir,
+            offline,
+            counters: CacheCounters::default(),
+        })
+    }
+
+    /// Snapshot of the cache counters
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            revalidated: self.counters.revalidated.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn fetch_cached(&self, url: &str) -> Result<FetchResponse> {
+        let key = StandardUrlParser.normalize_url(url);
+        let cached = self.load(&key);
+
+        if self.offline {
+            let (entry, body) = cached.ok_or_else(|| AppError::Cache(format!("{} is not in the cache", url)))?;
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(entry.into_response(body, Duration::ZERO, ResponseSource::Replayed));
+        }
+
+        let validators = cached.as_ref().map(|(entry, _)| entry.conditional_headers()).unwrap_or_default();
+        let response = self.inner.fetch_with_headers(url, &validators).await?;
+
+        if response.status == 304
+            && let Some((entry, body)) = cached
+        {
+            log::debug!("Cache revalidated: {}", url);
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            self.counters.revalidated.fetch_add(1, Ordering::Relaxed);
+            // Keep what was actually exchanged, so the archive can tell this from a fresh fetch
+            let mut cached_response =
+                entry.into_response(body, response.elapsed, ResponseSource::Revalidated(response.headers));
+            cached_response.version = response.version;
+            cached_response.request_headers = response.request_headers;
+            cached_response.remote_addr = response.remote_addr;
+            return Ok(cached_response);
+        }
+
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        if response.is_success() {
+            self.store(&key, &response);
+        }
+        Ok(response)
+    }
+
+    /// Read a cache entry, ignoring missing, unreadable or colliding entries
+    fn load(&self, key: &str) -> Option<(CacheEntry, Vec<u8>)> {
+        let (meta_path, body_path) = self.entry_paths(key);
+        let meta = fs::read_to_string(&meta_path).ok()?;
+        let entry: CacheEntry = match serde_json::from_str(&meta) {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Ignoring corrupt cache entry {}: {}", meta_path.display(), e);
+                return None;
+            }
+        };
+        if entry.url != key {
+            return None;
+        }
+        let body = fs::read(&body_path).ok()?;
+        Some((entry, body))
+    }
+
+    /// Write a response to the cache; failures are logged and otherwise ignored
+    fn store(&self, key: &str, response: &FetchResponse) {
+        let (meta_path, body_path) = self.entry_paths(key);
+        let entry = CacheEntry::from_response(key, response);
+        let result = serde_json::to_string(&entry)
+            .map_err(|e| AppError::Cache(e.to_string()))
+            .and_then(|meta| {
+                // Body first, so a metadata file always has a complete body next to it
+                Self::write_atomic(&body_path, &response.body)?;
+                Self::write_atomic(&meta_path, meta.as_bytes())
+            });
+        if let Err(e) = result {
+            log::warn!("Failed to cache {}: {}", key, e);
+        }
+    }
+
+    fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    fn entry_paths(&self, key: &str) -> (PathBuf, PathBuf) {
+        let name = format!("{:016x}", Self::hash_key(key));
+        (self.dir.join(format!("{}.json", name)), self.dir.join(format!("{}.body", name)))
+    }
+
+    /// FNV-1a, chosen because file names must stay stable across builds
+    fn hash_key(key: &str) -> u64 {
+        key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+}
+
+impl HttpClient for CachingClient {
+    fn fetch<'a>(&'a self, url: &'a str) -> Pin<Box<dyn Future<Output = Result<FetchResponse>> + Send + 'a>> {
+        Box::pin(self.fetch_cached(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CrawlerConfig, PolitenessConfig};
+    use crate::http::{PolitenessScheduler, WarcRecorder};
+    use crate::io::WarcWriter;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_conditional_headers_from_validators() {
+        let entry = CacheEntry {
+            url: "https://example.com/".to_string(),
+            status: 200,
+            final_url: "https://example.com/".to_string(),
+            headers: vec![
+                ("ETag".to_string(), "\"abc\"".to_string()),
+                ("last-modified".to_string(), "Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+            ],
+            redirects: Vec::new(),
+        };
+        let headers = entry.conditional_headers();
+        assert_eq!(headers.get(IF_NONE_MATCH).unwrap(), "\"abc\"");
+        assert_eq!(headers.get(IF_MODIFIED_SINCE).unwrap(), "Wed, 21 Oct 2015 07:28:00 GMT");
+
+        let response = entry.into_response(b"body".to_vec(), Duration::ZERO, ResponseSource::Replayed);
+        assert_eq!(response.header("etag"), Some("\"abc\""));
+        assert_eq!(response.body, b"body");
+    }
+
+    #[test]
+    fn test_hash_key_is_stable() {
+        assert_eq!(CachingClient::hash_key(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(CachingClient::hash_key("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[tokio::test]
+    async fn cached_fetches_are_archived_as_revisits_and_replays() {
+        // Answers one request with 304 and returns what it was sent
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap().to_ascii_lowercase()
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let scheduler = Arc::new(PolitenessScheduler::new(PolitenessConfig::default()));
+        let reqwest = Arc::new(ReqwestClient::new("test-agent", scheduler).unwrap());
+        let online = Arc::new(CachingClient::open(&cache_dir, reqwest.clone(), false).unwrap());
+        let key = StandardUrlParser.normalize_url(&url);
+        let entry = CacheEntry {
+            url: key.clone(),
+            status: 200,
+            final_url: url.clone(),
+            headers: vec![("ETag".to_string(), "\"v1\"".to_string())],
+            redirects: Vec::new(),
+        };
+        online.store(&key, &entry.into_response(b"cached".to_vec(), Duration::ZERO, ResponseSource::Network));
+
+        let mut config = CrawlerConfig::default();
+        config.warc.gzip = false;
+        let writer = Arc::new(WarcWriter::open(dir.path().join("warc"), &config).unwrap());
+
+        let revalidated = WarcRecorder::new(online, writer.clone()).fetch(&url).await.unwrap();
+        assert_eq!(revalidated.body, b"cached");
+        assert!(matches!(revalidated.source, ResponseSource::Revalidated(_)));
+        assert!(server.join().unwrap().contains("if-none-match: \"v1\""));
+
+        let offline = Arc::new(CachingClient::open(&cache_dir, reqwest, true).unwrap());
+        let replayed = WarcRecorder::new(offline, writer.clone()).fetch(&url).await.unwrap();
+        assert!(matches!(replayed.source, ResponseSource::Replayed));
+        writer.finish().unwrap();
+
+        let path = fs::read_dir(dir.path().join("warc")).unwrap().next().unwrap().unwrap().path();
+        let content = String::from_utf8(fs::read(&path).unwrap()).unwrap();
+        let types: Vec<&str> = content
+            .lines()
+            .filter_map(|line| line.strip_prefix("WARC-Type: "))
+            .collect();
+        assert_eq!(types, ["warcinfo", "revisit", "request", "response"]);
+        assert!(content.contains("WARC-Profile: http://netpreserve.org/warc/1.1/revisit/server-not-modified\r\n"));
+        assert!(content.contains("HTTP/1.1 304 Not Modified\r\n"));
+    }
+}
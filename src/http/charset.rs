@@ -0,0 +1,135 @@
+//! Text decoding for fetched bodies: textual type detection and charset sniffing
+
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::Regex;
+use std::sync::OnceLock;
+
+/// Number of leading bytes scanned for a `<meta charset>` declaration
+const META_SNIFF_BYTES: usize = 1024;
+
+static META_CHARSET_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// Decodes response bodies to text for textual content types
+pub struct BodyDecoder;
+
+impl BodyDecoder {
+    /// Check if a content type carries text that should be decoded
+    pub fn is_textual(content_type: &str) -> bool {
+        let mime = Self::mime_type(content_type);
+        mime.starts_with("text/")
+            || mime.ends_with("+xml")
+            || mime.ends_with("+json")
+            || matches!(
+                mime.as_str(),
+                "application/xml"
+                    | "application/json"
+                    | "application/javascript"
+                    | "application/x-javascript"
+                    | "application/ecmascript"
+            )
+    }
+
+    /// Decode a body to text, or `None` for binary content types
+    pub fn decode(content_type: &str, body: &[u8]) -> Option<String> {
+        if !Self::is_textual(content_type) {
+            return None;
+        }
+        let encoding = Self::detect_encoding(content_type, body);
+        // decode() honors a BOM over the detected encoding
+        let (text, _, had_errors) = encoding.decode(body);
+        if had_errors {
+            log::debug!("Body contained invalid {} sequences", encoding.name());
+        }
+        Some(text.into_owned())
+    }
+
+    /// Encode text back into the charset the body was originally served in
+    pub fn encode(content_type: &str, original: &[u8], text: &str) -> Vec<u8> {
+        let encoding = Self::detect_encoding(content_type, original);
+        let (bytes, _, _) = encoding.encode(text);
+        bytes.into_owned()
+    }
+
+    /// Determine the encoding from the Content-Type charset, then `<meta charset>`, else UTF-8
+    pub fn detect_encoding(content_type: &str, body: &[u8]) -> &'static Encoding {
+        if let Some(encoding) = Self::charset_param(content_type).and_then(|c| Encoding::for_label(c.as_bytes())) {
+            return encoding;
+        }
+        if Self::mime_type(content_type).contains("html")
+            && let Some(encoding) = Self::meta_charset(body)
+        {
+            return encoding;
+        }
+        UTF_8
+    }
+
+    /// Extract the bare MIME type (lowercase, without parameters)
+    fn mime_type(content_type: &str) -> String {
+        content_type
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase()
+    }
+
+    /// Extract the `charset` parameter of a Content-Type header
+    fn charset_param(content_type: &str) -> Option<String> {
+        content_type.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+        })
+    }
+
+    /// Find `<meta charset=...>` or `<meta http-equiv=... content="...charset=...">` near the top of a document
+    fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+        let regex = META_CHARSET_REGEX.get_or_init(|| {
+            Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?\s*([a-z0-9_:.\-]+)"#)
+                .expect("meta charset regex is valid")
+        });
+        let head = &body[..body.len().min(META_SNIFF_BYTES)];
+        let label = regex.captures(head)?.get(1)?.as_bytes();
+        let encoding = Encoding::for_label(label)?;
+        // A meta declaration can only name an ASCII-compatible encoding
+        if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+            Some(UTF_8)
+        } else {
+            Some(encoding)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_types_are_not_decoded() {
+        assert!(BodyDecoder::decode("image/png", &[0x89, b'P', b'N', b'G']).is_none());
+        assert!(BodyDecoder::decode("application/pdf", b"%PDF-1.7").is_none());
+        assert!(BodyDecoder::is_textual("image/svg+xml"));
+        assert!(BodyDecoder::is_textual("text/css; charset=utf-8"));
+    }
+
+    #[test]
+    fn test_charset_from_header_and_meta() {
+        let latin1 = b"caf\xe9";
+        assert_eq!(BodyDecoder::decode("text/plain; charset=ISO-8859-1", latin1).unwrap(), "café");
+
+        let html = b"<html><head><meta charset=\"windows-1252\"></head><body>caf\xe9</body></html>";
+        let text = BodyDecoder::decode("text/html", html).unwrap();
+        assert!(text.contains("café"));
+
+        let http_equiv = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=iso-8859-1\">\xe9";
+        assert!(BodyDecoder::decode("text/html", http_equiv).unwrap().ends_with('é'));
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let html = b"<meta charset=\"windows-1252\">caf\xe9";
+        let text = BodyDecoder::decode("text/html", html).unwrap();
+        assert_eq!(BodyDecoder::encode("text/html", html, &text), html.to_vec());
+    }
+}
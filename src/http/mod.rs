@@ -5,11 +5,21 @@ use std::pin::Pin;
 use crate::error::Result;
 
 pub mod reqwest;
+pub mod politeness;
+pub mod charset;
+pub mod response;
+pub mod cache;
+pub mod recorder;
 
 pub use reqwest::ReqwestClient;
+pub use politeness::PolitenessScheduler;
+pub use charset::BodyDecoder;
+pub use response::{FetchResponse, RedirectHop, ResponseSource};
+pub use cache::CachingClient;
+pub use recorder::WarcRecorder;
 
 /// HTTP client trait using manual future implementation
 pub trait HttpClient: Send + Sync {
-    /// Fetch a URL with retry logic
-    fn fetch<'a>(&'a self, url: &'a str) -> Pin<Box<dyn Future<Output = Result<(u16, String, Option<usize>, String)>> + Send + 'a>>;
+    /// Fetch a URL with retry logic, following redirects and returning the body bytes undecoded
+    fn fetch<'a>(&'a self, url: &'a str) -> Pin<Box<dyn Future<Output = Result<FetchResponse>> + Send + 'a>>;
 }
\ No newline at end of file
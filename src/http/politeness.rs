@@ -0,0 +1,153 @@
+//! Per-host politeness scheduling: concurrency limits, request spacing and adaptive backoff
+
+use crate::config::PolitenessConfig;
+use crate::error::Result;
+use dashmap::DashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{sleep_until, Instant};
+
+/// Timing state for a single host
+struct HostTiming {
+    /// Earliest moment the next request may start
+    next_allowed: Instant,
+    /// Delay from config, raised by robots.txt Crawl-delay
+    base_delay: Duration,
+    /// Delay currently in effect, raised after throttling responses
+    current_delay: Duration,
+}
+
+/// Scheduling state for a single host
+struct HostState {
+    semaphore: Arc<Semaphore>,
+    timing: Mutex<HostTiming>,
+}
+
+/// Schedules requests so that each host sees bounded concurrency and spacing
+pub struct PolitenessScheduler {
+    config: PolitenessConfig,
+    hosts: DashMap<String, Arc<HostState>>,
+}
+
+impl PolitenessScheduler {
+    pub fn new(config: PolitenessConfig) -> Self {
+        Self {
+            config,
+            hosts: DashMap::new(),
+        }
+    }
+
+    /// Wait for a concurrency slot and for the host's delay to elapse.
+    /// The returned permit must be held until the response body has been read.
+    pub async fn acquire(&self, host: &str) -> Result<OwnedSemaphorePermit> {
+        let state = self.host_state(host);
+        let permit = state.semaphore.clone().acquire_owned().await?;
+
+        // Reserve the next slot under the lock, then sleep without holding it
+        let start_at = {
+            let mut timing = Self::lock_timing(&state);
+            let now = Instant::now();
+            let start_at = timing.next_allowed.max(now);
+            timing.next_allowed = start_at + timing.current_delay;
+            start_at
+        };
+        sleep_until(start_at).await;
+
+        Ok(permit)
+    }
+
+    /// Apply a robots.txt `Crawl-delay` to a host
+    pub fn set_crawl_delay(&self, host: &str, delay: Duration) {
+        if !self.config.respect_crawl_delay {
+            return;
+        }
+        let delay = delay.min(self.max_delay());
+        let state = self.host_state(host);
+        let mut timing = Self::lock_timing(&state);
+        if delay > timing.base_delay {
+            log::debug!("Using Crawl-delay of {:?} for {}", delay, host);
+            timing.base_delay = delay;
+            timing.current_delay = timing.current_delay.max(delay);
+        }
+    }
+
+    /// Slow down after a 429/503 response, honoring `Retry-After` when present
+    pub fn record_throttled(&self, host: &str, retry_after: Option<Duration>) {
+        let state = self.host_state(host);
+        let mut timing = Self::lock_timing(&state);
+
+        let doubled = (timing.current_delay * 2).max(Duration::from_secs(1));
+        let delay = retry_after.map_or(doubled, |r| r.max(timing.current_delay));
+        timing.current_delay = delay.min(self.max_delay());
+
+        let resume_at = Instant::now() + retry_after.unwrap_or(timing.current_delay).min(self.max_delay());
+        timing.next_allowed = timing.next_allowed.max(resume_at);
+        log::debug!("Host {} throttled, delay now {:?}", host, timing.current_delay);
+    }
+
+    /// Gradually return to the base delay after a successful response
+    pub fn record_success(&self, host: &str) {
+        let state = self.host_state(host);
+        let mut timing = Self::lock_timing(&state);
+        if timing.current_delay > timing.base_delay {
+            timing.current_delay = (timing.current_delay * 3 / 4).max(timing.base_delay);
+        }
+    }
+
+    /// Get or create the scheduling state for a host
+    fn host_state(&self, host: &str) -> Arc<HostState> {
+        if let Some(state) = self.hosts.get(host) {
+            return Arc::clone(&state);
+        }
+
+        self.hosts
+            .entry(host.to_string())
+            .or_insert_with(|| {
+                let (max_concurrent, delay_ms) = self.limits_for(host);
+                let delay = Duration::from_millis(delay_ms);
+                Arc::new(HostState {
+                    semaphore: Arc::new(Semaphore::new(max_concurrent.max(1))),
+                    timing: Mutex::new(HostTiming {
+                        next_allowed: Instant::now(),
+                        base_delay: delay,
+                        current_delay: delay,
+                    }),
+                })
+            })
+            .clone()
+    }
+
+    /// Resolve the concurrency limit and delay for a host, applying overrides
+    fn limits_for(&self, host: &str) -> (usize, u64) {
+        let host_override = self.config.override_for(host);
+        let max_concurrent = host_override
+            .and_then(|o| o.max_concurrent)
+            .unwrap_or(self.config.max_concurrent_per_host);
+        let delay_ms = host_override
+            .and_then(|o| o.min_delay_ms)
+            .unwrap_or(self.config.min_delay_ms);
+        (max_concurrent, delay_ms)
+    }
+
+    fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.config.max_delay_ms)
+    }
+
+    fn lock_timing(state: &HostState) -> std::sync::MutexGuard<'_, HostTiming> {
+        state.timing.lock().unwrap_or_else(|e| {
+            log::warn!("Mutex poisoned in host timing, recovering");
+            e.into_inner()
+        })
+    }
+}
+
+/// Parse a `Retry-After` header value (delta-seconds or HTTP-date)
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
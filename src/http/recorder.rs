@@ -0,0 +1,33 @@
+//! `HttpClient` wrapper that archives every fetch to WARC
+
+use crate::error::Result;
+use crate::http::{FetchResponse, HttpClient};
+use crate::io::WarcWriter;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Passes fetches through to another client and writes each exchange to a `WarcWriter`
+pub struct WarcRecorder {
+    inner: Arc<dyn HttpClient>,
+    writer: Arc<WarcWriter>,
+}
+
+impl WarcRecorder {
+    pub fn new(inner: Arc<dyn HttpClient>, writer: Arc<WarcWriter>) -> Self {
+        Self { inner, writer }
+    }
+}
+
+impl HttpClient for WarcRecorder {
+    fn fetch<'a>(&'a self, url: &'a str) -> Pin<Box<dyn Future<Output = Result<FetchResponse>> + Send + 'a>> {
+        Box::pin(async move {
+            let response = self.inner.fetch(url).await?;
+            // A failed archive write should not fail the crawl
+            if let Err(e) = self.writer.write_exchange(url, &response) {
+                log::warn!("Failed to write WARC records for {}: {}", url, e);
+            }
+            Ok(response)
+        })
+    }
+}
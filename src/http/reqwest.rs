@@ -1,115 +1,218 @@
 //! Implementation of HTTP client using reqwest
 
 use crate::config::REQUEST_TIMEOUT_MS;
-use crate::http::HttpClient;
-use crate::error::Result;
+use crate::http::{FetchResponse, HttpClient, RedirectHop, ResponseSource};
+use crate::http::politeness::{parse_retry_after, PolitenessScheduler};
+use crate::error::{AppError, Result};
 
 use core::time::Duration;
-use reqwest::{Client, ClientBuilder};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, HOST, USER_AGENT};
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+use reqwest::{Client, ClientBuilder, Response};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::Semaphore;
-use tokio::time::{sleep, Instant};
+use std::time::Instant;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::time::sleep;
+use url::Url;
+
+/// Maximum number of redirects followed for one fetch
+const MAX_REDIRECTS: usize = 10;
 
 /// Implementation of HttpClient using reqwest
 pub struct ReqwestClient {
     client: Client,
-    rate_limiter: Arc<Semaphore>,
-    last_request_time: Arc<tokio::sync::Mutex<Instant>>,
+    scheduler: Arc<PolitenessScheduler>,
+    user_agent: String,
+    max_response_bytes: Option<u64>,
 }
 
 impl ReqwestClient {
-    pub fn new(user_agent: &str) -> Result<Self> {
+    pub fn new(user_agent: &str, scheduler: Arc<PolitenessScheduler>) -> Result<Self> {
         let client = ClientBuilder::new()
             .timeout(Duration::from_millis(REQUEST_TIMEOUT_MS))
             .user_agent(user_agent)
+            // Redirects are followed in fetch() so each hop is scheduled and recorded
+            .redirect(Policy::none())
             .build()?;
 
         Ok(Self { 
             client,
-            rate_limiter: Arc::new(Semaphore::new(10)), // Max 10 concurrent requests
-            last_request_time: Arc::new(tokio::sync::Mutex::new(Instant::now())),
+            scheduler,
+            user_agent: user_agent.to_string(),
+            max_response_bytes: None,
         })
     }
+
+    /// Fail fetches whose body is larger than `limit` bytes, without reading the rest
+    pub fn with_max_response_bytes(mut self, limit: Option<u64>) -> Self {
+        self.max_response_bytes = limit;
+        self
+    }
 }
 
-impl HttpClient for ReqwestClient {
-    fn fetch<'a>(&'a self, url: &'a str) -> Pin<Box<dyn Future<Output = Result<(u16, String, Option<usize>, String)>> + Send + 'a>> {
-        Box::pin(async move {
-            // Acquire rate limit permit
-            let _permit = self.rate_limiter.acquire().await?;
-            
-            // Enforce minimum delay between requests (100ms)
-            {
-                let mut last_time = self.last_request_time.lock().await;
-                let now = Instant::now();
-                let time_since_last = now.duration_since(*last_time);
-                if time_since_last < Duration::from_millis(100) {
-                    let sleep_time = Duration::from_millis(100) - time_since_last;
-                    sleep(sleep_time).await;
-                }
-                *last_time = Instant::now();
-            }
-            
-            let mut retries = 3;
-            let mut last_err: Option<reqwest::Error> = None;
-
-            while retries > 0 {
-                match self.client.get(url).send().await {
-                    Ok(response) => {
-                        let status = response.status().as_u16();
-                        
-                        // Handle rate limiting responses
-                        if status == 429 || status == 503 {
-                            retries -= 1;
-                            if retries > 0 {
-                                let backoff = 1000 * (2_u64.pow((3 - retries) as u32)); // Longer backoff for rate limits
-                                log::debug!("Rate limited ({}), backing off {}ms: {}", status, backoff, url);
-                                sleep(Duration::from_millis(backoff)).await;
-                                continue;
-                            }
-                        }
-                        
-                        let content_type = response.headers()
-                            .get("content-type")
-                            .and_then(|v| v.to_str().ok())
-                            .unwrap_or("unknown")
-                            .to_string();
-                        let content_length = response.headers()
-                            .get("content-length")
-                            .and_then(|v| v.to_str().ok())
-                            .and_then(|s| s.parse().ok());
+impl ReqwestClient {
+    /// Send a GET request with per-host scheduling and retry logic.
+    /// The returned permit keeps the host slot busy until the body has been read.
+    async fn send(&self, url: &str, headers: &HeaderMap) -> Result<(Response, OwnedSemaphorePermit)> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
 
-                        // Get the body text - this consumes the response
-                        return match response.text().await {
-                            Ok(body) => Ok((status, content_type, content_length, body)),
-                            Err(e) => Err(e.into()),
-                        }
-                    },
-                    Err(e) => {
-                        // Handle specific error types
-                        if e.is_timeout() {
-                            log::debug!("Timeout error for {}: {}", url, e);
-                        } else if e.is_connect() {
-                            log::debug!("Connection error for {}: {}", url, e);
-                        } else {
-                            log::debug!("Request error for {}: {}", url, e);
-                        }
-                        
-                        last_err = Some(e);
+        let mut retries = 3;
+        let mut last_err: Option<reqwest::Error> = None;
+
+        while retries > 0 {
+            // Wait for this host's concurrency slot and delay
+            let permit = self.scheduler.acquire(&host).await?;
+
+            match self.client.get(url).headers(headers.clone()).send().await {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    
+                    // Handle rate limiting responses
+                    if status == 429 || status == 503 {
+                        let retry_after = response.headers()
+                            .get("retry-after")
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(parse_retry_after);
+                        self.scheduler.record_throttled(&host, retry_after);
                         retries -= 1;
-                        
                         if retries > 0 {
-                            let backoff = 100 * (2_u64.pow((3 - retries) as u32));
-                            sleep(Duration::from_millis(backoff)).await;
+                            // The scheduler delays the next attempt for this host
+                            log::debug!("Rate limited ({}), retry-after {:?}: {}", status, retry_after, url);
+                            continue;
                         }
+                    } else {
+                        self.scheduler.record_success(&host);
+                    }
+
+                    return Ok((response, permit));
+                },
+                Err(e) => {
+                    drop(permit);
+
+                    // Handle specific error types
+                    if e.is_timeout() {
+                        log::debug!("Timeout error for {}: {}", url, e);
+                    } else if e.is_connect() {
+                        log::debug!("Connection error for {}: {}", url, e);
+                    } else {
+                        log::debug!("Request error for {}: {}", url, e);
+                    }
+                    
+                    last_err = Some(e);
+                    retries -= 1;
+                    
+                    if retries > 0 {
+                        let backoff = 100 * (2_u64.pow((3 - retries) as u32));
+                        sleep(Duration::from_millis(backoff)).await;
                     }
                 }
             }
+        }
 
-            Err(last_err.unwrap().into())
-        })
+        Err(last_err.unwrap().into())
+    }
+
+    /// Headers sent for a request: the client defaults plus the extra headers
+    fn request_headers(&self, url: &str, extra: &HeaderMap) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(host) = Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string))
+            && let Ok(value) = HeaderValue::from_str(&host)
+        {
+            headers.insert(HOST, value);
+        }
+        if let Ok(value) = HeaderValue::from_str(&self.user_agent) {
+            headers.insert(USER_AGENT, value);
+        }
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+        headers.extend(extra.clone());
+        headers
+    }
+
+    /// Resolve the `Location` of a redirect response against the URL that returned it
+    fn redirect_target(url: &str, response: &Response) -> Option<String> {
+        if !response.status().is_redirection() || response.status() == StatusCode::NOT_MODIFIED {
+            return None;
+        }
+        let location = response.headers().get("location")?.to_str().ok()?;
+        Url::parse(url).ok()?.join(location.trim()).ok().map(String::from)
+    }
+
+    /// Read a response body, aborting once it exceeds `max_response_bytes`
+    async fn read_body(&self, url: &str, mut response: Response) -> Result<Vec<u8>> {
+        let Some(limit) = self.max_response_bytes else {
+            return Ok(response.bytes().await?.to_vec());
+        };
+        if let Some(declared) = response.content_length().filter(|&len| len > limit) {
+            return Err(AppError::TooLarge(format!("{} declares {} bytes, limit is {}", url, declared, limit)));
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (body.len() + chunk.len()) as u64 > limit {
+                return Err(AppError::TooLarge(format!("{} exceeded {} bytes", url, limit)));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// Fetch a URL sending extra request headers (e.g. conditional validators).
+    /// Redirects are followed manually so every hop is scheduled and recorded.
+    pub async fn fetch_with_headers(&self, url: &str, headers: &HeaderMap) -> Result<FetchResponse> {
+        let start = Instant::now();
+        let mut current = url.to_string();
+        let mut redirects: Vec<RedirectHop> = Vec::new();
+
+        loop {
+            let (response, _permit) = self.send(&current, headers).await?;
+
+            if let Some(next) = Self::redirect_target(&current, &response) {
+                if redirects.len() >= MAX_REDIRECTS {
+                    return Err(AppError::Redirect(format!("too many redirects starting at {}", url)));
+                }
+                if next == url || redirects.iter().any(|hop| hop.url == next) {
+                    return Err(AppError::Redirect(format!("redirect loop at {}", next)));
+                }
+                log::debug!("Redirect {} -> {} ({})", current, next, response.status());
+                redirects.push(RedirectHop {
+                    url: current,
+                    status: response.status().as_u16(),
+                    headers: response.headers().clone(),
+                });
+                current = next;
+                continue;
+            }
+
+            let status = response.status().as_u16();
+            let version = response.version();
+            let response_headers = response.headers().clone();
+            let remote_addr = response.remote_addr();
+
+            // Read the raw body - this consumes the response
+            let body = self.read_body(&current, response).await?;
+            return Ok(FetchResponse {
+                status,
+                version,
+                headers: response_headers,
+                request_headers: self.request_headers(&current, headers),
+                final_url: current,
+                redirects,
+                elapsed: start.elapsed(),
+                remote_addr,
+                body,
+                source: ResponseSource::Network,
+            });
+        }
     }
 }
 
+impl HttpClient for ReqwestClient {
+    fn fetch<'a>(&'a self, url: &'a str) -> Pin<Box<dyn Future<Output = Result<FetchResponse>> + Send + 'a>> {
+        Box::pin(async move { self.fetch_with_headers(url, &HeaderMap::new()).await })
+    }
+}
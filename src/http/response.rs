@@ -0,0 +1,116 @@
+//! Structured result of fetching a URL
+
+use reqwest::header::HeaderMap;
+use reqwest::Version;
+use std::net::SocketAddr;
+use std::time::Duration;
+use url::Url;
+
+/// One redirect response followed while fetching a URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    /// URL that answered with the redirect
+    pub url: String,
+    /// Redirect status code (301, 302, 303, 307 or 308)
+    pub status: u16,
+    /// Headers of the redirect response
+    pub headers: HeaderMap,
+}
+
+/// Where the body of a `FetchResponse` came from
+#[derive(Debug, Clone, Default)]
+pub enum ResponseSource {
+    /// Sent by the server for this request
+    #[default]
+    Network,
+    /// Served from the cache after the server answered `304 Not Modified`
+    /// with these headers
+    Revalidated(HeaderMap),
+    /// Served from the cache without contacting the server
+    Replayed,
+}
+
+/// A fetched resource after following redirects
+#[derive(Debug, Clone)]
+pub struct FetchResponse {
+    /// Status code of the final response
+    pub status: u16,
+    /// HTTP version of the final response
+    pub version: Version,
+    /// Headers of the final response
+    pub headers: HeaderMap,
+    /// Headers sent with the final request
+    pub request_headers: HeaderMap,
+    /// URL after following all redirects
+    pub final_url: String,
+    /// Redirects followed, in order, starting with the requested URL
+    pub redirects: Vec<RedirectHop>,
+    /// Time from the first request until the final body was read
+    pub elapsed: Duration,
+    /// Address of the server that sent the final response, if known
+    pub remote_addr: Option<SocketAddr>,
+    /// Raw body of the final response
+    pub body: Vec<u8>,
+    /// Whether the body was fetched or served from the cache
+    pub source: ResponseSource,
+}
+
+impl FetchResponse {
+    /// Get a header value as a string, if present and valid
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// The `Content-Type` header, or `"unknown"` if missing
+    pub fn content_type(&self) -> String {
+        self.header("content-type").unwrap_or("unknown").to_string()
+    }
+
+    /// The `Content-Length` header, if present and numeric
+    pub fn content_length(&self) -> Option<usize> {
+        self.header("content-length").and_then(|s| s.trim().parse().ok())
+    }
+
+    /// Absolute target of a `Link: <url>; rel="canonical"` header, if any
+    pub fn canonical_link(&self) -> Option<String> {
+        let target = self
+            .headers
+            .get_all("link")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(canonical_link_target)?;
+        Url::parse(&self.final_url).and_then(|base| base.join(target)).ok().map(String::from)
+    }
+
+    /// Check if the status code is in the 2xx range
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// URL of one `<url>; rel=...` entry of a `Link` header if its relation is canonical
+fn canonical_link_target(entry: &str) -> Option<&str> {
+    let (target, params) = entry.trim().strip_prefix('<')?.split_once('>')?;
+    params
+        .split(';')
+        .filter_map(|param| param.trim().split_once('='))
+        .any(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("rel")
+                && value.trim().trim_matches('"').split_whitespace().any(|r| r.eq_ignore_ascii_case("canonical"))
+        })
+        .then_some(target.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_canonical_among_link_header_entries() {
+        let header = r#"<https://cdn.example.com/style.css>; rel=preload, </articles/42>; rel="canonical""#;
+        let target = header.split(',').find_map(canonical_link_target);
+        assert_eq!(target, Some("/articles/42"));
+        assert_eq!(canonical_link_target("<https://example.com/>; rel=next"), None);
+    }
+}
@@ -0,0 +1,317 @@
+//! Append-only crawl state journal for resuming interrupted crawls
+//!
+//! Every frontier addition, fetch and filtering decision is appended as one
+//! JSON line. Writes are buffered and flushed at least every
+//! `FLUSH_INTERVAL`, so a crash loses only the last few seconds of progress.
+//! Replaying the journal yields the remaining frontier and the visited set.
+
+use crate::error::{AppError, Result};
+use crate::models::QueuedUrl;
+use dashmap::DashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default journal file name inside the output directory
+pub const STATE_FILE_NAME: &str = "crawl_state.jsonl";
+/// Maximum time buffered records may stay in memory
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// One line of the state journal
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StateRecord {
+    Queued {
+        url: String,
+        depth: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lastmod: Option<String>,
+    },
+    Visited {
+        url: String,
+        depth: usize,
+        /// `None` when the fetch failed
+        status: Option<u16>,
+    },
+    Filtered {
+        url: String,
+        reason: String,
+    },
+}
+
+/// Outcome of fetching a URL in a previous run
+#[derive(Debug, Clone)]
+pub struct VisitRecord {
+    pub depth: usize,
+    pub status: Option<u16>,
+}
+
+/// Crawl progress reconstructed from a journal
+#[derive(Debug, Default)]
+pub struct CrawlState {
+    /// URLs queued but not yet fetched, including previously failed fetches
+    pub frontier: Vec<QueuedUrl>,
+    /// URLs fetched successfully
+    pub visited: HashMap<String, VisitRecord>,
+    /// URLs discovered but rejected, with the reason
+    pub filtered: HashMap<String, String>,
+}
+
+impl CrawlState {
+    /// Replay a journal file into a state snapshot
+    fn replay(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut queued: Vec<QueuedUrl> = Vec::new();
+        let mut queued_seen: HashSet<String> = HashSet::new();
+        let mut visited: HashMap<String, VisitRecord> = HashMap::new();
+        let mut filtered = HashMap::new();
+
+        for (line_no, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A crash can leave a truncated final line behind
+            let record: StateRecord = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                Err(e) => {
+                    log::warn!("Ignoring unreadable state line {}: {}", line_no + 1, e);
+                    continue;
+                }
+            };
+
+            match record {
+                StateRecord::Queued { url, depth, lastmod } => {
+                    if queued_seen.insert(url.clone()) {
+                        queued.push(QueuedUrl { url, depth, lastmod });
+                    }
+                }
+                StateRecord::Visited { url, depth, status } => {
+                    visited.insert(url, VisitRecord { depth, status });
+                }
+                StateRecord::Filtered { url, reason } => {
+                    filtered.insert(url, reason);
+                }
+            }
+        }
+
+        // Failed fetches go back into the frontier so the resumed crawl retries them
+        visited.retain(|_, record| record.status.is_some());
+        let frontier = queued
+            .into_iter()
+            .filter(|item| !visited.contains_key(&item.url))
+            .collect();
+
+        Ok(Self { frontier, visited, filtered })
+    }
+}
+
+/// Buffered writer state for the journal
+struct JournalWriter {
+    writer: BufWriter<File>,
+    last_flush: Instant,
+}
+
+/// Persists crawl progress to an append-only journal
+pub struct CrawlStateStore {
+    path: PathBuf,
+    writer: Mutex<JournalWriter>,
+    queued_seen: DashSet<String>,
+    filtered_seen: DashSet<String>,
+}
+
+impl CrawlStateStore {
+    /// Open a journal. With `resume`, the existing journal is replayed, compacted
+    /// and returned as a snapshot; otherwise any previous journal is discarded.
+    pub fn open<P: AsRef<Path>>(path: P, resume: bool) -> Result<(Self, Option<CrawlState>)> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        let state = if resume && path.exists() {
+            let state = CrawlState::replay(&path)?;
+            Self::write_compacted(&path, &state)?;
+            Some(state)
+        } else {
+            if resume {
+                log::warn!("No crawl state found at {}, starting a new crawl", path.display());
+            }
+            File::create(&path)?;
+            None
+        };
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+        let store = Self {
+            path,
+            writer: Mutex::new(JournalWriter {
+                writer: BufWriter::new(file),
+                last_flush: Instant::now(),
+            }),
+            queued_seen: DashSet::new(),
+            filtered_seen: DashSet::new(),
+        };
+
+        if let Some(state) = &state {
+            for item in &state.frontier {
+                store.queued_seen.insert(item.url.clone());
+            }
+            for url in state.visited.keys() {
+                store.queued_seen.insert(url.clone());
+            }
+            for url in state.filtered.keys() {
+                store.filtered_seen.insert(url.clone());
+            }
+        }
+
+        Ok((store, state))
+    }
+
+    /// Path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a URL entering the frontier (only the first time it is seen)
+    pub fn record_queued(&self, item: &QueuedUrl) {
+        if self.queued_seen.insert(item.url.clone()) {
+            self.append(&StateRecord::Queued {
+                url: item.url.clone(),
+                depth: item.depth,
+                lastmod: item.lastmod.clone(),
+            });
+        }
+    }
+
+    /// Record the outcome of a fetch; `status` is `None` if the fetch failed
+    pub fn record_visited(&self, url: &str, depth: usize, status: Option<u16>) {
+        self.append(&StateRecord::Visited {
+            url: url.to_string(),
+            depth,
+            status,
+        });
+    }
+
+    /// Record a discovered URL that was rejected (only the first time it is seen)
+    pub fn record_filtered(&self, url: &str, reason: &str) {
+        if self.filtered_seen.insert(url.to_string()) {
+            self.append(&StateRecord::Filtered {
+                url: url.to_string(),
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    /// Write buffered records to disk
+    pub fn flush(&self) {
+        let mut journal = self.lock_writer();
+        if let Err(e) = journal.writer.flush() {
+            log::warn!("Failed to flush crawl state {}: {}", self.path.display(), e);
+        }
+        journal.last_flush = Instant::now();
+    }
+
+    fn append(&self, record: &StateRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => {
+                log::warn!("Failed to serialize crawl state record: {}", e);
+                return;
+            }
+        };
+
+        let mut journal = self.lock_writer();
+        if let Err(e) = writeln!(journal.writer, "{}", line) {
+            log::warn!("Failed to write crawl state {}: {}", self.path.display(), e);
+            return;
+        }
+        if journal.last_flush.elapsed() >= FLUSH_INTERVAL {
+            if let Err(e) = journal.writer.flush() {
+                log::warn!("Failed to flush crawl state {}: {}", self.path.display(), e);
+            }
+            journal.last_flush = Instant::now();
+        }
+    }
+
+    fn lock_writer(&self) -> std::sync::MutexGuard<'_, JournalWriter> {
+        self.writer.lock().unwrap_or_else(|e| {
+            log::warn!("Mutex poisoned in crawl state writer, recovering");
+            e.into_inner()
+        })
+    }
+
+    /// Rewrite the journal with one record per URL so it does not grow across resumes
+    fn write_compacted(path: &Path, state: &CrawlState) -> Result<()> {
+        let tmp_path = path.with_extension("jsonl.tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            let mut write = |record: StateRecord| -> Result<()> {
+                let line = serde_json::to_string(&record)
+                    .map_err(|e| AppError::Crawler(format!("Failed to serialize crawl state: {}", e)))?;
+                writeln!(writer, "{}", line)?;
+                Ok(())
+            };
+
+            for (url, record) in &state.visited {
+                write(StateRecord::Queued { url: url.clone(), depth: record.depth, lastmod: None })?;
+                write(StateRecord::Visited { url: url.clone(), depth: record.depth, status: record.status })?;
+            }
+            for item in &state.frontier {
+                write(StateRecord::Queued {
+                    url: item.url.clone(),
+                    depth: item.depth,
+                    lastmod: item.lastmod.clone(),
+                })?;
+            }
+            for (url, reason) in &state.filtered {
+                write(StateRecord::Filtered { url: url.clone(), reason: reason.clone() })?;
+            }
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+impl Drop for CrawlStateStore {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_skips_visited_and_retries_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crawl_state.jsonl");
+        {
+            let (store, state) = CrawlStateStore::open(&path, false).unwrap();
+            assert!(state.is_none());
+            for url in ["https://a.test/", "https://a.test/x", "https://a.test/y", "https://a.test/z"] {
+                store.record_queued(&QueuedUrl::new(url.to_string(), 1));
+            }
+            store.record_visited("https://a.test/", 0, Some(200));
+            store.record_visited("https://a.test/x", 1, None);
+            store.record_filtered("https://other.test/", "out of scope");
+        }
+        // Simulate a crash in the middle of writing a line
+        fs::OpenOptions::new().append(true).open(&path).unwrap()
+            .write_all(b"{\"type\":\"visited\",\"url\":\"https://a.te").unwrap();
+
+        let (_store, state) = CrawlStateStore::open(&path, true).unwrap();
+        let state = state.unwrap();
+        let frontier: Vec<&str> = state.frontier.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(frontier, ["https://a.test/x", "https://a.test/y", "https://a.test/z"]);
+        assert!(state.visited.contains_key("https://a.test/"));
+        assert_eq!(state.filtered.get("https://other.test/").map(String::as_str), Some("out of scope"));
+    }
+}
@@ -0,0 +1,27 @@
+//! SHA-1 digests in the `sha1:BASE32` form used by WARC payload and block digests
+
+use data_encoding::BASE32;
+use sha1::{Digest, Sha1};
+
+/// Compute a SHA-1 digest and format it as `sha1:<base32>`
+pub fn sha1_label(data: &[u8]) -> String {
+    format!("sha1:{}", BASE32.encode(&sha1(data)))
+}
+
+/// SHA-1 of `data`
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    Sha1::digest(data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha1_known_vectors() {
+        assert_eq!(sha1_label(b"abc"), "sha1:VGMT4NSHA2AWVOR6EVYXQUGCNSONBWE5");
+        assert_eq!(sha1_label(b""), "sha1:3I42H3S6NNFQ2MSVX7XZKYAYSCX5QBYJ");
+        let long = b"The quick brown fox jumps over the lazy dog".repeat(3);
+        assert_eq!(sha1_label(&long), "sha1:2QXYLCWYCL6ZQ36Y3RZBNL27RC6KUFDD");
+    }
+}
@@ -0,0 +1,82 @@
+//! Bookkeeping for incremental mirror updates
+//!
+//! `MirrorIndex` records which file each URL was saved to, so the next run can
+//! tell which URLs disappeared. `ChangeManifest` lists what a run changed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Index file name inside the output directory
+pub const INDEX_FILE_NAME: &str = ".mirror_index.json";
+/// Change manifest file name inside the output directory
+pub const MANIFEST_FILE_NAME: &str = "change_manifest.json";
+/// Directory that receives files of removed URLs in quarantine mode
+pub const QUARANTINE_DIR_NAME: &str = ".quarantine";
+
+/// URL to saved file mapping of a mirror, with paths relative to the output directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MirrorIndex {
+    pub files: BTreeMap<String, PathBuf>,
+}
+
+impl MirrorIndex {
+    /// Load the index of a previous run, or an empty index if there is none
+    pub fn load(output_dir: &Path) -> Self {
+        let path = output_dir.join(INDEX_FILE_NAME);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable mirror index {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// Write the index into the output directory
+    pub fn save(&self, output_dir: &Path) -> Result<(), String> {
+        write_json(&output_dir.join(INDEX_FILE_NAME), self)
+    }
+}
+
+/// URLs added, modified, removed and left unchanged by one mirror run
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChangeManifest {
+    pub added: BTreeSet<String>,
+    pub modified: BTreeSet<String>,
+    pub removed: BTreeSet<String>,
+    pub unchanged: BTreeSet<String>,
+}
+
+impl ChangeManifest {
+    /// Write the manifest into the output directory
+    pub fn save(&self, output_dir: &Path) -> Result<(), String> {
+        write_json(&output_dir.join(MANIFEST_FILE_NAME), self)
+    }
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        assert!(MirrorIndex::load(dir).files.is_empty());
+
+        let mut index = MirrorIndex::default();
+        index.files.insert("https://a.test/".to_string(), PathBuf::from("a.test/index.html"));
+        index.save(dir).unwrap();
+        let loaded = MirrorIndex::load(dir);
+        assert_eq!(loaded.files.get("https://a.test/"), Some(&PathBuf::from("a.test/index.html")));
+    }
+}
@@ -1,5 +1,11 @@
 //! File operations and persistence layer
 
 pub mod site_saver;
+pub mod crawl_state;
+pub mod mirror_manifest;
+pub mod digest;
+pub mod warc;
 
-pub use site_saver::SiteSaver;
\ No newline at end of file
+pub use site_saver::SiteSaver;
+pub use crawl_state::{CrawlState, CrawlStateStore};
+pub use warc::WarcWriter;
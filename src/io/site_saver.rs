@@ -1,15 +1,33 @@
+use crate::config::PruneMode;
 use crate::crawler::StandardUrlParser;
 use crate::extraction::ResourceValidator;
+use crate::http::BodyDecoder;
+use crate::io::digest::sha1;
+use crate::io::mirror_manifest::{ChangeManifest, MirrorIndex, QUARANTINE_DIR_NAME};
 use crate::models::PageInfo;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use url::Url;
-use log::{info, warn, error};
+use log::{info, warn};
 
+use crate::crawler::UrlScope;
 use crate::processing::LinkRewriter;
 
+/// HTML page or stylesheet saved while some of its link targets were not crawled yet
+struct DeferredPage {
+    /// Normalized page URL
+    url: String,
+    content_type: String,
+    local_path: PathBuf,
+    /// Normalized link URLs with the local path their links were rewritten to
+    links: Vec<(String, PathBuf)>,
+    /// Digest of the file this run replaced (incremental mode only)
+    previous_digest: Option<[u8; 20]>,
+}
+
 /// Responsible for saving a crawled website to disk
 pub struct SiteSaver {
     /// Base directory where the website will be saved
@@ -18,6 +36,22 @@ pub struct SiteSaver {
     url_to_path: HashMap<String, PathBuf>,
     /// Link rewriter for HTML content
     rewriter: LinkRewriter,
+    /// Only write changed files and prune files of removed URLs
+    incremental: bool,
+    /// What happens to files of removed URLs in incremental mode
+    prune_mode: PruneMode,
+    /// Files saved by the previous run
+    previous_index: MirrorIndex,
+    /// Files saved by this run
+    current_index: MirrorIndex,
+    /// Changes made by this run (incremental mode only)
+    changes: ChangeManifest,
+    /// URLs saved or registered as redirects, whose local path is final
+    confirmed: HashSet<String>,
+    /// URLs that returned 404 or 410, whose previous files are pruned
+    gone: HashSet<String>,
+    /// Pages whose links may need another rewrite once all targets are known
+    deferred: Vec<DeferredPage>,
 }
 
 impl SiteSaver {
@@ -27,11 +61,67 @@ impl SiteSaver {
             output_dir: output_dir.as_ref().to_path_buf(),
             url_to_path: HashMap::new(),
             rewriter: LinkRewriter::new(),
+            incremental: false,
+            prune_mode: PruneMode::default(),
+            previous_index: MirrorIndex::default(),
+            current_index: MirrorIndex::default(),
+            changes: ChangeManifest::default(),
+            confirmed: HashSet::new(),
+            gone: HashSet::new(),
+            deferred: Vec::new(),
         }
     }
 
+    /// Rewrite links to the other hosts the crawl may fetch from, such as
+    /// asset hosts, as well as links within the site
+    pub fn with_scope(mut self, scope: Arc<UrlScope>) -> Self {
+        self.rewriter = self.rewriter.with_scope(scope);
+        self
+    }
+
+    /// Update an existing mirror in place: unchanged files are left alone and
+    /// files of URLs that now 404 or disappeared are pruned
+    pub fn with_incremental(mut self, prune_mode: PruneMode) -> Self {
+        self.incremental = true;
+        self.prune_mode = prune_mode;
+        self.previous_index = MirrorIndex::load(&self.output_dir);
+        self
+    }
+
+    /// Keep the files of URLs an interrupted crawl already saved. A resumed
+    /// crawl does not fetch `visited` again, so without this `finish` would
+    /// prune their files and drop them from the index.
+    pub fn with_resumed<I>(mut self, visited: I, base_url: &str) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let saved = MirrorIndex::load(&self.output_dir);
+        let domain_dir = self.prepare_domain_dir(base_url).ok();
+        for url in visited {
+            let url = StandardUrlParser.normalize_url(&url);
+            let relative_path = match saved.files.get(&url) {
+                Some(path) => path.clone(),
+                // The interrupted run may have ended before writing its index
+                None => {
+                    let Some((base, dir)) = &domain_dir else {
+                        continue;
+                    };
+                    let Some(path) = self.map_source_url_to_target_path(&url, base, dir).filter(|p| p.is_file()) else {
+                        continue;
+                    };
+                    path.strip_prefix(&self.output_dir).unwrap_or(&path).to_path_buf()
+                }
+            };
+            self.url_to_path.insert(url.clone(), self.output_dir.join(&relative_path));
+            self.current_index.files.insert(url.clone(), relative_path);
+            self.changes.unchanged.insert(url.clone());
+            self.confirmed.insert(url);
+        }
+        self
+    }
+
     /// Helper to map any source URL (crawled or linked) to the target local path and insert into url_to_path
-    fn map_source_url_to_target_path(&mut self, source_url: &str, _target_base_url: &Url, domain_dir: &Path) -> Option<PathBuf> {
+    fn map_source_url_to_target_path(&mut self, source_url: &str, target_base_url: &Url, domain_dir: &Path) -> Option<PathBuf> {
         let normalizer = StandardUrlParser;
         let normalized_url = normalizer.normalize_url(source_url);
 
@@ -56,6 +146,14 @@ impl SiteSaver {
             return None;
         }
 
+        // Files of other hosts go beside the site's directory, in one named after their host
+        let domain_dir = match url.host_str() {
+            Some(host) if url.host_str() != target_base_url.host_str() => {
+                domain_dir.parent().unwrap_or(domain_dir).join(host)
+            }
+            _ => domain_dir.to_path_buf(),
+        };
+
         // Create local path based on the target domain structure, using the source URL's path
         let local_path = if path == "/" {
             domain_dir.join("index.html")
@@ -71,13 +169,6 @@ impl SiteSaver {
             domain_dir.join(path)
         };
 
-        if let Some(parent) = local_path.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                error!("Failed to create directory {}: {}", parent.display(), e);
-                return None;
-            }
-        }
-
         info!("Mapped (source): {} -> {}", normalized_url, local_path.display());
         // Insert the path into the map and return a new PathBuf with the same path
         let result_path = PathBuf::from(&local_path);
@@ -85,8 +176,8 @@ impl SiteSaver {
         Some(result_path)
     }
 
-    /// Incrementally save a single page as it is received (uses existing content from PageInfo)
-    pub fn save_page_from_content(&mut self, page: &PageInfo, base_url: &str) -> Result<(), String> {
+    /// Parse the base URL and create the output directory for its domain
+    fn prepare_domain_dir(&self, base_url: &str) -> Result<(Url, PathBuf), String> {
         let base_url_parsed = Url::parse(base_url)
             .map_err(|e| format!("Invalid base URL: {}", e))?;
         let domain = base_url_parsed.host_str()
@@ -95,6 +186,43 @@ impl SiteSaver {
         let domain_dir = self.output_dir.join(&domain);
         fs::create_dir_all(&domain_dir)
             .map_err(|e| format!("Failed to create domain directory: {}", e))?;
+        Ok((base_url_parsed, domain_dir))
+    }
+
+    /// Map a redirecting URL, or an alias of a canonical URL, to the local
+    /// file of its target, so links to either URL are rewritten to the same
+    /// file. Pages saved earlier that link to it are fixed up by `finish`.
+    pub fn register_alias(&mut self, page: &PageInfo, base_url: &str) -> Result<(), String> {
+        let Some(target) = page.redirect_to.as_ref().or(page.canonical.as_ref()) else {
+            return Ok(());
+        };
+        let (base_url_parsed, domain_dir) = self.prepare_domain_dir(base_url)?;
+
+        let Some(target_path) = self.map_source_url_to_target_path(target, &base_url_parsed, &domain_dir) else {
+            warn!("Could not determine target local path for alias {} -> {}", page.url, target);
+            return Ok(());
+        };
+
+        let normalized_url = StandardUrlParser.normalize_url(&page.url);
+        let kind = if page.redirect_to.is_some() { "redirect" } else { "canonical" };
+        info!("Mapped ({}): {} -> {}", kind, normalized_url, target_path.display());
+        self.url_to_path.insert(normalized_url.clone(), target_path);
+        self.confirmed.insert(normalized_url);
+        Ok(())
+    }
+
+    /// Save a single page as it is received (uses existing content from PageInfo).
+    /// Links to URLs not saved yet are rewritten to the path the URL would be
+    /// saved at and revisited by `finish` in case the URL turns out to redirect.
+    pub fn save_page_from_content(&mut self, page: &PageInfo, base_url: &str) -> Result<(), String> {
+        if page.redirect_to.is_some() || page.canonical.is_some() {
+            return self.register_alias(page, base_url);
+        }
+        if self.incremental && page.status_code >= 400 {
+            self.keep_previous_file(page);
+            return Ok(());
+        }
+        let (base_url_parsed, domain_dir) = self.prepare_domain_dir(base_url)?;
 
         // Map the actual crawled URL (page.url) to its local path under the target domain
         let local_path = match self.map_source_url_to_target_path(&page.url, &base_url_parsed, &domain_dir) {
@@ -105,12 +233,22 @@ impl SiteSaver {
             }
         };
 
-        // For HTML pages, pre-map all linked URLs (resolved relative to the page URL) to their local paths
-        if page.content_type.contains("text/html") {
+        let normalized_url = StandardUrlParser.normalize_url(&page.url);
+        self.confirmed.insert(normalized_url.clone());
+
+        // For HTML pages and stylesheets, pre-map all linked URLs (resolved relative to the page URL) to their local paths
+        let mut pending_links = Vec::new();
+        if page.content_type.contains("text/html") || page.content_type.contains("text/css") {
             info!("Pre-mapping {} links for {}", page.links.len(), page.url);
             for link_str in &page.links {
                 if let Ok(resolved_link_url) = Url::parse(&page.url).and_then(|u| u.join(link_str)) {
-                    self.map_source_url_to_target_path(&resolved_link_url.to_string(), &base_url_parsed, &domain_dir);
+                    let mapped = self.map_source_url_to_target_path(resolved_link_url.as_ref(), &base_url_parsed, &domain_dir);
+                    let link_url = StandardUrlParser.normalize_url(resolved_link_url.as_ref());
+                    if let Some(path) = mapped
+                        && !self.confirmed.contains(&link_url)
+                    {
+                        pending_links.push((link_url, path));
+                    }
                 } else {
                     warn!("Could not resolve link: {} from base {}", link_str, page.url);
                 }
@@ -124,7 +262,19 @@ impl SiteSaver {
         }
 
         // Save the page content with link rewriting (no additional HTTP request needed)
-        self.save_page_content_from_memory(page, &local_path)?;
+        let previous_digest = self.save_page_content_from_memory(page, &local_path)?;
+
+        if !pending_links.is_empty() {
+            pending_links.sort();
+            pending_links.dedup();
+            self.deferred.push(DeferredPage {
+                url: normalized_url,
+                content_type: page.content_type.clone(),
+                local_path,
+                links: pending_links,
+                previous_digest,
+            });
+        }
 
         Ok(())
     }
@@ -132,29 +282,336 @@ impl SiteSaver {
     // Removed: Complex validation function replaced with ResourceValidator::is_valid_resource_url
 
 
-    /// Save the content of a page to disk using content from PageInfo (no additional HTTP request)
-    fn save_page_content_from_memory(&self, page: &PageInfo, local_path: &Path) -> Result<(), String> {
-        info!("Saving to: {}", local_path.display());
-
+    /// Save the content of a page to disk using content from PageInfo (no additional HTTP request).
+    /// Returns the digest of the previous file when an incremental run replaced it.
+    fn save_page_content_from_memory(&mut self, page: &PageInfo, local_path: &Path) -> Result<Option<[u8; 20]>, String> {
         // Handle HTML pages with link rewriting
-        if page.content_type.contains("text/html") {
+        let content = if page.content_type.contains("text/html") && let Some(text) = &page.text {
             info!("Rewriting links for: {}", page.url);
-            let rewritten_content = self.rewriter.rewrite_links(&page.url, &page.content, &self.url_to_path);
+            let rewritten_content = self.rewriter.rewrite_links(&page.url, text, &self.url_to_path);
+            // Keep the page in the charset it was served in so its declarations stay valid
+            BodyDecoder::encode(&page.content_type, &page.content, &rewritten_content)
+        } else if page.content_type.contains("text/css") && let Some(text) = &page.text {
+            info!("Rewriting stylesheet links for: {}", page.url);
+            let rewritten_content = self.rewriter.rewrite_css_links(&page.url, text, &self.url_to_path);
+            BodyDecoder::encode(&page.content_type, &page.content, &rewritten_content)
+        } else {
+            // For non-HTML content, save the raw bytes directly
+            page.content.clone()
+        };
 
-            let mut file = File::create(local_path)
-                .map_err(|e| format!("Failed to create file {}: {}", local_path.display(), e))?;
+        let url = StandardUrlParser.normalize_url(&page.url);
+        let relative_path = local_path.strip_prefix(&self.output_dir).unwrap_or(local_path).to_path_buf();
+        self.current_index.files.insert(url.clone(), relative_path);
 
-            file.write_all(rewritten_content.as_bytes())
-                .map_err(|e| format!("Failed to write to file {}: {}", local_path.display(), e))?;
-        } else {
-            // For non-HTML content, save directly
-            let mut file = File::create(local_path)
-                .map_err(|e| format!("Failed to create file {}: {}", local_path.display(), e))?;
+        let mut previous_digest = None;
+        if self.incremental {
+            match fs::read(local_path) {
+                Ok(existing) if existing == content => {
+                    info!("Unchanged: {}", local_path.display());
+                    self.changes.unchanged.insert(url);
+                    return Ok(None);
+                }
+                Ok(existing) => {
+                    previous_digest = Some(sha1(&existing));
+                    self.changes.modified.insert(url)
+                }
+                Err(_) => self.changes.added.insert(url),
+            };
+        }
 
-            file.write_all(page.content.as_bytes())
-                .map_err(|e| format!("Failed to write to file {}: {}", local_path.display(), e))?;
+        info!("Saving to: {}", local_path.display());
+        // Directories are only created here, so links that are never saved leave none behind
+        if let Some(parent) = local_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        let mut file = File::create(local_path)
+            .map_err(|e| format!("Failed to create file {}: {}", local_path.display(), e))?;
+
+        file.write_all(&content)
+            .map_err(|e| format!("Failed to write to file {}: {}", local_path.display(), e))?;
+
+        Ok(previous_digest)
+    }
+
+    /// Second rewrite pass over pages saved before all their link targets were
+    /// known: links to URLs that turned out to redirect are pointed at the file
+    /// of the redirect target instead of the redirecting URL's own path
+    fn rewrite_deferred_links(&mut self) {
+        for page in std::mem::take(&mut self.deferred) {
+            let moved: Vec<(PathBuf, PathBuf)> = page
+                .links
+                .into_iter()
+                .filter_map(|(url, written)| {
+                    let current = self.url_to_path.get(&url)?;
+                    (*current != written).then(|| (written, current.clone()))
+                })
+                .collect();
+            if moved.is_empty() {
+                continue;
+            }
+
+            let original = match fs::read(&page.local_path) {
+                Ok(original) => original,
+                Err(e) => {
+                    warn!("Failed to reread {} for link rewriting: {}", page.local_path.display(), e);
+                    continue;
+                }
+            };
+            let Some(text) = BodyDecoder::decode(&page.content_type, &original) else {
+                continue;
+            };
+            let retargeted = if page.content_type.contains("text/css") {
+                self.rewriter.retarget_css_links(&text, &page.local_path, &moved)
+            } else {
+                self.rewriter.retarget_links(&text, &page.local_path, &moved)
+            };
+            let content = BodyDecoder::encode(&page.content_type, &original, &retargeted);
+            if content == original {
+                continue;
+            }
+            if let Err(e) = fs::write(&page.local_path, &content) {
+                warn!("Failed to rewrite links in {}: {}", page.local_path.display(), e);
+                continue;
+            }
+            info!("Rewrote {} deferred link target(s) in {}", moved.len(), page.local_path.display());
+
+            // The first write may have differed from the previous run only by those links
+            if self.incremental {
+                if page.previous_digest == Some(sha1(&content)) {
+                    self.changes.modified.remove(&page.url);
+                    self.changes.unchanged.insert(page.url);
+                } else if self.changes.unchanged.remove(&page.url) {
+                    self.changes.modified.insert(page.url);
+                }
+            }
         }
-        
-        Ok(())
     }
-}
\ No newline at end of file
+
+    /// Keep the previously saved copy of a page that returned an error status.
+    /// 404 and 410 are left out of the index so `finish` prunes them.
+    fn keep_previous_file(&mut self, page: &PageInfo) {
+        let url = StandardUrlParser.normalize_url(&page.url);
+        if matches!(page.status_code, 404 | 410) {
+            self.gone.insert(url);
+            return;
+        }
+        if let Some(path) = self.previous_index.files.get(&url) {
+            warn!("Keeping previous copy of {} (status {})", page.url, page.status_code);
+            self.current_index.files.insert(url.clone(), path.clone());
+            self.changes.unchanged.insert(url);
+        }
+    }
+
+    /// Keep the previous files of URLs this run reached but did not save, such
+    /// as failed fetches and URLs skipped by robots.txt or a budget, so a flaky
+    /// run does not prune them. URLs that returned 404 or 410 are still pruned.
+    pub fn keep_unfinished<I>(&mut self, urls: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        for url in urls {
+            let url = StandardUrlParser.normalize_url(&url);
+            if self.current_index.files.contains_key(&url) || self.gone.contains(&url) {
+                continue;
+            }
+            if let Some(path) = self.previous_index.files.get(&url) {
+                warn!("Keeping previous copy of {}, which was not saved this run", url);
+                self.current_index.files.insert(url.clone(), path.clone());
+                self.changes.unchanged.insert(url);
+            }
+        }
+    }
+
+    /// Fix up links of pages saved before their targets were known, record the
+    /// saved files for the next run and, in incremental mode, prune files of
+    /// URLs that returned 404 or 410 or were no longer linked, and write the
+    /// change manifest
+    pub fn finish(&mut self) -> Result<Option<ChangeManifest>, String> {
+        self.rewrite_deferred_links();
+        if self.incremental {
+            let still_used: HashSet<&PathBuf> = self.current_index.files.values().collect();
+            for (url, path) in &self.previous_index.files {
+                if self.current_index.files.contains_key(url) {
+                    continue;
+                }
+                if !still_used.contains(path) {
+                    self.prune_file(path);
+                }
+                self.changes.removed.insert(url.clone());
+            }
+            self.changes.save(&self.output_dir)?;
+        }
+        self.current_index.save(&self.output_dir)?;
+
+        Ok(self.incremental.then(|| self.changes.clone()))
+    }
+
+    /// `finish` for an interrupted crawl: URLs it did not reach keep their
+    /// previous files and index entries instead of being pruned
+    pub fn finish_partial(&mut self) -> Result<Option<ChangeManifest>, String> {
+        for (url, path) in &self.previous_index.files {
+            self.current_index.files.entry(url.clone()).or_insert_with(|| path.clone());
+        }
+        self.finish()
+    }
+
+    /// Delete or quarantine a previously saved file
+    fn prune_file(&self, relative_path: &Path) {
+        let path = self.output_dir.join(relative_path);
+        if !path.exists() {
+            return;
+        }
+        let result = match self.prune_mode {
+            PruneMode::Delete => fs::remove_file(&path),
+            PruneMode::Quarantine => {
+                let target = self.output_dir.join(QUARANTINE_DIR_NAME).join(relative_path);
+                target
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::rename(&path, &target))
+            }
+        };
+        match result {
+            Ok(()) => info!("Pruned ({:?}): {}", self.prune_mode, path.display()),
+            Err(e) => warn!("Failed to prune {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScopeConfig;
+
+    fn page(url: &str, body: &str) -> PageInfo {
+        let mut page = PageInfo::redirect(url.to_string(), 200, String::new(), 0);
+        page.redirect_to = None;
+        page.content_type = "text/html".to_string();
+        page.content = body.as_bytes().to_vec();
+        page.text = Some(body.to_string());
+        page
+    }
+
+    #[test]
+    fn resumed_crawl_keeps_files_saved_before_the_interruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let base = "https://a.test/";
+
+        // The first run is interrupted after saving one page
+        let mut first = SiteSaver::new(dir).with_incremental(PruneMode::Delete);
+        first.save_page_from_content(&page("https://a.test/one", "<p>one</p>"), base).unwrap();
+        first.finish_partial().unwrap();
+
+        // The resumed run only fetches the rest and then completes
+        let visited = vec!["https://a.test/one".to_string()];
+        let mut resumed = SiteSaver::new(dir).with_incremental(PruneMode::Delete).with_resumed(visited, base);
+        resumed.save_page_from_content(&page("https://a.test/two", "<a href=\"/one\">1</a>"), base).unwrap();
+        let changes = resumed.finish().unwrap().unwrap();
+
+        assert!(dir.join("a.test/one/index.html").is_file());
+        assert!(changes.removed.is_empty());
+        let index = MirrorIndex::load(dir);
+        assert_eq!(index.files.len(), 2);
+        let two = fs::read_to_string(dir.join("a.test/two/index.html")).unwrap();
+        assert_eq!(two, "<a href=\"../one/index.html\">1</a>");
+
+        // Without --incremental the index must not lose the page either
+        let mut plain = SiteSaver::new(dir).with_resumed(vec!["https://a.test/one".to_string()], base);
+        plain.finish().unwrap();
+        assert!(MirrorIndex::load(dir).files.contains_key(&StandardUrlParser.normalize_url("https://a.test/one")));
+    }
+
+    #[test]
+    fn failed_fetches_keep_their_files_but_gone_pages_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let base = "https://a.test/";
+
+        let mut first = SiteSaver::new(dir).with_incremental(PruneMode::Delete);
+        for url in ["https://a.test/", "https://a.test/flaky", "https://a.test/gone"] {
+            first.save_page_from_content(&page(url, "<p>saved</p>"), base).unwrap();
+        }
+        first.finish().unwrap();
+
+        // The next run times out on one page and gets a 404 for another
+        let mut second = SiteSaver::new(dir).with_incremental(PruneMode::Delete);
+        second.save_page_from_content(&page(base, "<p>saved</p>"), base).unwrap();
+        let mut gone = page("https://a.test/gone", "");
+        gone.status_code = 404;
+        second.save_page_from_content(&gone, base).unwrap();
+        second.keep_unfinished(vec!["https://a.test/flaky".to_string(), "https://a.test/gone".to_string()]);
+        let changes = second.finish().unwrap().unwrap();
+
+        assert!(dir.join("a.test/flaky/index.html").is_file());
+        assert!(!dir.join("a.test/gone/index.html").exists());
+        let flaky = StandardUrlParser.normalize_url("https://a.test/flaky");
+        assert!(MirrorIndex::load(dir).files.contains_key(&flaky));
+        assert!(!changes.removed.contains(&flaky));
+        assert!(changes.removed.contains(&StandardUrlParser.normalize_url("https://a.test/gone")));
+    }
+
+    #[test]
+    fn stylesheet_links_follow_targets_that_redirect() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let base = "https://a.test/";
+        let mut saver = SiteSaver::new(dir);
+
+        let mut index = page(base, r#"<link rel="stylesheet" href="/css/site.css">"#);
+        index.links = vec!["https://a.test/css/site.css".to_string()];
+        saver.save_page_from_content(&index, base).unwrap();
+        let mut css = page("https://a.test/css/site.css", "body { background: url(/img/bg) }");
+        css.content_type = "text/css".to_string();
+        css.links = vec!["https://a.test/img/bg".to_string()];
+        saver.save_page_from_content(&css, base).unwrap();
+        // The image turns out to live elsewhere
+        let redirect = PageInfo::redirect("https://a.test/img/bg".to_string(), 301, "https://a.test/img/bg.png".to_string(), 2);
+        saver.save_page_from_content(&redirect, base).unwrap();
+        let mut image = page("https://a.test/img/bg.png", "png");
+        image.content_type = "image/png".to_string();
+        saver.save_page_from_content(&image, base).unwrap();
+        saver.finish().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join("a.test/index.html")).unwrap(),
+            r#"<link rel="stylesheet" href="css/site.css">"#
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("a.test/css/site.css")).unwrap(),
+            "body { background: url(../img/bg.png) }"
+        );
+    }
+
+    #[test]
+    fn off_site_assets_get_their_own_host_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let base = "https://a.test/";
+        let config = ScopeConfig { external_assets: true, ..ScopeConfig::default() };
+        let scope = Arc::new(UrlScope::new(&["a.test".to_string()], &config).unwrap());
+        let mut saver = SiteSaver::new(dir).with_scope(scope);
+
+        let mut logo = page("https://a.test/logo.png", "local");
+        logo.content_type = "image/png".to_string();
+        let mut cdn_logo = page("https://cdn.test/logo.png", "cdn");
+        cdn_logo.content_type = "image/png".to_string();
+        saver.save_page_from_content(&logo, base).unwrap();
+        saver.save_page_from_content(&cdn_logo, base).unwrap();
+        let html = r#"<img src="/logo.png"><img src="https://cdn.test/logo.png"><a href="https://b.test/">b</a>"#;
+        let mut home = page(base, html);
+        home.links = vec!["/logo.png".into(), "https://cdn.test/logo.png".into(), "https://b.test/".into()];
+        saver.save_page_from_content(&home, base).unwrap();
+        saver.finish().unwrap();
+
+        assert!(!dir.join("b.test").exists());
+        assert_eq!(fs::read_to_string(dir.join("a.test/logo.png")).unwrap(), "local");
+        assert_eq!(fs::read_to_string(dir.join("cdn.test/logo.png")).unwrap(), "cdn");
+        assert_eq!(
+            fs::read_to_string(dir.join("a.test/index.html")).unwrap(),
+            r#"<img src="logo.png"><img src="../cdn.test/logo.png"><a href="https://b.test/">b</a>"#
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//! WARC/1.1 output for archival crawls
+//!
+//! Every fetch is written as a `request`/`response` pair (redirect hops as
+//! bodiless `response` records). A payload already archived in this run, or
+//! one the server confirmed with `304 Not Modified`, is written as a `revisit`
+//! record instead. Responses replayed from the cache have no `request` record. Outlinks of each page are written as
+//! a `metadata` record when the page arrives. Records are optionally gzipped
+//! one member each, and a new file is started once `max_file_size_mb` is reached.
+
+use crate::config::{CrawlerConfig, WarcConfig};
+use crate::crawler::StandardUrlParser;
+use crate::error::Result;
+use crate::http::{FetchResponse, ResponseSource};
+use crate::io::digest::sha1_label;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use url::Url;
+use uuid::Uuid;
+
+const REVISIT_PROFILE: &str = "http://netpreserve.org/warc/1.1/revisit/identical-payload-digest";
+const NOT_MODIFIED_PROFILE: &str = "http://netpreserve.org/warc/1.1/revisit/server-not-modified";
+
+/// First capture of a payload, referenced by later `revisit` records
+struct PayloadCapture {
+    uri: String,
+    date: String,
+    record_id: String,
+}
+
+/// The WARC file currently being written
+struct WarcFile {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    index: usize,
+    size: u64,
+}
+
+/// Writes crawl records into rotating WARC files
+pub struct WarcWriter {
+    dir: PathBuf,
+    config: WarcConfig,
+    /// Prefix plus start timestamp shared by all files of this crawl
+    basename: String,
+    /// `warcinfo` block written at the start of every file
+    info_block: String,
+    file: Mutex<WarcFile>,
+    payloads: Mutex<HashMap<String, PayloadCapture>>,
+    response_ids: Mutex<HashMap<String, String>>,
+}
+
+impl WarcWriter {
+    /// Create the output directory and the first WARC file
+    pub fn open<P: AsRef<Path>>(dir: P, config: &CrawlerConfig) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let timestamp: String = warc_date(SystemTime::now()).chars().filter(char::is_ascii_digit).collect();
+        let basename = format!("{}-{}", config.warc.prefix, timestamp);
+        let info_block = format!(
+            "software: {}/{}\r\nformat: WARC File Format 1.1\r\n\
+             conformsTo: http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n\
+             http-header-user-agent: {}\r\nrobots: {}\r\n",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            config.user_agent,
+            if config.ignore_robots_txt { "ignore" } else { "obey" },
+        );
+
+        let mut file = Self::create_file(&dir, &config.warc, &basename, 0)?;
+        let warcinfo = Self::build_warcinfo(&file.path, &info_block);
+        Self::append(&mut file, &config.warc, &warcinfo)?;
+
+        Ok(Self {
+            dir,
+            config: config.warc.clone(),
+            basename,
+            info_block,
+            file: Mutex::new(file),
+            payloads: Mutex::new(HashMap::new()),
+            response_ids: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Record one fetch: its redirect hops, the request and the response or revisit
+    pub fn write_exchange(&self, url: &str, response: &FetchResponse) -> Result<()> {
+        let date = warc_date(SystemTime::now());
+        let ip = response.remote_addr.map(|addr| addr.ip().to_string());
+
+        for hop in &response.redirects {
+            let block = http_response_head("HTTP/1.1", hop.status, &hop.headers);
+            let headers = vec![
+                ("WARC-Type", "response".to_string()),
+                ("WARC-Target-URI", hop.url.clone()),
+                ("WARC-Date", date.clone()),
+                ("Content-Type", "application/http;msgtype=response".to_string()),
+                ("WARC-Block-Digest", sha1_label(&block)),
+            ];
+            self.write_record(headers, &block)?;
+        }
+
+        let target = response.final_url.as_str();
+        let payload_digest = sha1_label(&response.body);
+        let head = http_response_head(&format!("{:?}", response.version), response.status, &response.headers);
+
+        let previous = self.lock_payloads().get(&payload_digest).map(|capture| {
+            (capture.uri.clone(), capture.date.clone(), capture.record_id.clone())
+        });
+
+        let mut headers = vec![
+            ("WARC-Target-URI", target.to_string()),
+            ("WARC-Date", date.clone()),
+            ("Content-Type", "application/http;msgtype=response".to_string()),
+            ("WARC-Payload-Digest", payload_digest.clone()),
+        ];
+        if let Some(ip) = &ip {
+            headers.push(("WARC-IP-Address", ip.clone()));
+        }
+
+        let response_id = match (&response.source, previous) {
+            // The server only confirmed the cached copy: keep its 304 answer
+            (ResponseSource::Revalidated(not_modified), previous) => {
+                let head = http_response_head(&format!("{:?}", response.version), 304, not_modified);
+                headers.insert(0, ("WARC-Type", "revisit".to_string()));
+                headers.push(("WARC-Profile", NOT_MODIFIED_PROFILE.to_string()));
+                if let Some((uri, refers_date, refers_id)) = previous {
+                    headers.push(("WARC-Refers-To", refers_id));
+                    headers.push(("WARC-Refers-To-Target-URI", uri));
+                    headers.push(("WARC-Refers-To-Date", refers_date));
+                }
+                headers.push(("WARC-Block-Digest", sha1_label(&head)));
+                self.write_record(headers, &head)?
+            }
+            // Duplicate payload: keep only the headers and point at the first capture
+            (_, Some((uri, refers_date, refers_id))) if !response.body.is_empty() => {
+                headers.insert(0, ("WARC-Type", "revisit".to_string()));
+                headers.push(("WARC-Profile", REVISIT_PROFILE.to_string()));
+                headers.push(("WARC-Refers-To", refers_id));
+                headers.push(("WARC-Refers-To-Target-URI", uri));
+                headers.push(("WARC-Refers-To-Date", refers_date));
+                headers.push(("WARC-Block-Digest", sha1_label(&head)));
+                self.write_record(headers, &head)?
+            }
+            _ => {
+                let mut block = head;
+                block.extend_from_slice(&response.body);
+                headers.insert(0, ("WARC-Type", "response".to_string()));
+                headers.push(("WARC-Block-Digest", sha1_label(&block)));
+                let record_id = self.write_record(headers, &block)?;
+                self.lock_payloads().entry(payload_digest).or_insert(PayloadCapture {
+                    uri: target.to_string(),
+                    date: date.clone(),
+                    record_id: record_id.clone(),
+                });
+                record_id
+            }
+        };
+
+        // A replay from the cache sent no request, so there is nothing to record
+        if !matches!(response.source, ResponseSource::Replayed) {
+            let request = http_request_head(target, &response.request_headers);
+            let mut headers = vec![
+                ("WARC-Type", "request".to_string()),
+                ("WARC-Target-URI", target.to_string()),
+                ("WARC-Date", date),
+                ("WARC-Concurrent-To", response_id.clone()),
+                ("Content-Type", "application/http;msgtype=request".to_string()),
+                ("WARC-Block-Digest", sha1_label(&request)),
+            ];
+            if let Some(ip) = ip {
+                headers.push(("WARC-IP-Address", ip));
+            }
+            self.write_record(headers, &request)?;
+        }
+
+        let key = StandardUrlParser.normalize_url(target);
+        self.response_ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, response_id);
+        log::debug!("Archived {} as {}", url, target);
+        Ok(())
+    }
+
+    /// Record the outlinks discovered on a page as a `metadata` record
+    pub fn write_outlinks(&self, page_url: &str, links: &[String]) -> Result<()> {
+        if links.is_empty() {
+            return Ok(());
+        }
+        let base = Url::parse(page_url)?;
+        let mut block = String::new();
+        for link in links {
+            if let Ok(resolved) = base.join(link) {
+                block.push_str(&format!("outlink: {}\r\n", resolved));
+            }
+        }
+
+        let mut headers = vec![
+            ("WARC-Type", "metadata".to_string()),
+            ("WARC-Target-URI", page_url.to_string()),
+            ("WARC-Date", warc_date(SystemTime::now())),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ];
+        let key = StandardUrlParser.normalize_url(page_url);
+        if let Some(id) = self.response_ids.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+            headers.push(("WARC-Concurrent-To", id.clone()));
+        }
+        self.write_record(headers, block.as_bytes())?;
+        Ok(())
+    }
+
+    /// Flush buffered records; returns the number of WARC files written
+    pub fn finish(&self) -> Result<usize> {
+        let mut file = self.lock_file();
+        file.writer.flush()?;
+        Ok(file.index + 1)
+    }
+
+    /// Directory the WARC files are written to
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Serialize and append a record, rotating the file first if it is full.
+    /// Returns the record's `WARC-Record-ID`.
+    fn write_record(&self, headers: Vec<(&str, String)>, block: &[u8]) -> Result<String> {
+        let record_id = record_id();
+        let record = build_record(&record_id, &headers, block);
+
+        let mut file = self.lock_file();
+        if file.size >= self.config.max_file_size_mb.saturating_mul(1024 * 1024) {
+            file.writer.flush()?;
+            let mut next = Self::create_file(&self.dir, &self.config, &self.basename, file.index + 1)?;
+            let warcinfo = Self::build_warcinfo(&next.path, &self.info_block);
+            Self::append(&mut next, &self.config, &warcinfo)?;
+            log::info!("Started WARC file {}", next.path.display());
+            *file = next;
+        }
+        Self::append(&mut file, &self.config, &record)?;
+        Ok(record_id)
+    }
+
+    fn build_warcinfo(path: &Path, info_block: &str) -> Vec<u8> {
+        let filename = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let headers = [
+            ("WARC-Type", "warcinfo".to_string()),
+            ("WARC-Date", warc_date(SystemTime::now())),
+            ("WARC-Filename", filename),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ];
+        build_record(&record_id(), &headers, info_block.as_bytes())
+    }
+
+    fn create_file(dir: &Path, config: &WarcConfig, basename: &str, index: usize) -> Result<WarcFile> {
+        let extension = if config.gzip { "warc.gz" } else { "warc" };
+        let path = dir.join(format!("{}-{:05}.{}", basename, index, extension));
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(WarcFile { writer, path, index, size: 0 })
+    }
+
+    /// Write one record, as its own gzip member when compression is on
+    fn append(file: &mut WarcFile, config: &WarcConfig, record: &[u8]) -> Result<()> {
+        let bytes = if config.gzip {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(record)?;
+            encoder.finish()?
+        } else {
+            record.to_vec()
+        };
+        file.writer.write_all(&bytes)?;
+        file.size += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn lock_file(&self) -> std::sync::MutexGuard<'_, WarcFile> {
+        self.file.lock().unwrap_or_else(|e| {
+            log::warn!("Mutex poisoned in WARC writer, recovering");
+            e.into_inner()
+        })
+    }
+
+    fn lock_payloads(&self) -> std::sync::MutexGuard<'_, HashMap<String, PayloadCapture>> {
+        self.payloads.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for WarcWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.lock_file().writer.flush() {
+            log::warn!("Failed to flush WARC file: {}", e);
+        }
+    }
+}
+
+/// Assemble a record: version line, named fields, Content-Length, block and trailer
+fn build_record(record_id: &str, headers: &[(&str, String)], block: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(block.len() + 512);
+    record.extend_from_slice(b"WARC/1.1\r\n");
+    record.extend_from_slice(format!("WARC-Record-ID: {}\r\n", record_id).as_bytes());
+    for (name, value) in headers {
+        record.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    record.extend_from_slice(format!("Content-Length: {}\r\n\r\n", block.len()).as_bytes());
+    record.extend_from_slice(block);
+    record.extend_from_slice(b"\r\n\r\n");
+    record
+}
+
+/// Status line and headers of an HTTP response
+fn http_response_head(version: &str, status: u16, headers: &HeaderMap) -> Vec<u8> {
+    let reason = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("");
+    let mut head = format!("{} {} {}\r\n", version, status, reason).into_bytes();
+    for (name, value) in headers {
+        // The stored body is already de-chunked, so replay tools must not expect chunks
+        if name == "transfer-encoding" {
+            head.extend_from_slice(b"X-Crawler-Transfer-Encoding: ");
+        } else {
+            head.extend_from_slice(name.as_str().as_bytes());
+            head.extend_from_slice(b": ");
+        }
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+    head
+}
+
+/// Request line and headers of a GET request
+fn http_request_head(url: &str, headers: &HeaderMap) -> Vec<u8> {
+    let target = Url::parse(url)
+        .map(|u| match u.query() {
+            Some(query) => format!("{}?{}", u.path(), query),
+            None => u.path().to_string(),
+        })
+        .unwrap_or_else(|_| "/".to_string());
+    let mut head = format!("GET {} HTTP/1.1\r\n", target).into_bytes();
+    for (name, value) in headers {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+    head
+}
+
+/// Random (version 4) UUID as a WARC record ID
+fn record_id() -> String {
+    format!("<{}>", Uuid::new_v4().urn())
+}
+
+/// Format a timestamp as a UTC W3C-ISO8601 date (`2024-01-31T12:00:00Z`)
+fn warc_date(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Version;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn response(url: &str, body: &[u8]) -> FetchResponse {
+        FetchResponse {
+            status: 200,
+            version: Version::HTTP_11,
+            headers: HeaderMap::new(),
+            request_headers: HeaderMap::new(),
+            final_url: url.to_string(),
+            redirects: Vec::new(),
+            elapsed: Duration::ZERO,
+            remote_addr: None,
+            body: body.to_vec(),
+            source: ResponseSource::Network,
+        }
+    }
+
+    #[test]
+    fn test_warc_date() {
+        assert_eq!(warc_date(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(warc_date(UNIX_EPOCH + Duration::from_secs(951_825_600)), "2000-02-29T12:00:00Z");
+    }
+
+    #[test]
+    fn test_duplicate_payload_becomes_revisit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = CrawlerConfig::default();
+        config.warc.gzip = false;
+
+        let writer = WarcWriter::open(dir.path(), &config).unwrap();
+        writer.write_exchange("https://a.test/", &response("https://a.test/", b"same")).unwrap();
+        writer.write_exchange("https://a.test/copy", &response("https://a.test/copy", b"same")).unwrap();
+        writer.write_outlinks("https://a.test/", &["/copy".to_string()]).unwrap();
+        assert_eq!(writer.finish().unwrap(), 1);
+        drop(writer);
+
+        let path = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap().path();
+        let content = String::from_utf8(fs::read(&path).unwrap()).unwrap();
+        let types: Vec<&str> = content
+            .lines()
+            .filter_map(|line| line.strip_prefix("WARC-Type: "))
+            .collect();
+        assert_eq!(types, ["warcinfo", "response", "request", "revisit", "request", "metadata"]);
+        assert!(content.contains("WARC-Refers-To-Target-URI: https://a.test/\r\n"));
+        assert!(content.contains("outlink: https://a.test/copy\r\n"));
+    }
+}
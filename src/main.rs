@@ -7,6 +7,7 @@
 mod config;
 mod models;
 mod blacklist;
+mod filtering;
 mod html;
 mod extraction;
 mod http;
@@ -19,7 +20,7 @@ mod error;
 use std::env;
 
 use blacklist::BlacklistLoader;
-use cli::ArgParser;
+use cli::{ArgParser, UrlExplainer};
 use crawler::CrawlExecutor;
 use processing::ReportGenerator;
 use error::Result;
@@ -33,8 +34,13 @@ fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
     let (config, save_dir) = ArgParser::parse(&args)?;
 
-    // Load blacklist
-    let blacklist = BlacklistLoader::load("blacklist.toml")?;
+    // Load blacklists
+    let blacklist = BlacklistLoader::load(&config.blacklists)?;
+
+    // `explain <URL>` reports why a URL would or would not be crawled, then exits
+    if let Some(url) = ArgParser::explain_target(&args)? {
+        return UrlExplainer::explain(&config, &blacklist, url);
+    }
 
     // Execute the crawl
     let (result, elapsed) = CrawlExecutor::run_crawl_and_save(&config, save_dir, blacklist)?;
@@ -1,6 +1,6 @@
 //! Data models for the crawler
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// Information about a crawled page
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -11,7 +11,76 @@ pub struct PageInfo {
     pub content_length: Option<usize>,
     pub title: Option<String>,
     pub links: Vec<String>,
-    pub content: String,
+    /// Raw response body
+    pub content: Vec<u8>,
+    /// Decoded body for textual content types
+    pub text: Option<String>,
+    /// `<lastmod>` from the sitemap that listed this page, if any
+    pub lastmod: Option<String>,
+    /// Final URL for a redirect entry; such entries carry no body
+    pub redirect_to: Option<String>,
+    /// Canonical URL for an alias entry, a page that declared another URL
+    /// canonical; such entries carry no body either
+    pub canonical: Option<String>,
+    /// Link depth at which the page was reached
+    pub depth: usize,
+    /// Time spent fetching the page, including redirects
+    pub fetch_ms: u64,
+}
+
+impl PageInfo {
+    /// Entry for a URL that answered with a redirect to `target`
+    pub fn redirect(url: String, status_code: u16, target: String, depth: usize) -> Self {
+        Self {
+            url,
+            status_code,
+            content_type: String::new(),
+            content_length: None,
+            title: None,
+            links: Vec::new(),
+            content: Vec::new(),
+            text: None,
+            lastmod: None,
+            redirect_to: Some(target),
+            canonical: None,
+            depth,
+            fetch_ms: 0,
+        }
+    }
+
+    /// Entry for a URL whose page declared `canonical` as its canonical URL
+    pub fn alias(url: String, status_code: u16, canonical: String, depth: usize, fetch_ms: u64) -> Self {
+        Self {
+            url,
+            status_code,
+            content_type: String::new(),
+            content_length: None,
+            title: None,
+            links: Vec::new(),
+            content: Vec::new(),
+            text: None,
+            lastmod: None,
+            redirect_to: None,
+            canonical: Some(canonical),
+            depth,
+            fetch_ms,
+        }
+    }
+}
+
+/// A URL waiting in the crawl frontier
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QueuedUrl {
+    pub url: String,
+    pub depth: usize,
+    /// `<lastmod>` from the sitemap that listed this URL, if any
+    pub lastmod: Option<String>,
+}
+
+impl QueuedUrl {
+    pub fn new(url: String, depth: usize) -> Self {
+        Self { url, depth, lastmod: None }
+    }
 }
 
 /// Result of a crawl operation
@@ -19,7 +88,44 @@ pub struct PageInfo {
 pub struct CrawlResult {
     pub pages: BTreeSet<PageInfo>,
     pub errors: BTreeSet<(String, String)>,
+    /// URLs that were discovered but deliberately not crawled, with the reason
+    pub skipped: BTreeSet<(String, String)>,
+    /// Discovered URLs rejected by each filter rule, e.g. `blacklist` or `scope-domain`
+    pub filtered: BTreeMap<String, usize>,
     pub worker_stats: HashMap<usize, WorkerStats>,
+    /// Response cache counters, if a cache was used
+    pub cache_stats: Option<CacheStats>,
+    /// Peak queue depths between the crawl stages
+    pub queue_stats: QueueStats,
+    /// Why the crawl ended before its frontier was exhausted, if it did
+    pub stop_reason: Option<StopReason>,
+    /// Groups of URLs that returned byte-identical bodies
+    pub duplicates: Vec<Vec<String>>,
+}
+
+/// Why a crawl was stopped early
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// Ctrl-C or SIGTERM
+    Interrupted,
+    /// `max_pages` budget reached
+    MaxPages,
+    /// `max_bytes` budget reached
+    MaxBytes,
+    /// `max_time_secs` budget reached
+    MaxTime,
+}
+
+impl StopReason {
+    /// Name used in reports: `interrupted` or the budget setting that ended the crawl
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StopReason::Interrupted => "interrupted",
+            StopReason::MaxPages => "max_pages",
+            StopReason::MaxBytes => "max_bytes",
+            StopReason::MaxTime => "max_time_secs",
+        }
+    }
 }
 
 /// Statistics for each worker
@@ -29,4 +135,32 @@ pub struct WorkerStats {
     pub errors: usize,
     pub total_links_found: usize,
     pub processing_time_ms: u64,
+    /// Time spent waiting for responses, including politeness delays
+    pub fetch_time_ms: u64,
+    /// Time spent extracting links from HTML
+    pub parse_time_ms: u64,
+}
+
+/// Queue depth metrics for one crawl
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QueueStats {
+    /// Most URLs waiting in the frontier at once, in memory and on disk
+    pub peak_frontier: usize,
+    /// URLs that overflowed the in-memory frontier to disk
+    pub spilled_urls: usize,
+    /// Most discovered URLs waiting to enter the frontier at once
+    pub peak_pending_urls: usize,
+    /// Most crawled pages waiting to be saved at once
+    pub peak_pending_pages: usize,
+}
+
+/// Response cache counters for one crawl
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheStats {
+    /// Responses served from the cache, including revalidated ones
+    pub hits: usize,
+    /// Responses downloaded in full
+    pub misses: usize,
+    /// Cached responses confirmed unchanged by a 304
+    pub revalidated: usize,
 }
@@ -0,0 +1,344 @@
+//! Machine-readable crawl reports: JSON, JSON Lines and CSV
+
+use crate::config::ReportFormat;
+use crate::models::{CacheStats, CrawlResult, PageInfo, QueueStats, StopReason};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// One crawled page as written to a report
+#[derive(Serialize)]
+struct PageRecord<'a> {
+    url: &'a str,
+    status: u16,
+    content_type: &'a str,
+    content_length: Option<usize>,
+    title: Option<&'a str>,
+    links: usize,
+    depth: usize,
+    fetch_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redirect_to: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    canonical: Option<&'a str>,
+}
+
+impl<'a> From<&'a PageInfo> for PageRecord<'a> {
+    fn from(page: &'a PageInfo) -> Self {
+        Self {
+            url: &page.url,
+            status: page.status_code,
+            content_type: &page.content_type,
+            content_length: page.content_length.or(Some(page.content.len()).filter(|&len| len > 0)),
+            title: page.title.as_deref(),
+            links: page.links.len(),
+            depth: page.depth,
+            fetch_ms: page.fetch_ms,
+            redirect_to: page.redirect_to.as_deref(),
+            canonical: page.canonical.as_deref(),
+        }
+    }
+}
+
+/// A URL paired with an error message or skip reason
+#[derive(Serialize)]
+struct UrlNote<'a> {
+    url: &'a str,
+    reason: &'a str,
+}
+
+/// URLs that returned byte-identical bodies
+#[derive(Serialize)]
+struct DuplicateCluster<'a> {
+    urls: &'a [String],
+}
+
+/// Totals for the whole crawl
+#[derive(Serialize)]
+struct Summary {
+    /// `interrupted` or the budget that ended the crawl; absent when it completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stopped_by: Option<&'static str>,
+    pages: usize,
+    errors: usize,
+    skipped: usize,
+    /// Discovered URLs rejected per filter rule
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    filtered: BTreeMap<String, usize>,
+    duplicate_clusters: usize,
+    elapsed_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache: Option<CacheSummary>,
+    queues: QueueSummary,
+}
+
+#[derive(Serialize)]
+struct CacheSummary {
+    hits: usize,
+    misses: usize,
+    revalidated: usize,
+}
+
+impl From<CacheStats> for CacheSummary {
+    fn from(stats: CacheStats) -> Self {
+        Self { hits: stats.hits, misses: stats.misses, revalidated: stats.revalidated }
+    }
+}
+
+#[derive(Serialize)]
+struct QueueSummary {
+    peak_frontier: usize,
+    spilled_urls: usize,
+    peak_pending_urls: usize,
+    peak_pending_pages: usize,
+}
+
+impl From<QueueStats> for QueueSummary {
+    fn from(stats: QueueStats) -> Self {
+        Self {
+            peak_frontier: stats.peak_frontier,
+            spilled_urls: stats.spilled_urls,
+            peak_pending_urls: stats.peak_pending_urls,
+            peak_pending_pages: stats.peak_pending_pages,
+        }
+    }
+}
+
+impl Summary {
+    fn new(result: &CrawlResult, elapsed: Duration) -> Self {
+        Self {
+            stopped_by: result.stop_reason.map(StopReason::as_str),
+            pages: result.pages.len(),
+            errors: result.errors.len(),
+            skipped: result.skipped.len(),
+            filtered: result.filtered.clone(),
+            duplicate_clusters: result.duplicates.len(),
+            elapsed_secs: elapsed.as_secs_f64(),
+            cache: result.cache_stats.map(CacheSummary::from),
+            queues: QueueSummary::from(result.queue_stats),
+        }
+    }
+}
+
+/// Counters of one worker
+#[derive(Serialize)]
+struct WorkerRecord {
+    worker: usize,
+    pages: usize,
+    errors: usize,
+    links_found: usize,
+    processing_ms: u64,
+    fetch_ms: u64,
+    parse_ms: u64,
+}
+
+impl WorkerRecord {
+    fn all(result: &CrawlResult) -> Vec<Self> {
+        let mut workers: Vec<Self> = result
+            .worker_stats
+            .iter()
+            .map(|(&worker, stats)| Self {
+                worker,
+                pages: stats.pages_processed,
+                errors: stats.errors,
+                links_found: stats.total_links_found,
+                processing_ms: stats.processing_time_ms,
+                fetch_ms: stats.fetch_time_ms,
+                parse_ms: stats.parse_time_ms,
+            })
+            .collect();
+        workers.sort_by_key(|w| w.worker);
+        workers
+    }
+}
+
+/// Whole report as a single JSON document
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    workers: Vec<WorkerRecord>,
+    pages: Vec<PageRecord<'a>>,
+    errors: Vec<UrlNote<'a>>,
+    skipped: Vec<UrlNote<'a>>,
+    duplicates: Vec<DuplicateCluster<'a>>,
+}
+
+/// One line of a JSON Lines report
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonLine<'a> {
+    Page(PageRecord<'a>),
+    Error(UrlNote<'a>),
+    Skipped(UrlNote<'a>),
+    Duplicate(DuplicateCluster<'a>),
+    Worker(WorkerRecord),
+    Summary(Summary),
+}
+
+/// Writes complete reports after a crawl
+pub struct ReportExporter;
+
+impl ReportExporter {
+    /// Write the report for a finished crawl in one go. Use `JsonLinesReport`
+    /// directly to write a JSON Lines report while the crawl is running.
+    pub fn write(path: &Path, format: ReportFormat, result: &CrawlResult, elapsed: Duration) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create report {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        let written = match format {
+            ReportFormat::Json => Self::write_json(&mut writer, result, elapsed),
+            ReportFormat::Csv => Self::write_csv(&mut writer, result),
+            ReportFormat::JsonLines => {
+                let mut report = JsonLinesReport { writer };
+                for page in &result.pages {
+                    report.write_page(page);
+                }
+                return report.finish(result, elapsed);
+            }
+        };
+        written
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write report {}: {}", path.display(), e))
+    }
+
+    fn write_json<W: Write>(writer: &mut W, result: &CrawlResult, elapsed: Duration) -> std::io::Result<()> {
+        let report = JsonReport {
+            summary: Summary::new(result, elapsed),
+            workers: WorkerRecord::all(result),
+            pages: result.pages.iter().map(PageRecord::from).collect(),
+            errors: result.errors.iter().map(|(url, reason)| UrlNote { url, reason }).collect(),
+            skipped: result.skipped.iter().map(|(url, reason)| UrlNote { url, reason }).collect(),
+            duplicates: result.duplicates.iter().map(|urls| DuplicateCluster { urls }).collect(),
+        };
+        serde_json::to_writer_pretty(&mut *writer, &report)?;
+        writeln!(writer)
+    }
+
+    /// One row per page; errors follow as rows with only `url` and `error` set
+    fn write_csv<W: Write>(writer: &mut W, result: &CrawlResult) -> std::io::Result<()> {
+        writeln!(writer, "url,status,content_type,content_length,title,links,depth,fetch_ms,redirect_to,canonical,error")?;
+        for page in &result.pages {
+            let record = PageRecord::from(page);
+            let fields = [
+                csv_field(record.url),
+                record.status.to_string(),
+                csv_field(record.content_type),
+                record.content_length.map(|l| l.to_string()).unwrap_or_default(),
+                csv_field(record.title.unwrap_or("")),
+                record.links.to_string(),
+                record.depth.to_string(),
+                record.fetch_ms.to_string(),
+                csv_field(record.redirect_to.unwrap_or("")),
+                csv_field(record.canonical.unwrap_or("")),
+                String::new(),
+            ];
+            writeln!(writer, "{}", fields.join(","))?;
+        }
+        for (url, reason) in &result.errors {
+            writeln!(writer, "{},,,,,,,,,,{}", csv_field(url), csv_field(reason))?;
+        }
+        Ok(())
+    }
+}
+
+/// JSON Lines report written page by page, so it survives a crashed crawl
+pub struct JsonLinesReport {
+    writer: BufWriter<File>,
+}
+
+impl JsonLinesReport {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create report {}: {}", path.display(), e))?;
+        Ok(Self { writer: BufWriter::new(file) })
+    }
+
+    /// Append a page and flush it to disk
+    pub fn write_page(&mut self, page: &PageInfo) {
+        self.write_line(&JsonLine::Page(PageRecord::from(page)));
+    }
+
+    /// Append errors, skipped URLs, duplicate clusters and the summary, then flush
+    pub fn finish(&mut self, result: &CrawlResult, elapsed: Duration) -> Result<(), String> {
+        for (url, reason) in &result.errors {
+            self.write_line(&JsonLine::Error(UrlNote { url, reason }));
+        }
+        for (url, reason) in &result.skipped {
+            self.write_line(&JsonLine::Skipped(UrlNote { url, reason }));
+        }
+        for urls in &result.duplicates {
+            self.write_line(&JsonLine::Duplicate(DuplicateCluster { urls }));
+        }
+        for worker in WorkerRecord::all(result) {
+            self.write_line(&JsonLine::Worker(worker));
+        }
+        self.write_line(&JsonLine::Summary(Summary::new(result, elapsed)));
+        self.writer.flush().map_err(|e| format!("Failed to write report: {}", e))
+    }
+
+    fn write_line(&mut self, line: &JsonLine) {
+        let result = serde_json::to_writer(&mut self.writer, line)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(self.writer))
+            .and_then(|_| self.writer.flush());
+        if let Err(e) = result {
+            log::warn!("Failed to write report line: {}", e);
+        }
+    }
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(url: &str, title: &str) -> PageInfo {
+        PageInfo {
+            url: url.to_string(),
+            status_code: 200,
+            content_type: "text/html".to_string(),
+            content_length: Some(42),
+            title: Some(title.to_string()),
+            links: vec!["/a".to_string()],
+            content: Vec::new(),
+            text: None,
+            lastmod: None,
+            redirect_to: None,
+            canonical: None,
+            depth: 1,
+            fetch_ms: 12,
+        }
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let mut result = CrawlResult::default();
+        result.pages.insert(page("https://a.test/", "Hello, \"world\""));
+        result.errors.insert(("https://a.test/x".to_string(), "timeout".to_string()));
+
+        let mut out = Vec::new();
+        ReportExporter::write_csv(&mut out, &result).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[1], "https://a.test/,200,text/html,42,\"Hello, \"\"world\"\"\",1,1,12,,,");
+        assert_eq!(lines[2], "https://a.test/x,,,,,,,,,,timeout");
+    }
+
+    #[test]
+    fn test_json_line_is_tagged() {
+        let page = page("https://a.test/", "t");
+        let line = serde_json::to_string(&JsonLine::Page(PageRecord::from(&page))).unwrap();
+        assert!(line.starts_with("{\"type\":\"page\",\"url\":\"https://a.test/\""));
+        assert!(!line.contains("redirect_to"));
+        assert!(!line.contains("canonical"));
+    }
+}
@@ -1,159 +1,224 @@
-//! HTML attribute processing for link rewriting
+//! Finds URL-bearing attributes in a parsed document and works out their new values
 
-use crate::crawler::StandardUrlParser;
+use kuchiki::NodeRef;
+use kuchiki::traits::*;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use url::Url;
-use regex::Regex;
 
-use super::validation::RewriteValidator;
-use super::path_calculator::PathCalculator;
-use super::url_resolver::UrlResolver;
+use super::css_rewriter::CssRewriter;
 
-/// Processes HTML attributes (href, src, etc.) for link rewriting
-pub struct AttributeProcessor {
-    url_resolver: UrlResolver,
+/// What happens to one attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeEdit {
+    Keep,
+    Set(String),
+    Remove,
 }
 
+/// One attribute as the parser saw it
+#[derive(Debug)]
+struct Occurrence {
+    value: String,
+    edit: AttributeEdit,
+}
+
+/// Edits for the attributes of a document, in document order per tag and
+/// attribute name, so each edit applies to its own element rather than to
+/// every element with the same value
+#[derive(Debug, Default)]
+pub struct AttributeEdits {
+    occurrences: HashMap<(String, String), Vec<Occurrence>>,
+}
+
+impl AttributeEdits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the next `attr` attribute of a `tag` element
+    pub fn push(&mut self, tag: &str, attr: &str, value: &str, edit: AttributeEdit) {
+        self.occurrences
+            .entry((tag.to_string(), attr.to_string()))
+            .or_default()
+            .push(Occurrence { value: value.to_string(), edit });
+    }
+
+    /// Edit for an attribute found in the source: the first occurrence at or
+    /// after `cursor` with the same value, and `cursor` moves past it.
+    /// Occurrences skipped on the way, such as elements the parser cloned,
+    /// have no counterpart in the source.
+    pub fn next(&self, tag: &str, attr: &str, value: &str, cursor: &mut usize) -> Option<&AttributeEdit> {
+        let occurrences = self.occurrences.get(&(tag.to_string(), attr.to_string()))?;
+        let offset = occurrences.get(*cursor..)?.iter().position(|o| o.value == value)?;
+        *cursor += offset + 1;
+        Some(&occurrences[*cursor - 1].edit)
+    }
+}
+
+/// How an attribute holds its URLs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UrlKind {
+    /// The whole value is one URL
+    Url,
+    /// Comma-separated `URL descriptor` candidates
+    Srcset,
+    /// `<meta http-equiv=refresh content="5; url=...">`
+    Refresh,
+    /// Inline CSS in a `style` attribute
+    Css,
+}
+
+/// Attributes known to hold URLs, by element
+const URL_ATTRIBUTES: &[(&str, &str, UrlKind)] = &[
+    ("a", "href", UrlKind::Url),
+    ("area", "href", UrlKind::Url),
+    ("link", "href", UrlKind::Url),
+    ("link", "imagesrcset", UrlKind::Srcset),
+    ("img", "src", UrlKind::Url),
+    ("img", "srcset", UrlKind::Srcset),
+    ("source", "src", UrlKind::Url),
+    ("source", "srcset", UrlKind::Srcset),
+    ("script", "src", UrlKind::Url),
+    ("iframe", "src", UrlKind::Url),
+    ("frame", "src", UrlKind::Url),
+    ("embed", "src", UrlKind::Url),
+    ("track", "src", UrlKind::Url),
+    ("audio", "src", UrlKind::Url),
+    ("video", "src", UrlKind::Url),
+    ("video", "poster", UrlKind::Url),
+    ("input", "src", UrlKind::Url),
+    ("input", "formaction", UrlKind::Url),
+    ("button", "formaction", UrlKind::Url),
+    ("form", "action", UrlKind::Url),
+    ("object", "data", UrlKind::Url),
+    ("blockquote", "cite", UrlKind::Url),
+    ("q", "cite", UrlKind::Url),
+    ("del", "cite", UrlKind::Url),
+    ("ins", "cite", UrlKind::Url),
+    ("body", "background", UrlKind::Url),
+    ("table", "background", UrlKind::Url),
+    ("td", "background", UrlKind::Url),
+    ("th", "background", UrlKind::Url),
+    ("meta", "content", UrlKind::Refresh),
+];
+
+/// Collects attribute edits from a parsed HTML document
+pub struct AttributeProcessor;
+
 impl AttributeProcessor {
     pub fn new() -> Self {
-        Self {
-            url_resolver: UrlResolver::new(),
-        }
+        Self
     }
 
-    /// Count how many URLs can be rewritten for a specific attribute
-    pub fn count_rewriteable_urls(
-        &self,
-        content: &str,
-        attr_name: &str,
-        base_url: &Url,
-        url_to_path: &HashMap<String, PathBuf>,
-    ) -> usize {
-        let re = match Self::create_attribute_regex(attr_name) {
-            Ok(re) => re,
-            Err(_) => return 0,
-        };
+    /// Parse HTML into a DOM tree
+    pub fn parse(content: &str) -> NodeRef {
+        kuchiki::parse_html().one(content)
+    }
 
-        let count = re.captures_iter(content)
-            .filter_map(|cap| cap.get(1))
-            .map(|url_match| url_match.as_str())
-            .filter(|url_value| self.is_url_rewriteable(url_value, base_url, url_to_path))
-            .count();
+    /// Value of the first `<base href>`, which applies to the whole document
+    pub fn declared_base(document: &NodeRef) -> Option<String> {
+        let base = document.select_first("base[href]").ok()?;
+        let attributes = base.attributes.borrow();
+        attributes.get("href").map(str::to_string)
+    }
 
-        log::debug!("Found {} rewriteable {} attributes", count, attr_name);
-        count
+    /// Run every URL in a known URL-bearing attribute through `rewrite`, which
+    /// returns `None` to keep a URL as it is
+    pub fn collect_edits(&self, document: &NodeRef, rewrite: &dyn Fn(&str) -> Option<String>) -> AttributeEdits {
+        let mut edits = AttributeEdits::new();
+        Self::collect_from(document, rewrite, &mut edits);
+        edits
     }
 
-    /// Check if a single URL can be rewritten
-    fn is_url_rewriteable(&self, url_value: &str, base_url: &Url, url_to_path: &HashMap<String, PathBuf>) -> bool {
-        if !RewriteValidator::should_rewrite_url(url_value, base_url) {
-            return false;
+    fn collect_from(node: &NodeRef, rewrite: &dyn Fn(&str) -> Option<String>, edits: &mut AttributeEdits) {
+        for element in node.descendants().elements() {
+            let tag = element.name.local.to_string();
+            let attributes = element.attributes.borrow();
+            for (name, attribute) in &attributes.map {
+                let Some(kind) = Self::url_kind(&tag, &name.local) else {
+                    continue;
+                };
+                let edit = if kind == UrlKind::Refresh
+                    && !attributes.get("http-equiv").is_some_and(|v| v.trim().eq_ignore_ascii_case("refresh"))
+                {
+                    AttributeEdit::Keep
+                } else {
+                    Self::rewrite_value(kind, &attribute.value, rewrite).map_or(AttributeEdit::Keep, AttributeEdit::Set)
+                };
+                edits.push(&tag, &name.local, &attribute.value, edit);
+            }
+            // Template contents are not children, but sit here in the source
+            if let Some(contents) = &element.template_contents {
+                Self::collect_from(contents, rewrite, edits);
+            }
         }
+    }
 
-        if let Some(normalized_url) = self.url_resolver.resolve_and_normalize(base_url, url_value) {
-            url_to_path.contains_key(&normalized_url)
-        } else {
-            false
+    fn url_kind(tag: &str, attr: &str) -> Option<UrlKind> {
+        if attr == "style" {
+            return Some(UrlKind::Css);
         }
+        URL_ATTRIBUTES.iter().find(|(t, a, _)| *t == tag && *a == attr).map(|(_, _, kind)| *kind)
     }
 
-    /// Apply link replacements for a specific attribute
-    pub fn apply_attribute_replacements(
-        &self,
-        content: &str,
-        attr_name: &str,
-        base_url: &Url,
-        current_path: &Path,
-        url_to_path: &HashMap<String, PathBuf>,
-    ) -> String {
-        // Use separate patterns for single and double quotes since backreferences aren't supported
-        let patterns = [
-            format!(r#"{}="([^"]+)""#, regex::escape(attr_name)),
-            format!(r#"{}='([^']+)'"#, regex::escape(attr_name)),
-        ];
-
-        let mut result = content.to_string();
-
-        for pattern in &patterns {
-            result = self.process_pattern(&result, &pattern, attr_name, base_url, current_path, url_to_path);
+    fn rewrite_value(kind: UrlKind, value: &str, rewrite: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        match kind {
+            UrlKind::Url => rewrite(value),
+            UrlKind::Srcset => Self::rewrite_srcset(value, rewrite),
+            UrlKind::Refresh => Self::rewrite_refresh(value, rewrite),
+            UrlKind::Css => CssRewriter::rewrite(value, rewrite),
         }
+    }
 
-        result
-    }
-
-    /// Process a single regex pattern for attribute replacement
-    fn process_pattern(
-        &self,
-        content: &str,
-        pattern: &str,
-        attr_name: &str,
-        base_url: &Url,
-        current_path: &Path,
-        url_to_path: &HashMap<String, PathBuf>,
-    ) -> String {
-        let re = match Regex::new(pattern) {
-            Ok(re) => re,
-            Err(e) => {
-                log::warn!("Failed to create regex for {}: {}", attr_name, e);
-                return content.to_string();
+    /// Rewrite each candidate URL of a srcset, keeping descriptors and spacing
+    fn rewrite_srcset(value: &str, rewrite: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        let mut result = String::with_capacity(value.len());
+        let mut changed = false;
+        let mut rest = value;
+        loop {
+            let candidate = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+            result.push_str(&rest[..rest.len() - candidate.len()]);
+            if candidate.is_empty() {
+                break;
             }
-        };
-
-        re.replace_all(content, |caps: &regex::Captures| {
-            self.replace_single_match(caps, pattern, attr_name, base_url, current_path, url_to_path)
-        }).to_string()
-    }
-
-    /// Replace a single regex match
-    fn replace_single_match(
-        &self,
-        caps: &regex::Captures,
-        pattern: &str,
-        attr_name: &str,
-        base_url: &Url,
-        current_path: &Path,
-        url_to_path: &HashMap<String, PathBuf>,
-    ) -> String {
-        // Get the URL value or return the original match if not found
-        let url_value = match caps.get(1) {
-            Some(m) => m.as_str(),
-            None => return caps.get(0).map_or_else(String::new, |m| m.as_str().to_string())
-        };
-
-        let quote_char = if pattern.contains("\"") { "\"" } else { "'" };
-
-        if !RewriteValidator::should_rewrite_url(url_value, base_url) {
-            return caps.get(0).map_or_else(String::new, |m| m.as_str().to_string());
+            let url_end = candidate.find(char::is_whitespace).unwrap_or(candidate.len());
+            // A URL directly followed by a comma has no descriptor
+            let url = candidate[..url_end].trim_end_matches(',');
+            match rewrite(url) {
+                Some(rewritten) => {
+                    result.push_str(&rewritten);
+                    changed = true;
+                }
+                None => result.push_str(url),
+            }
+            let after = &candidate[url.len()..];
+            let descriptor_end = if url.len() < url_end { 0 } else { after.find(',').unwrap_or(after.len()) };
+            result.push_str(&after[..descriptor_end]);
+            rest = &after[descriptor_end..];
         }
+        changed.then_some(result)
+    }
 
-        // Resolve to absolute URL
-        let resolved_url = match base_url.join(url_value) {
-            Ok(url) => url,
-            Err(_) => {
-                log::debug!("Failed to resolve URL: {}", url_value);
-                return caps.get(0).map_or_else(String::new, |m| m.as_str().to_string());
+    /// Rewrite the URL of a refresh declaration such as `5; url='/next'`
+    fn rewrite_refresh(value: &str, rewrite: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        let separator = value.find([';', ','])?;
+        let target = value[separator + 1..].trim_start();
+        let target = match target.get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+                target[3..].trim_start().strip_prefix('=').map_or(target, str::trim_start)
             }
+            _ => target,
         };
-
-        let normalizer = StandardUrlParser;
-        let normalized_url = normalizer.normalize_url(&resolved_url.to_string());
-
-        // Check if we have a local path for this URL
-        if let Some(target_path) = url_to_path.get(&normalized_url) {
-            let relative_path = PathCalculator::calculate_relative_path(current_path, target_path);
-            log::debug!("Rewriting: {} -> {}", url_value, relative_path);
-            format!("{}={}{}{}", attr_name, quote_char, relative_path, quote_char)
-        } else {
-            log::debug!("No mapping found for: {}", normalized_url);
-            caps.get(0).map_or_else(String::new, |m| m.as_str().to_string())
+        let mut start = value.len() - target.len();
+        let mut end = value.trim_end().len();
+        if let Some(quote) = target.chars().next().filter(|c| *c == '\'' || *c == '"') {
+            start += 1;
+            end = value[start..].find(quote).map_or(end, |len| start + len);
         }
-    }
-
-    /// Create a regex for matching HTML attributes
-    fn create_attribute_regex(attr_name: &str) -> Result<Regex, regex::Error> {
-        let pattern = format!(r#"{}=["']([^"']+)["']"#, regex::escape(attr_name));
-        Regex::new(&pattern)
+        if end <= start {
+            return None;
+        }
+        let rewritten = rewrite(&value[start..end])?;
+        Some(format!("{}{}{}", &value[..start], rewritten, &value[end..]))
     }
 }
 
@@ -161,4 +226,51 @@ impl Default for AttributeProcessor {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(url: &str) -> Option<String> {
+        url.strip_prefix('/').map(|path| format!("local/{}", path))
+    }
+
+    #[test]
+    fn rewrites_srcset_candidates_and_refresh_targets() {
+        assert_eq!(
+            AttributeProcessor::rewrite_srcset("/a.png 1x, https://cdn.test/b.png 2x,/c.png", &local).as_deref(),
+            Some("local/a.png 1x, https://cdn.test/b.png 2x,local/c.png")
+        );
+        assert_eq!(AttributeProcessor::rewrite_srcset("https://cdn.test/b.png 2x", &local), None);
+        assert_eq!(
+            AttributeProcessor::rewrite_refresh("5; URL='/next.html'", &local).as_deref(),
+            Some("5; URL='local/next.html'")
+        );
+        assert_eq!(AttributeProcessor::rewrite_refresh("0;/x", &local).as_deref(), Some("0;local/x"));
+        assert_eq!(AttributeProcessor::rewrite_refresh("30", &local), None);
+    }
+
+    #[test]
+    fn only_refresh_meta_tags_carry_urls() {
+        let document = AttributeProcessor::parse(
+            r#"<meta name="x" content="0; url=/a"><meta http-equiv="Refresh" content="0; url=/a"><video poster="/p.jpg"></video><template><p style="background: url('/b.png')"></template>"#,
+        );
+        let edits = AttributeProcessor::new().collect_edits(&document, &local);
+        let mut cursor = 0;
+        assert_eq!(edits.next("meta", "content", "0; url=/a", &mut cursor), Some(&AttributeEdit::Keep));
+        assert_eq!(
+            edits.next("meta", "content", "0; url=/a", &mut cursor),
+            Some(&AttributeEdit::Set("0; url=local/a".to_string()))
+        );
+        assert_eq!(edits.next("meta", "content", "0; url=/a", &mut cursor), None);
+        assert_eq!(
+            edits.next("video", "poster", "/p.jpg", &mut 0),
+            Some(&AttributeEdit::Set("local/p.jpg".to_string()))
+        );
+        assert_eq!(
+            edits.next("p", "style", "background: url('/b.png')", &mut 0),
+            Some(&AttributeEdit::Set("background: url('local/b.png')".to_string()))
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Rewrites `url()` and `@import` targets inside CSS
+
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Comments, `url(...)` with any quoting, and `@import "..."`
+static CSS_URL_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// Changes the URLs of a stylesheet, `<style>` block or `style` attribute,
+/// leaving the rest of the CSS as it was
+pub struct CssRewriter;
+
+impl CssRewriter {
+    /// Run every `url()` and `@import` target in `css` through `rewrite`, which
+    /// returns `None` to keep a URL. Returns `None` if nothing changed.
+    pub fn rewrite(css: &str, rewrite: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        let regex = CSS_URL_REGEX.get_or_init(|| {
+            Regex::new(concat!(
+                r#"(?s)/\*.*?\*/"#,
+                r#"|(?i:url)\(\s*(?:"([^"]*)"|'([^']*)'|([^"'()\s]*))\s*\)"#,
+                r#"|@(?i:import)\s*(?:"([^"]*)"|'([^']*)')"#,
+            ))
+            .expect("css url regex is valid")
+        });
+        let mut changed = false;
+        let rewritten = regex.replace_all(css, |caps: &Captures| {
+            let whole = &caps[0];
+            let Some((index, target)) = caps.iter().enumerate().skip(1).find_map(|(i, m)| Some((i, m?))) else {
+                // A comment
+                return whole.to_string();
+            };
+            let Some(new_url) = rewrite(target.as_str()).filter(|new_url| new_url != target.as_str()) else {
+                return whole.to_string();
+            };
+            changed = true;
+            let start = caps.get(0).map_or(0, |m| m.start());
+            let (before, after) = (&whole[..target.start() - start], &whole[target.end() - start..]);
+            // Unquoted url() values cannot hold quotes, parentheses or whitespace
+            let quoted = index == 3 && new_url.contains(|c: char| c.is_whitespace() || "\"'()".contains(c));
+            if quoted {
+                format!("{}\"{}\"{}", before, new_url.replace('"', "\\\""), after)
+            } else {
+                format!("{}{}{}", before, new_url, after)
+            }
+        });
+        changed.then(|| rewritten.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(url: &str) -> Option<String> {
+        url.strip_prefix('/').map(|path| format!("../{}", path))
+    }
+
+    #[test]
+    fn rewrites_urls_and_imports_but_not_comments() {
+        let css = "@import '/base.css';\n@IMPORT url(/print.css) print;\n\
+                   /* url(/old.png) */ body { background: URL( \"/bg.png\" ) no-repeat; }\n\
+                   @font-face { src: url(/f.woff2) format('woff2'), url(data:font/woff;base64,AA); }";
+        assert_eq!(
+            CssRewriter::rewrite(css, &local).as_deref(),
+            Some(
+                "@import '../base.css';\n@IMPORT url(../print.css) print;\n\
+                 /* url(/old.png) */ body { background: URL( \"../bg.png\" ) no-repeat; }\n\
+                 @font-face { src: url(../f.woff2) format('woff2'), url(data:font/woff;base64,AA); }"
+            )
+        );
+        assert_eq!(CssRewriter::rewrite("a { color: red }", &local), None);
+    }
+}
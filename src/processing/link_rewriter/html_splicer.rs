@@ -0,0 +1,264 @@
+//! Applies attribute edits to the original HTML source, leaving every other byte as it was
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use super::attribute_processor::{AttributeEdit, AttributeEdits};
+
+/// Elements whose content is text rather than markup. The parser runs with
+/// scripting enabled, so `<noscript>` holds text too.
+const RAW_TEXT_ELEMENTS: &[&str] =
+    &["script", "style", "textarea", "title", "xmp", "iframe", "noembed", "noframes", "noscript"];
+
+/// Where one attribute sits inside a start tag
+struct AttributeSpan {
+    name: String,
+    /// Start of the whitespace before the name, so a removed attribute leaves no gap
+    start: usize,
+    /// Value without its quotes
+    value_start: usize,
+    value_end: usize,
+    /// End of the attribute, including a closing quote
+    end: usize,
+    quote: Option<u8>,
+    has_value: bool,
+}
+
+/// Rewrites attribute values in place by scanning start tags in the source
+pub struct HtmlSplicer;
+
+impl HtmlSplicer {
+    /// Apply `edits` to the attributes of every start tag in `content`, in
+    /// order, and `rewrite_text` to the content of each `<style>` and
+    /// `<noscript>` element, given its tag name. Returns the new source and
+    /// the number of attributes and elements changed.
+    pub fn apply(
+        content: &str,
+        edits: &AttributeEdits,
+        rewrite_text: &dyn Fn(&str, &str) -> Option<String>,
+    ) -> (String, usize) {
+        let mut output = String::with_capacity(content.len());
+        let (mut pos, mut copied, mut applied) = (0, 0, 0);
+        let mut cursors: HashMap<(String, String), usize> = HashMap::new();
+
+        while let Some(offset) = content[pos..].find('<') {
+            let open = pos + offset;
+            let rest = &content[open..];
+            if rest.starts_with("<!--") {
+                pos = content[open + 4..].find("-->").map_or(content.len(), |end| open + 4 + end + 3);
+                continue;
+            }
+            if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+                // End tags, doctypes and processing instructions carry no URLs
+                let markup = rest.starts_with("</") || rest.starts_with("<!") || rest.starts_with("<?");
+                pos = if markup { rest.find('>').map_or(content.len(), |end| open + end + 1) } else { open + 1 };
+                continue;
+            }
+
+            let name_len = rest[1..]
+                .bytes()
+                .take_while(|b| !b.is_ascii_whitespace() && *b != b'/' && *b != b'>')
+                .count();
+            let tag = rest[1..1 + name_len].to_ascii_lowercase();
+            let (spans, tag_end) = Self::scan_attributes(content, open + 1 + name_len);
+            let mut names = HashSet::new();
+            for span in &spans {
+                // The parser keeps only the first of repeated attributes
+                if !names.insert(span.name.as_str()) {
+                    continue;
+                }
+                let value = Self::decode_entities(&content[span.value_start..span.value_end]);
+                let cursor = cursors.entry((tag.clone(), span.name.clone())).or_default();
+                let Some(edit) = edits.next(&tag, &span.name, &value, cursor) else {
+                    continue;
+                };
+                match edit {
+                    AttributeEdit::Keep => continue,
+                    AttributeEdit::Set(rewritten) => {
+                        output.push_str(&content[copied..span.value_start]);
+                        let quote = span.quote.unwrap_or(b'"') as char;
+                        if !span.has_value {
+                            output.push('=');
+                        }
+                        if span.quote.is_none() {
+                            output.push(quote);
+                        }
+                        output.push_str(&Self::escape(rewritten, quote));
+                        if span.quote.is_none() {
+                            output.push(quote);
+                        }
+                        copied = span.value_end;
+                    }
+                    AttributeEdit::Remove => {
+                        output.push_str(&content[copied..span.start]);
+                        copied = span.end;
+                    }
+                }
+                applied += 1;
+            }
+
+            pos = tag_end;
+            if RAW_TEXT_ELEMENTS.contains(&tag.as_str()) {
+                pos = Self::find_end_tag(content, tag_end, &tag);
+                if (tag == "style" || tag == "noscript")
+                    && let Some(text) = rewrite_text(&tag, &content[tag_end..pos])
+                {
+                    output.push_str(&content[copied..tag_end]);
+                    output.push_str(&text);
+                    copied = pos;
+                    applied += 1;
+                }
+            }
+        }
+        output.push_str(&content[copied..]);
+        (output, applied)
+    }
+
+    /// Attributes of a start tag whose name ends at `pos`, and the end of the tag
+    fn scan_attributes(content: &str, mut pos: usize) -> (Vec<AttributeSpan>, usize) {
+        let bytes = content.as_bytes();
+        let len = bytes.len();
+        let skip_whitespace = |mut i: usize| {
+            while i < len && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            i
+        };
+        let mut spans = Vec::new();
+        loop {
+            let start = pos;
+            while pos < len && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'/') {
+                pos += 1;
+            }
+            if pos >= len {
+                return (spans, len);
+            }
+            if bytes[pos] == b'>' {
+                return (spans, pos + 1);
+            }
+
+            let name_start = pos;
+            pos += 1;
+            while pos < len && !bytes[pos].is_ascii_whitespace() && !matches!(bytes[pos], b'/' | b'>' | b'=') {
+                pos += 1;
+            }
+            let name = content[name_start..pos].to_ascii_lowercase();
+
+            let after_name = skip_whitespace(pos);
+            let mut span = AttributeSpan {
+                name,
+                start,
+                value_start: pos,
+                value_end: pos,
+                end: pos,
+                quote: None,
+                has_value: false,
+            };
+            if after_name < len && bytes[after_name] == b'=' {
+                let value_start = skip_whitespace(after_name + 1);
+                span.has_value = true;
+                match bytes.get(value_start) {
+                    Some(&quote) if quote == b'"' || quote == b'\'' => {
+                        span.quote = Some(quote);
+                        span.value_start = value_start + 1;
+                        span.value_end = content[value_start + 1..]
+                            .find(quote as char)
+                            .map_or(len, |end| value_start + 1 + end);
+                        span.end = (span.value_end + 1).min(len);
+                    }
+                    _ => {
+                        span.value_start = value_start;
+                        span.value_end = content[value_start..]
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .map_or(len, |end| value_start + end);
+                        span.end = span.value_end;
+                    }
+                }
+                pos = span.end;
+            }
+            spans.push(span);
+        }
+    }
+
+    /// Start of the end tag that closes a raw text element
+    fn find_end_tag(content: &str, pos: usize, tag: &str) -> usize {
+        content[pos..]
+            .to_ascii_lowercase()
+            .find(&format!("</{}", tag))
+            .map_or(content.len(), |end| pos + end)
+    }
+
+    /// Decode the character references a URL attribute is likely to contain
+    fn decode_entities(value: &str) -> Cow<'_, str> {
+        if !value.contains('&') {
+            return Cow::Borrowed(value);
+        }
+        let mut decoded = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(amp) = rest.find('&') {
+            decoded.push_str(&rest[..amp]);
+            rest = &rest[amp..];
+            let reference = rest
+                .find(';')
+                .filter(|end| *end <= 10)
+                .and_then(|end| Self::character(&rest[1..end]).map(|c| (c, end)));
+            match reference {
+                Some((c, end)) => {
+                    decoded.push(c);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    decoded.push('&');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        decoded.push_str(rest);
+        Cow::Owned(decoded)
+    }
+
+    fn character(name: &str) -> Option<char> {
+        match name {
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            _ => {
+                let number = name.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        }
+    }
+
+    fn escape(value: &str, quote: char) -> String {
+        let escaped_quote = if quote == '"' { "&quot;" } else { "&#39;" };
+        value.replace('&', "&amp;").replace(quote, escaped_quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_tags_but_not_text_scripts_or_comments() {
+        let html = "<A HREF=/a title=x>/a</A><!-- <a href=/a> --><script>s = '<a href=\"/a\">';</script>\
+                    <img src=\"/a?x=1&amp;y=2\"><base target=_top href='/root/'>";
+        let mut edits = AttributeEdits::new();
+        edits.push("a", "href", "/a", AttributeEdit::Set("a.html".to_string()));
+        edits.push("img", "src", "/a?x=1&y=2", AttributeEdit::Set("a&b.png".to_string()));
+        edits.push("base", "href", "/root/", AttributeEdit::Remove);
+        let (rewritten, applied) = HtmlSplicer::apply(html, &edits, &|_, _| None);
+        assert_eq!(applied, 3);
+        assert_eq!(
+            rewritten,
+            "<A HREF=\"a.html\" title=x>/a</A><!-- <a href=/a> --><script>s = '<a href=\"/a\">';</script>\
+             <img src=\"a&amp;b.png\"><base target=_top>"
+        );
+    }
+}
@@ -4,17 +4,30 @@ pub mod url_resolver;
 pub mod path_calculator;
 pub mod validation;
 pub mod attribute_processor;
+pub mod css_rewriter;
+pub mod html_splicer;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+use crate::crawler::UrlScope;
 
 use url_resolver::UrlResolver;
-use attribute_processor::AttributeProcessor;
+use attribute_processor::{AttributeEdit, AttributeProcessor};
+use kuchiki::NodeRef;
+use css_rewriter::CssRewriter;
+use html_splicer::HtmlSplicer;
+use path_calculator::PathCalculator;
+use validation::RewriteValidator;
 
 /// Main LinkRewriter struct with simplified, focused functionality
 pub struct LinkRewriter {
     url_resolver: UrlResolver,
     attribute_processor: AttributeProcessor,
+    /// Crawl scope, so links to other hosts that were fetched are rewritten too
+    scope: Option<Arc<UrlScope>>,
 }
 
 impl LinkRewriter {
@@ -23,77 +36,168 @@ impl LinkRewriter {
         Self {
             url_resolver: UrlResolver::new(),
             attribute_processor: AttributeProcessor::new(),
+            scope: None,
         }
     }
 
-    /// Rewrites links in HTML content to point to local files
+    /// Also rewrite absolute links to other hosts that `scope` lets the crawl fetch
+    pub fn with_scope(mut self, scope: Arc<UrlScope>) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Rewrites links in HTML content to point to local files. The document is
+    /// parsed to find URL-bearing attributes; only their values change in the
+    /// saved source, along with `url()` targets in `<style>` blocks and `style`
+    /// attributes. A `<base href>` is removed, since links no longer resolve
+    /// against it, and links it applied to that were not saved become absolute.
     pub fn rewrite_links(
         &self,
         current_url: &str,
         content: &str,
         url_to_path: &HashMap<String, PathBuf>,
     ) -> String {
+        let Some((current_path, page_url)) = self.locate(current_url, url_to_path) else {
+            return content.to_string();
+        };
+
+        let document = AttributeProcessor::parse(content);
+        let declared_base = AttributeProcessor::declared_base(&document);
+        let base_url = declared_base
+            .as_deref()
+            .and_then(|href| page_url.join(href).ok())
+            .unwrap_or(page_url);
+        let rewrite = |value: &str| {
+            self.rewrite_url(value, &base_url, &current_path, url_to_path, declared_base.is_some())
+        };
+        let (rewritten_content, total_replacements) =
+            self.splice(content, &document, &rewrite, declared_base.as_deref());
+        log::debug!("Made {} total replacements", total_replacements);
+        rewritten_content
+    }
+
+    /// Rewrites `url()` and `@import` targets in a stylesheet to point to local
+    /// files. They resolve against the stylesheet's own URL.
+    pub fn rewrite_css_links(
+        &self,
+        current_url: &str,
+        content: &str,
+        url_to_path: &HashMap<String, PathBuf>,
+    ) -> String {
+        let Some((current_path, css_url)) = self.locate(current_url, url_to_path) else {
+            return content.to_string();
+        };
+        let rewrite = |value: &str| self.rewrite_url(value, &css_url, &current_path, url_to_path, false);
+        CssRewriter::rewrite(content, &rewrite).unwrap_or_else(|| content.to_string())
+    }
+
+    /// Point links that were already rewritten to one local file at another.
+    /// `moved` pairs the old target file with the new one, both as mapped paths.
+    pub fn retarget_links(&self, content: &str, current_path: &Path, moved: &[(PathBuf, PathBuf)]) -> String {
+        let links = Self::moved_links(current_path, moved);
+        if links.is_empty() {
+            return content.to_string();
+        }
+        let retarget = |value: &str| {
+            links.iter().find(|(old_link, _)| old_link == value).map(|(_, new_link)| new_link.clone())
+        };
+        self.splice(content, &AttributeProcessor::parse(content), &retarget, None).0
+    }
+
+    /// Like `retarget_links`, for the `url()` and `@import` targets of a stylesheet
+    pub fn retarget_css_links(&self, content: &str, current_path: &Path, moved: &[(PathBuf, PathBuf)]) -> String {
+        let links = Self::moved_links(current_path, moved);
+        let retarget = |value: &str| {
+            links.iter().find(|(old_link, _)| old_link == value).map(|(_, new_link)| new_link.clone())
+        };
+        CssRewriter::rewrite(content, &retarget).unwrap_or_else(|| content.to_string())
+    }
+
+    /// Old and new relative links from `current_path` for each moved target
+    fn moved_links(current_path: &Path, moved: &[(PathBuf, PathBuf)]) -> Vec<(String, String)> {
+        moved
+            .iter()
+            .map(|(old_target, new_target)| {
+                (
+                    PathCalculator::calculate_relative_path(current_path, old_target),
+                    PathCalculator::calculate_relative_path(current_path, new_target),
+                )
+            })
+            .filter(|(old_link, new_link)| old_link != new_link)
+            .collect()
+    }
+
+    /// Run the URLs of `content` through `rewrite`, including those in `<style>`
+    /// blocks and in the markup of `<noscript>` elements, removing the
+    /// `<base href>` with the value `base_href`
+    fn splice(
+        &self,
+        content: &str,
+        document: &NodeRef,
+        rewrite: &dyn Fn(&str) -> Option<String>,
+        base_href: Option<&str>,
+    ) -> (String, usize) {
+        let mut edits = self.attribute_processor.collect_edits(document, rewrite);
+        if let Some(href) = base_href {
+            edits.push("base", "href", href, AttributeEdit::Remove);
+        }
+        let rewrite_text = |tag: &str, text: &str| match tag {
+            "style" => CssRewriter::rewrite(text, rewrite),
+            _ => {
+                let (markup, replacements) = self.splice(text, &AttributeProcessor::parse(text), rewrite, None);
+                (replacements > 0).then_some(markup)
+            }
+        };
+        HtmlSplicer::apply(content, &edits, &rewrite_text)
+    }
+
+    /// Local file and parsed URL of the document being rewritten
+    fn locate(&self, current_url: &str, url_to_path: &HashMap<String, PathBuf>) -> Option<(PathBuf, Url)> {
         log::debug!("Processing: {}", current_url);
         log::debug!("Available mappings: {}", url_to_path.len());
 
         // Find current path
-        let current_path = match self.url_resolver.find_current_path(current_url, url_to_path) {
-            Ok(path) => path,
-            Err(_) => return content.to_string(),
-        };
+        let current_path = self.url_resolver.find_current_path(current_url, url_to_path).ok()?;
 
         // Parse base URL
-        let base_url = match self.url_resolver.parse_url(current_url) {
+        let url = match self.url_resolver.parse_url(current_url) {
             Ok(url) => url,
             Err(e) => {
                 log::debug!("{}", e);
-                return content.to_string();
+                return None;
             }
         };
 
         log::debug!("Current file path: {}", current_path.display());
-
-        // Process attributes
-        let mut rewritten_content = content.to_string();
-        let mut total_replacements = 0;
-
-        // Process href attributes
-        total_replacements += self.process_attribute("href", &mut rewritten_content, &base_url, &current_path, url_to_path);
-        
-        // Process src attributes
-        total_replacements += self.process_attribute("src", &mut rewritten_content, &base_url, &current_path, url_to_path);
-
-        log::debug!("Made {} total replacements", total_replacements);
-        rewritten_content
+        Some((current_path, url))
     }
 
-    /// Process a single attribute type (href or src)
-    fn process_attribute(
+    /// Local path for a URL that was saved. With a `<base>` in effect, other
+    /// URLs become absolute so they still reach the original site.
+    fn rewrite_url(
         &self,
-        attr_name: &str,
-        content: &mut String,
-        base_url: &url::Url,
-        current_path: &std::path::Path,
+        url_value: &str,
+        base_url: &Url,
+        current_path: &Path,
         url_to_path: &HashMap<String, PathBuf>,
-    ) -> usize {
-        // Count potential replacements
-        let count = self.attribute_processor.count_rewriteable_urls(
-            content,
-            attr_name,
-            base_url,
-            url_to_path,
-        );
-
-        // Apply replacements
-        *content = self.attribute_processor.apply_attribute_replacements(
-            content,
-            attr_name,
-            base_url,
-            current_path,
-            url_to_path,
-        );
-
-        count
+        absolutize: bool,
+    ) -> Option<String> {
+        if !RewriteValidator::should_rewrite_url(url_value, base_url, self.scope.as_deref()) {
+            return None;
+        }
+        let normalized_url = self.url_resolver.resolve_and_normalize(base_url, url_value)?;
+        match url_to_path.get(&normalized_url) {
+            Some(target_path) => {
+                let relative_path = PathCalculator::calculate_relative_path(current_path, target_path);
+                log::debug!("Rewriting: {} -> {}", url_value, relative_path);
+                Some(relative_path)
+            }
+            None if absolutize => base_url.join(url_value).ok().map(String::from),
+            None => {
+                log::debug!("No mapping found for: {}", normalized_url);
+                None
+            }
+        }
     }
 }
 
@@ -101,4 +205,101 @@ impl Default for LinkRewriter {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retarget_links_moves_only_matching_attributes() {
+        let html = r#"<a href="../old/index.html">x</a><img src='../old/index.html'><p>../old/index.html</p>"#;
+        let moved = [(PathBuf::from("site/old/index.html"), PathBuf::from("site/new/index.html"))];
+        let retargeted = LinkRewriter::new().retarget_links(html, Path::new("site/page/index.html"), &moved);
+        assert_eq!(
+            retargeted,
+            r#"<a href="../new/index.html">x</a><img src='../new/index.html'><p>../old/index.html</p>"#
+        );
+    }
+
+    #[test]
+    fn test_rewrite_links_edits_only_url_attributes() {
+        let parser = crate::crawler::StandardUrlParser;
+        let url_to_path: HashMap<String, PathBuf> = [
+            ("https://a.test/docs/page", "site/docs/page/index.html"),
+            ("https://a.test/docs/a.html", "site/docs/a.html"),
+            ("https://a.test/img/small.png", "site/img/small.png"),
+        ]
+        .into_iter()
+        .map(|(url, path)| (parser.normalize_url(url), PathBuf::from(path)))
+        .collect();
+        let html = "<html><head><base href=\"/docs/\"></head><body>\n\
+                    <a href=a.html>A</a> <a href='other.html'>B</a>\n\
+                    <img srcset=\"/img/small.png 1x, /img/big.png 2x\">\n\
+                    <pre>href=\"a.html\"</pre><script>var u = \"<a href='a.html'>\";</script>\n\
+                    </body></html>";
+        let rewritten = LinkRewriter::new().rewrite_links("https://a.test/docs/page", html, &url_to_path);
+        assert_eq!(
+            rewritten,
+            "<html><head><base></head><body>\n\
+             <a href=\"../a.html\">A</a> <a href='https://a.test/docs/other.html'>B</a>\n\
+             <img srcset=\"../../img/small.png 1x, https://a.test/img/big.png 2x\">\n\
+             <pre>href=\"a.html\"</pre><script>var u = \"<a href='a.html'>\";</script>\n\
+             </body></html>"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_links_edits_each_element_on_its_own() {
+        let parser = crate::crawler::StandardUrlParser;
+        let url_to_path: HashMap<String, PathBuf> = [
+            ("https://a.test/page", "site/page/index.html"),
+            ("https://a.test/next", "site/next/index.html"),
+            ("https://a.test/img/x.png", "site/img/x.png"),
+        ]
+        .into_iter()
+        .map(|(url, path)| (parser.normalize_url(url), PathBuf::from(path)))
+        .collect();
+        let html = "<head><meta name=\"description\" content=\"0; url=/next\">\
+                    <meta http-equiv=\"refresh\" content=\"0; url=/next\"></head>\
+                    <body><noscript><img src=\"/img/x.png\"><a href=/next>next</a></noscript>\
+                    <img src=\"/img/x.png\"></body>";
+        let rewritten = LinkRewriter::new().rewrite_links("https://a.test/page", html, &url_to_path);
+        assert_eq!(
+            rewritten,
+            "<head><meta name=\"description\" content=\"0; url=/next\">\
+             <meta http-equiv=\"refresh\" content=\"0; url=../next/index.html\"></head>\
+             <body><noscript><img src=\"../img/x.png\"><a href=\"../next/index.html\">next</a></noscript>\
+             <img src=\"../img/x.png\"></body>"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_css_links_resolves_against_the_stylesheet() {
+        let parser = crate::crawler::StandardUrlParser;
+        let url_to_path: HashMap<String, PathBuf> = [
+            ("https://a.test/assets/css/site.css", "site/assets/css/site.css"),
+            ("https://a.test/assets/img/bg.png", "site/assets/img/bg.png"),
+            ("https://a.test/assets/css/print.css", "site/assets/css/print.css"),
+        ]
+        .into_iter()
+        .map(|(url, path)| (parser.normalize_url(url), PathBuf::from(path)))
+        .collect();
+        let css = "@import 'https://a.test/assets/css/print.css';\n\
+                   body { background: url(/assets/img/bg.png) } .x { background: url(/gone.png) }";
+        let rewritten = LinkRewriter::new().rewrite_css_links("https://a.test/assets/css/site.css", css, &url_to_path);
+        assert_eq!(
+            rewritten,
+            "@import 'print.css';\n\
+             body { background: url(../img/bg.png) } .x { background: url(/gone.png) }"
+        );
+
+        let html = "<style>p { background: url('assets/img/bg.png') }</style><p style=\"background:url(/assets/img/bg.png)\">";
+        let mut url_to_path = url_to_path;
+        url_to_path.insert(parser.normalize_url("https://a.test/page"), PathBuf::from("site/page/index.html"));
+        let rewritten = LinkRewriter::new().rewrite_links("https://a.test/page", html, &url_to_path);
+        assert_eq!(
+            rewritten,
+            "<style>p { background: url('../assets/img/bg.png') }</style><p style=\"background:url(../assets/img/bg.png)\">"
+        );
+    }
+}
@@ -64,7 +64,7 @@ impl UrlResolver {
     /// Resolve a relative URL against a base URL and normalize it
     pub fn resolve_and_normalize(&self, base_url: &Url, relative_url: &str) -> Option<String> {
         if let Ok(resolved_url) = base_url.join(relative_url) {
-            Some(self.normalizer.normalize_url(&resolved_url.to_string()))
+            Some(self.normalizer.normalize_url(resolved_url.as_ref()))
         } else {
             None
         }
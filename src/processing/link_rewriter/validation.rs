@@ -2,12 +2,15 @@
 
 use url::Url;
 
+use crate::crawler::{ScopeVerdict, UrlScope};
+
 /// Validates URLs to determine if they should be rewritten
 pub struct RewriteValidator;
 
 impl RewriteValidator {
-    /// Check if a URL should be rewritten (skip external, javascript:, etc.)
-    pub fn should_rewrite_url(url_value: &str, base_url: &Url) -> bool {
+    /// Check if a URL should be rewritten (skip external, javascript:, etc.).
+    /// Absolute URLs on other hosts are rewritten only if `scope` lets them be fetched.
+    pub fn should_rewrite_url(url_value: &str, base_url: &Url, scope: Option<&UrlScope>) -> bool {
         // Skip empty URLs
         if url_value.trim().is_empty() {
             return false;
@@ -23,9 +26,10 @@ impl RewriteValidator {
             return false;
         }
 
-        // For absolute HTTP URLs, check if they're from the same host
+        // For absolute HTTP URLs, check if they're from the same host or in scope
         if Self::is_absolute_http_url(url_value) {
-            return Self::is_same_host(url_value, base_url);
+            return Self::is_same_host(url_value, base_url)
+                || scope.is_some_and(|scope| scope.check(url_value) != ScopeVerdict::Out);
         }
 
         // Relative URLs should be rewritten
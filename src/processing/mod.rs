@@ -2,6 +2,8 @@
 
 pub mod link_rewriter;
 pub mod report;
+pub mod export;
 
 pub use link_rewriter::LinkRewriter;
 pub use report::ReportGenerator;
+pub use export::{JsonLinesReport, ReportExporter};
@@ -2,7 +2,7 @@
 
 use std::time::Duration;
 use log::{info, warn, error};
-use crate::models::CrawlResult;
+use crate::models::{CrawlResult, StopReason};
 
 /// ReportGenerator handles formatting and displaying crawler results
 pub struct ReportGenerator;
@@ -13,6 +13,9 @@ impl ReportGenerator {
         Self::print_summary(result, elapsed);
         Self::print_worker_stats(result);
         Self::print_crawled_pages(result);
+        Self::print_skipped(result);
+        Self::print_filtered(result);
+        Self::print_duplicates(result);
         Self::print_errors(result);
     }
 
@@ -23,28 +26,44 @@ impl ReportGenerator {
         let elapsed_secs = elapsed.as_secs_f64();
         let pages_per_second = pages_count as f64 / elapsed_secs;
 
-        info!("\nCrawl complete: {} pages, {} errors in {:.2} seconds",
-            pages_count, errors_count, elapsed_secs);
+        let outcome = match result.stop_reason {
+            None => "complete".to_string(),
+            Some(StopReason::Interrupted) => "interrupted (partial results)".to_string(),
+            Some(reason) => format!("stopped by the {} budget (partial results)", reason.as_str()),
+        };
+        info!("\nCrawl {}: {} pages, {} errors, {} skipped in {:.2} seconds",
+            outcome, pages_count, errors_count, result.skipped.len(), elapsed_secs);
         info!("Pages per second: {:.2}", pages_per_second);
+        if let Some(cache) = &result.cache_stats {
+            info!("Cache: {} hits ({} revalidated), {} misses",
+                cache.hits, cache.revalidated, cache.misses);
+        }
+        let queues = &result.queue_stats;
+        info!("Peak queue depths: {} frontier URLs ({} spilled to disk), {} discovered URLs, {} pages awaiting save",
+            queues.peak_frontier, queues.spilled_urls, queues.peak_pending_urls, queues.peak_pending_pages);
     }
 
     /// Print worker statistics
     fn print_worker_stats(result: &CrawlResult) {
         info!("\nWorker Statistics:");
-        info!("{:<8} {:<15} {:<10} {:<15} {:<15}",
-            "Worker", "Pages", "Errors", "Links Found", "Avg Time (ms)");
-        info!("{:-<60}", "");
+        info!("{:<8} {:<10} {:<10} {:<15} {:<15} {:<15} {:<15}",
+            "Worker", "Pages", "Errors", "Links Found", "Avg Time (ms)", "Avg Fetch (ms)", "Avg Parse (ms)");
+        info!("{:-<90}", "");
 
-        for (worker_id, stats) in &result.worker_stats {
-            let avg_time = if stats.pages_processed > 0 {
-                stats.processing_time_ms as f64 / stats.pages_processed as f64
-            } else {
-                0.0
+        let mut workers: Vec<_> = result.worker_stats.iter().collect();
+        workers.sort_by_key(|(worker_id, _)| **worker_id);
+        for (worker_id, stats) in workers {
+            let average = |total_ms: u64| {
+                if stats.pages_processed > 0 {
+                    total_ms as f64 / stats.pages_processed as f64
+                } else {
+                    0.0
+                }
             };
 
-            info!("{:<8} {:<15} {:<10} {:<15} {:<15.2}",
-                worker_id, stats.pages_processed, stats.errors,
-                stats.total_links_found, avg_time);
+            info!("{:<8} {:<10} {:<10} {:<15} {:<15.2} {:<15.2} {:<15.2}",
+                worker_id, stats.pages_processed, stats.errors, stats.total_links_found,
+                average(stats.processing_time_ms), average(stats.fetch_time_ms), average(stats.parse_time_ms));
         }
     }
 
@@ -52,8 +71,50 @@ impl ReportGenerator {
     fn print_crawled_pages(result: &CrawlResult) {
         info!("\nCrawled Pages:");
         for page in &result.pages {
-            info!("{} → {} links, status {}",
-                page.url, page.links.len(), page.status_code);
+            if let Some(target) = &page.redirect_to {
+                info!("{} → redirect {} to {}", page.url, page.status_code, target);
+                continue;
+            }
+            if let Some(canonical) = &page.canonical {
+                info!("{} → alias of {}", page.url, canonical);
+                continue;
+            }
+            match &page.lastmod {
+                Some(lastmod) => info!("{} → {} links, status {}, lastmod {}",
+                    page.url, page.links.len(), page.status_code, lastmod),
+                None => info!("{} → {} links, status {}",
+                    page.url, page.links.len(), page.status_code),
+            }
+        }
+    }
+
+    /// Print URLs that were deliberately not crawled
+    fn print_skipped(result: &CrawlResult) {
+        if !result.skipped.is_empty() {
+            info!("\nSkipped URLs:");
+            for (u, reason) in &result.skipped {
+                info!("  {} → {}", u, reason);
+            }
+        }
+    }
+
+    /// Print how many discovered URLs each filter rule rejected
+    fn print_filtered(result: &CrawlResult) {
+        if !result.filtered.is_empty() {
+            info!("\nFiltered URLs by rule:");
+            for (rule, count) in &result.filtered {
+                info!("  {:<20} {}", rule, count);
+            }
+        }
+    }
+
+    /// Print groups of URLs that returned identical bodies
+    fn print_duplicates(result: &CrawlResult) {
+        if !result.duplicates.is_empty() {
+            info!("\nDuplicate content ({} groups):", result.duplicates.len());
+            for urls in &result.duplicates {
+                info!("  {}", urls.join(" = "));
+            }
         }
     }
 